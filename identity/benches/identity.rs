@@ -0,0 +1,165 @@
+//! Benchmarks for MAYO key generation, key expansion, signing and verification for every
+//! variant, and for the GF(16) primitives underneath them.
+//!
+//! Run with `cargo bench`; a single group with e.g. `cargo bench -- sign/`. Every
+//! operation is reported in operations per second, and the `*_bytes` groups report the
+//! same operations in bytes per second of key, signature or message processed.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use identity::gf::gf16_mul;
+use identity::keygen::{compact_key_gen, expand_pk, expand_sk};
+use identity::params::MayoParams;
+use identity::rng::{CtrDrbg, RandomSource};
+use identity::sign::sign_message;
+use identity::solver::{sample_solution_ct, solve_linear_system};
+use identity::types::{GFElement, GFMatrix, GFVector, Message};
+use identity::verify::verify_signature;
+
+/// Message sizes used for the per-byte signing and verification throughput.
+const MESSAGE_SIZES: [usize; 3] = [32, 1024, 64 * 1024];
+
+fn random_elements(rng: &mut CtrDrbg, len: usize) -> GFVector {
+    let mut bytes = vec![0u8; len];
+    rng.fill_bytes(&mut bytes).unwrap();
+    bytes.iter().map(|b| GFElement(b & 0x0F)).collect()
+}
+
+fn bench_keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    group.throughput(Throughput::Elements(1));
+    for params in MayoParams::all() {
+        group.bench_function(BenchmarkId::new("compact_key_gen", params.name()), |b| {
+            b.iter(|| compact_key_gen(black_box(&params)).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("keygen_bytes");
+    for params in MayoParams::all() {
+        group.throughput(Throughput::Bytes(params.cpk_bytes() as u64));
+        group.bench_function(BenchmarkId::new("compact_key_gen", params.name()), |b| {
+            b.iter(|| compact_key_gen(black_box(&params)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_expansion(c: &mut Criterion) {
+    for (group_name, per_byte) in [("expand", false), ("expand_bytes", true)] {
+        let mut group = c.benchmark_group(group_name);
+        for params in MayoParams::all() {
+            let (csk, cpk) = compact_key_gen(&params).unwrap();
+
+            group.throughput(if per_byte { Throughput::Bytes(params.esk_bytes() as u64) } else { Throughput::Elements(1) });
+            group.bench_function(BenchmarkId::new("expand_sk", params.name()), |b| {
+                b.iter(|| expand_sk(black_box(&csk)).unwrap())
+            });
+
+            group.throughput(if per_byte { Throughput::Bytes(params.epk_bytes() as u64) } else { Throughput::Elements(1) });
+            group.bench_function(BenchmarkId::new("expand_pk", params.name()), |b| {
+                b.iter(|| expand_pk(black_box(&cpk)).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_sign_verify(c: &mut Criterion) {
+    let message = Message(b"cubix transaction".to_vec());
+
+    let mut group = c.benchmark_group("sign");
+    group.throughput(Throughput::Elements(1));
+    for params in MayoParams::all() {
+        let (csk, _cpk) = compact_key_gen(&params).unwrap();
+        let esk = expand_sk(&csk).unwrap();
+        group.bench_function(BenchmarkId::new("sign_message", params.name()), |b| {
+            b.iter(|| sign_message(black_box(&esk), black_box(&message)).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("verify");
+    group.throughput(Throughput::Elements(1));
+    for params in MayoParams::all() {
+        let (csk, cpk) = compact_key_gen(&params).unwrap();
+        let signature = sign_message(&expand_sk(&csk).unwrap(), &message).unwrap();
+        let epk = expand_pk(&cpk).unwrap();
+        group.bench_function(BenchmarkId::new("verify_signature", params.name()), |b| {
+            b.iter(|| assert!(verify_signature(black_box(&epk), black_box(&message), black_box(&signature)).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_sign_verify_bytes(c: &mut Criterion) {
+    // Signing and verification hash the whole message once, so the cost per message
+    // byte only shows for long messages; MAYO2 has the cheapest map to evaluate.
+    let params = MayoParams::mayo2();
+    let (csk, cpk) = compact_key_gen(&params).unwrap();
+    let esk = expand_sk(&csk).unwrap();
+    let epk = expand_pk(&cpk).unwrap();
+
+    let mut group = c.benchmark_group("sign_verify_bytes");
+    for size in MESSAGE_SIZES {
+        let message = Message(vec![0xA5; size]);
+        let signature = sign_message(&esk, &message).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("sign_message", size), &message, |b, message| {
+            b.iter(|| sign_message(black_box(&esk), message).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("verify_signature", size), &message, |b, message| {
+            b.iter(|| verify_signature(black_box(&epk), message, &signature).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_gf16(c: &mut Criterion) {
+    const BATCH: usize = 1024;
+    let mut rng = CtrDrbg::new(&[1u8; 48]);
+    let a = random_elements(&mut rng, BATCH);
+    let b = random_elements(&mut rng, BATCH);
+
+    let mut group = c.benchmark_group("gf16");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("gf16_mul", |bench| {
+        bench.iter(|| {
+            a.iter().zip(&b).fold(GFElement(0), |acc, (x, y)| GFElement(acc.0 ^ gf16_mul(*x, *y).0))
+        })
+    });
+    group.finish();
+}
+
+fn bench_solver(c: &mut Criterion) {
+    // The m x k*o systems solved while signing, one per variant.
+    let mut rng = CtrDrbg::new(&[2u8; 48]);
+    let mut group = c.benchmark_group("solver");
+    group.throughput(Throughput::Elements(1));
+    for params in MayoParams::all() {
+        let p = params.variant();
+        let (rows, cols) = (p.m, p.k * p.o);
+        let a = GFMatrix::new_with_data(rows, cols, random_elements(&mut rng, rows * cols));
+        let y = random_elements(&mut rng, rows);
+        let r = random_elements(&mut rng, cols);
+
+        group.bench_function(BenchmarkId::new("solve_linear_system", params.name()), |b| {
+            b.iter(|| solve_linear_system(black_box(&a), black_box(&y)).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("sample_solution_ct", params.name()), |b| {
+            b.iter(|| sample_solution_ct(black_box(&a), black_box(&y), black_box(&r)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_keygen,
+    bench_expansion,
+    bench_sign_verify,
+    bench_sign_verify_bytes,
+    bench_gf16,
+    bench_solver
+);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! `decode_gf_elements` on arbitrary bytes and element counts: it must either fail or
+//! return exactly the requested number of nibbles, which re-encode to the input prefix.
+
+#![no_main]
+
+use identity::codec::{decode_gf_elements, encode_gf_elements};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&count, bytes)) = data.split_first() else { return };
+    let count = count as usize * 2;
+    if let Ok(elements) = decode_gf_elements(bytes, count) {
+        assert_eq!(elements.len(), count);
+        assert!(elements.iter().all(|e| e.0 < 16));
+        assert_eq!(encode_gf_elements(&elements), bytes[..count / 2]);
+    }
+});
@@ -0,0 +1,17 @@
+//! `decode_o_matrix` on arbitrary bytes for every MAYO variant: it must either fail or
+//! decode to a matrix that re-encodes to the first `o_bytes` bytes of the input.
+
+#![no_main]
+
+use identity::codec::{decode_o_matrix, encode_o_matrix};
+use identity::params::MayoParams;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, bytes)) = data.split_first() else { return };
+    let variants = MayoParams::all();
+    let params = variants[selector as usize % variants.len()].variant();
+    if let Ok(decoded) = decode_o_matrix(bytes, params) {
+        assert_eq!(encode_o_matrix(&decoded, params).unwrap(), bytes[..params.o_bytes]);
+    }
+});
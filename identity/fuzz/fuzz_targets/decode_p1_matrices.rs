@@ -0,0 +1,17 @@
+//! `decode_p1_matrices` on arbitrary bytes for every MAYO variant: it must either fail or
+//! decode to matrices that re-encode to the input.
+
+#![no_main]
+
+use identity::codec::{decode_p1_matrices, encode_p1_matrices};
+use identity::params::MayoParams;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, bytes)) = data.split_first() else { return };
+    let variants = MayoParams::all();
+    let params = variants[selector as usize % variants.len()].variant();
+    if let Ok(decoded) = decode_p1_matrices(bytes, params) {
+        assert_eq!(encode_p1_matrices(&decoded, params).unwrap(), bytes);
+    }
+});
@@ -0,0 +1,18 @@
+//! `decode_s_vector` on arbitrary bytes for every MAYO variant: it must either fail or
+//! return n * k nibbles that re-encode to the input prefix.
+
+#![no_main]
+
+use identity::codec::{decode_s_vector, encode_s_vector};
+use identity::params::MayoParams;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, bytes)) = data.split_first() else { return };
+    let variants = MayoParams::all();
+    let params = variants[selector as usize % variants.len()].variant();
+    if let Ok(s) = decode_s_vector(bytes, params) {
+        assert_eq!(s.len(), params.n * params.k);
+        assert_eq!(encode_s_vector(&s, params), bytes[..params.s_bytes()]);
+    }
+});
@@ -0,0 +1,181 @@
+// End-to-end tests of the wasm build. Run `npm run build:node` first, then `npm test`.
+// The package is loaded through its own name, so these go through the "exports" map
+// exactly as a dependent such as cubix-js would.
+const { test } = require("node:test");
+const assert = require("node:assert/strict");
+
+const identity = require("@cubix/identity");
+const {
+    CompactPublicKey,
+    CompactSecretKey,
+    Message,
+    Signature,
+    SigningKey,
+    VerifyingKey,
+} = identity;
+
+// (cpk, csk, sig) sizes from the MAYO specification.
+const SIZES = {
+    mayo1: [1420, 24, 454],
+    mayo2: [4912, 24, 186],
+    mayo3: [2986, 32, 681],
+    mayo5: [5554, 40, 964],
+};
+
+const text = (s) => new Message(new TextEncoder().encode(s));
+
+function assertIdentityError(fn, code) {
+    assert.throws(fn, (err) => {
+        assert.ok(err instanceof Error);
+        assert.equal(err.name, "IdentityError");
+        assert.equal(err.code, code);
+        return true;
+    });
+}
+
+for (const [variant, [cpkBytes, cskBytes, sigBytes]] of Object.entries(SIZES)) {
+    test(`${variant}: keypair, sign and verify`, () => {
+        const pair = identity.keypair(variant);
+        assert.equal(pair.variant, variant);
+        assert.equal(pair.publicKey.toBytes().length, cpkBytes);
+        assert.equal(pair.secretKey.toBytes().length, cskBytes);
+
+        const message = text(`cube face of ${variant}`);
+        const signature = identity.signDetached(pair.secretKey, message);
+        assert.equal(signature.variant, variant);
+        assert.equal(signature.toBytes().length, sigBytes);
+        assert.equal(identity.verify(pair.publicKey, message, signature), true);
+        assert.equal(identity.verify(pair.publicKey, text("another face"), signature), false);
+    });
+}
+
+test("signing and verifying contexts match the one-shot functions", () => {
+    const pair = identity.keypair("mayo2");
+    const signingKey = new SigningKey(pair.secretKey);
+    const verifyingKey = new VerifyingKey(pair.publicKey);
+    assert.equal(signingKey.variant, "mayo2");
+    assert.equal(verifyingKey.variant, "mayo2");
+
+    for (const body of ["tx 1", "tx 2", ""]) {
+        const message = text(body);
+        const signature = signingKey.sign(message);
+        assert.equal(verifyingKey.verify(message, signature), true);
+        assert.equal(identity.verify(pair.publicKey, message, signature), true);
+    }
+    signingKey.free();
+    verifyingKey.free();
+});
+
+test("signed messages open only under the signing key", () => {
+    const pair = identity.keypair("mayo1");
+    const message = text("signed message");
+    const signed = identity.sign(pair.secretKey, message);
+    assert.ok(signed instanceof Uint8Array);
+    assert.deepEqual(identity.open(pair.publicKey, signed).toBytes(), message.toBytes());
+
+    const other = identity.keypair("mayo1");
+    assert.equal(identity.open(other.publicKey, signed), undefined);
+    assertIdentityError(() => identity.open(pair.publicKey, signed.slice(0, 10)), "INVALID_LENGTH");
+});
+
+test("addresses are derived from the public key", () => {
+    const seed = new Uint8Array(32).fill(7);
+    const first = identity.keypairFromSeed("mayo2", seed, "m/0/0");
+    const again = identity.keypairFromSeed("mayo2", seed, "m/0/0");
+    const sibling = identity.keypairFromSeed("mayo2", seed, "m/0/1");
+
+    assert.match(first.address, /^cbx1/);
+    assert.equal(first.address, first.publicKey.address);
+    assert.equal(first.address, again.address);
+    assert.notEqual(first.address, sibling.address);
+    assert.equal(identity.isValidAddress(first.address), true);
+    const typo = first.address.slice(0, -1) + (first.address.endsWith("q") ? "p" : "q");
+    assert.equal(identity.isValidAddress(typo), false);
+    assert.match(first.publicKey.fingerprint, /^[0-9a-f]+$/);
+});
+
+test("generated mnemonics restore the same identity", () => {
+    const phrase = identity.generateMnemonic(24);
+    assert.equal(phrase.split(" ").length, 24);
+    const first = identity.keypairFromMnemonic("mayo1", phrase, "", "m/0/0");
+    const again = identity.keypairFromMnemonic("mayo1", phrase, "", "m/0/0");
+    assert.equal(first.address, again.address);
+
+    const words = phrase.split(" ");
+    const misspelled = [...words.slice(0, -1), words[23] + "x"].join(" ");
+    assertIdentityError(() => identity.keypairFromMnemonic("mayo1", misspelled, "", "m/0/0"), "INVALID_MNEMONIC");
+    assertIdentityError(() => identity.generateMnemonic(13), "INVALID_INPUT");
+});
+
+test("keys and signatures survive every serialization", () => {
+    const pair = identity.keypair("mayo3");
+    const signature = identity.signDetached(pair.secretKey, text("serialize me"));
+
+    for (const [Type, value] of [
+        [CompactSecretKey, pair.secretKey],
+        [CompactPublicKey, pair.publicKey],
+        [Signature, signature],
+    ]) {
+        const bytes = value.toBytes();
+        assert.deepEqual(new Type("mayo3", bytes).toBytes(), bytes);
+        assert.deepEqual(Type.fromEnvelope(value.toEnvelope()).toBytes(), bytes);
+        assert.deepEqual(Type.fromHex(value.toHex()).toBytes(), bytes);
+        assert.deepEqual(Type.fromBase64(value.toBase64()).toBytes(), bytes);
+        assert.deepEqual(Type.fromPem(value.toPem()).toBytes(), bytes);
+        assert.equal(Type.fromPem(value.toPem()).variant, "mayo3");
+    }
+    assert.deepEqual([...pair.publicKey.toEnvelope().slice(0, 4)], [..."CBXI"].map((c) => c.charCodeAt(0)));
+    assert.match(pair.publicKey.toPem(), /^-----BEGIN /);
+});
+
+test("errors carry stable codes", () => {
+    assertIdentityError(() => identity.keypair("mayo4"), "UNKNOWN_VARIANT");
+    assertIdentityError(() => new CompactPublicKey("mayo1", new Uint8Array(3)), "INVALID_LENGTH");
+    assertIdentityError(() => CompactPublicKey.fromHex("zz"), "INVALID_ENCODING");
+
+    const mayo1 = identity.keypair("mayo1");
+    const mayo2 = identity.keypair("mayo2");
+    const signature = identity.signDetached(mayo1.secretKey, text("x"));
+    assertIdentityError(() => identity.verify(mayo2.publicKey, text("x"), signature), "VARIANT_MISMATCH");
+});
+
+test("batches report the failing entries", () => {
+    const a = identity.keypair("mayo2");
+    const b = identity.keypair("mayo2");
+    const messageA = text("a");
+    const messageB = text("b");
+    const sigA = identity.signDetached(a.secretKey, messageA);
+    const sigB = identity.signDetached(b.secretKey, messageB);
+
+    // verifyBatch takes ownership of the objects in its arrays, so pass copies.
+    const copy = (value) => value.constructor.fromEnvelope(value.toEnvelope());
+    const report = identity.verifyBatch(
+        [a.publicKey, b.publicKey, a.publicKey],
+        [new Message(messageA.toBytes()), new Message(messageB.toBytes()), new Message(messageB.toBytes())],
+        [copy(sigA), copy(sigB), copy(sigA)],
+    );
+    assert.equal(report.valid, false);
+    assert.deepEqual([...report.failed], [2]);
+    assertIdentityError(() => identity.verifyBatch([a.publicKey], [], []), "INVALID_INPUT");
+});
+
+test("spacetime hashes are domain separated", () => {
+    const pair = identity.keypair("mayo1");
+    const data = new TextEncoder().encode("payload");
+    assert.equal(identity.hashMessage("cubix/tx", data).length, 64);
+    assert.notDeepEqual(identity.hashMessage("cubix/tx", data), identity.hashMessage("cubix/block", data));
+    assert.notDeepEqual(identity.hashPublicKeyAt(pair.publicKey, 1n), identity.hashPublicKeyAt(pair.publicKey, 2n));
+    assert.deepEqual(identity.hashPublicKey(pair.publicKey), identity.hashPublicKey(pair.publicKey));
+    assert.equal(identity.hashCompactSecretKey(pair.secretKey).length, 64);
+});
+
+test("keystores unlock only with their password", () => {
+    const pair = identity.keypair("mayo1");
+    const json = identity.createKeystore(pair.secretKey, "correct horse");
+    assert.equal(JSON.parse(json).address, pair.address);
+    assert.deepEqual(identity.unlockKeystore(json, "correct horse").toBytes(), pair.secretKey.toBytes());
+    assertIdentityError(() => identity.unlockKeystore(json, "wrong"), "DECRYPTION_FAILED");
+
+    const rotated = identity.changeKeystorePassword(json, "correct horse", "battery staple");
+    assert.deepEqual(identity.unlockKeystore(rotated, "battery staple").toBytes(), pair.secretKey.toBytes());
+});
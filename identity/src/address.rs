@@ -0,0 +1,419 @@
+//! Public key fingerprints and Cubix addresses.
+//!
+//! The fingerprint of a compact public key is `Blake2b-256("cubix/fingerprint" ||
+//! variant_id || cpk)`, so that the same bytes under two variants never share one. An
+//! address is the short, checksummed form used in transactions: the payload
+//! `version || variant_id || fingerprint[..20]` encoded as Bech32m (BIP 350) under the
+//! human-readable part "cbx", e.g. `cbx1qq...`. The Bech32m checksum detects any error
+//! in up to four characters, and the version byte leaves room for later schemes.
+//! Version 1 is the `PolicyAddress` of a k-of-n group (see `multisig`), whose payload is
+//! `1 || k || policy id` with the full 32-byte id.
+
+use std::fmt;
+use std::str::FromStr;
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use crate::error::IdentityError;
+use crate::params::MayoParams;
+use crate::types::CompactPublicKey;
+
+/// Human-readable part of every Cubix address.
+pub const ADDRESS_HRP: &str = "cbx";
+/// The address version produced by `Address::from_public_key`.
+pub const ADDRESS_VERSION: u8 = 0;
+/// Number of fingerprint bytes kept in an address.
+pub const ADDRESS_HASH_BYTES: usize = 20;
+/// Length of a fingerprint.
+pub const FINGERPRINT_BYTES: usize = 32;
+/// The address version of policy addresses.
+pub const POLICY_ADDRESS_VERSION: u8 = 1;
+/// Length of a multisig policy id, kept whole in a policy address.
+pub const POLICY_ID_BYTES: usize = 32;
+/// Largest number of keys in a multisig policy, and so the largest policy threshold.
+pub const MAX_POLICY_KEYS: usize = 16;
+
+/// Length of a key address payload: version, variant id and hash.
+const PAYLOAD_BYTES: usize = 2 + ADDRESS_HASH_BYTES;
+/// Length of a policy address payload: version, threshold and policy id.
+const POLICY_PAYLOAD_BYTES: usize = 2 + POLICY_ID_BYTES;
+/// Bech32 alphabet, indexed by 5-bit value.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// The constant a valid Bech32m checksum leaves in the polymod.
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const CHECKSUM_CHARS: usize = 6;
+/// Longest Bech32 string allowed by BIP 173.
+const MAX_BECH32_LEN: usize = 90;
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATORS: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATORS.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let bytes = hrp.as_bytes();
+    bytes.iter().map(|c| c >> 5).chain([0]).chain(bytes.iter().map(|c| c & 31)).collect()
+}
+
+/// Regroups `data` from `from`-bit to `to`-bit values. With `pad`, a last partial group
+/// is zero-padded; without, leftover bits must be zero padding of fewer than `from` bits.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return None;
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Encodes 8-bit `payload` as a Bech32m string with human-readable part `hrp`.
+fn bech32m_encode(hrp: &str, payload: &[u8]) -> String {
+    let data = convert_bits(payload, 8, 5, true).expect("8-bit input always converts");
+    let checksum = polymod(hrp_expand(hrp).into_iter().chain(data.iter().copied()).chain([0; CHECKSUM_CHARS])) ^ BECH32M_CONST;
+    let mut s = String::with_capacity(hrp.len() + 1 + data.len() + CHECKSUM_CHARS);
+    s.push_str(hrp);
+    s.push('1');
+    s.extend(data.iter().map(|&d| CHARSET[d as usize] as char));
+    s.extend((0..CHECKSUM_CHARS).map(|i| CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char));
+    s
+}
+
+/// Decodes a Bech32m string into its lowercase human-readable part and 5-bit data,
+/// checksum removed.
+fn bech32m_decode(s: &str) -> Result<(String, Vec<u8>), IdentityError> {
+    if s.len() > MAX_BECH32_LEN || !s.bytes().all(|b| (33..=126).contains(&b)) {
+        return Err(IdentityError::InvalidEncoding("Address contains invalid characters or is too long"));
+    }
+    if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(IdentityError::InvalidEncoding("Address mixes upper and lower case"));
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1').ok_or(IdentityError::InvalidEncoding("Address has no separator"))?;
+    let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+    if hrp.is_empty() || data.len() < CHECKSUM_CHARS {
+        return Err(IdentityError::InvalidEncoding("Address is too short"));
+    }
+    let data = data
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&d| d == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(IdentityError::InvalidEncoding("Address contains invalid characters or is too long"))?;
+    if polymod(hrp_expand(hrp).into_iter().chain(data.iter().copied())) != BECH32M_CONST {
+        return Err(IdentityError::InvalidEncoding("Address checksum is invalid"));
+    }
+    Ok((hrp.to_string(), data[..data.len() - CHECKSUM_CHARS].to_vec()))
+}
+
+/// The Blake2b-256 fingerprint of a compact public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; FINGERPRINT_BYTES]);
+
+impl Fingerprint {
+    pub fn of(cpk: &CompactPublicKey) -> Self {
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update(b"cubix/fingerprint");
+        hasher.update([cpk.params().id()]);
+        hasher.update(cpk.as_bytes());
+        Fingerprint(hasher.finalize().into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; FINGERPRINT_BYTES] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    /// Lowercase hex.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// A Cubix address: the version, the MAYO variant and a 20-byte public key hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    version: u8,
+    params: MayoParams,
+    hash: [u8; ADDRESS_HASH_BYTES],
+}
+
+impl Address {
+    /// Returns the current-version address of `cpk`.
+    pub fn from_public_key(cpk: &CompactPublicKey) -> Self {
+        let mut hash = [0u8; ADDRESS_HASH_BYTES];
+        hash.copy_from_slice(&Fingerprint::of(cpk).as_bytes()[..ADDRESS_HASH_BYTES]);
+        Address { version: ADDRESS_VERSION, params: *cpk.params(), hash }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the MAYO variant of the key behind the address.
+    pub fn params(&self) -> &MayoParams {
+        &self.params
+    }
+
+    pub fn hash(&self) -> &[u8; ADDRESS_HASH_BYTES] {
+        &self.hash
+    }
+
+    /// Returns true if `cpk` is the key behind this address.
+    pub fn matches(&self, cpk: &CompactPublicKey) -> bool {
+        Address::from_public_key(cpk) == *self
+    }
+}
+
+/// Encodes an address payload under the "cbx" prefix.
+fn encode_address_payload(payload: &[u8]) -> String {
+    bech32m_encode(ADDRESS_HRP, payload)
+}
+
+/// Decodes any "cbx" address into its payload, checking the checksum and prefix. The
+/// payload is never empty; its version and length are left to the caller, since they
+/// differ between key and policy addresses.
+fn decode_address_payload(s: &str) -> Result<Vec<u8>, IdentityError> {
+    let (hrp, data) = bech32m_decode(s)?;
+    if hrp != ADDRESS_HRP {
+        return Err(IdentityError::InvalidEncoding("Address does not start with \"cbx1\""));
+    }
+    let payload = convert_bits(&data, 5, 8, false).ok_or(IdentityError::InvalidEncoding("Address payload has invalid padding"))?;
+    if payload.is_empty() {
+        return Err(IdentityError::InvalidLength("Address payload has incorrect length"));
+    }
+    Ok(payload)
+}
+
+impl fmt::Display for Address {
+    /// The Bech32m encoding, always lowercase.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut payload = [0u8; PAYLOAD_BYTES];
+        payload[0] = self.version;
+        payload[1] = self.params.id();
+        payload[2..].copy_from_slice(&self.hash);
+        f.write_str(&encode_address_payload(&payload))
+    }
+}
+
+impl FromStr for Address {
+    type Err = IdentityError;
+
+    /// Parses and validates an address: Bech32m checksum, "cbx" prefix, length, a
+    /// known version and a known MAYO variant. Upper-case addresses are accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let payload = decode_address_payload(s)?;
+        if payload[0] != ADDRESS_VERSION {
+            return Err(IdentityError::InvalidEncoding("Unsupported address version"));
+        }
+        if payload.len() != PAYLOAD_BYTES {
+            return Err(IdentityError::InvalidLength("Address payload has incorrect length"));
+        }
+        let params = MayoParams::from_id(payload[1]).ok_or(IdentityError::UnknownVariant)?;
+        let mut hash = [0u8; ADDRESS_HASH_BYTES];
+        hash.copy_from_slice(&payload[2..]);
+        Ok(Address { version: payload[0], params, hash })
+    }
+}
+
+/// The address of a k-of-n multisig policy: its threshold and the full policy id.
+/// `MultisigPolicy::address` builds one, and `matches` checks it against a policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolicyAddress {
+    threshold: u8,
+    id: [u8; POLICY_ID_BYTES],
+}
+
+impl PolicyAddress {
+    pub(crate) fn new(threshold: u8, id: [u8; POLICY_ID_BYTES]) -> Self {
+        PolicyAddress { threshold, id }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    /// The id of the policy behind this address.
+    pub fn id(&self) -> &[u8; POLICY_ID_BYTES] {
+        &self.id
+    }
+}
+
+impl fmt::Display for PolicyAddress {
+    /// The Bech32m encoding, always lowercase.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut payload = [0u8; POLICY_PAYLOAD_BYTES];
+        payload[0] = POLICY_ADDRESS_VERSION;
+        payload[1] = self.threshold;
+        payload[2..].copy_from_slice(&self.id);
+        f.write_str(&encode_address_payload(&payload))
+    }
+}
+
+impl FromStr for PolicyAddress {
+    type Err = IdentityError;
+
+    /// Parses and validates a policy address: Bech32m checksum, "cbx" prefix, version 1,
+    /// length and a threshold between 1 and `MAX_POLICY_KEYS`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let payload = decode_address_payload(s)?;
+        if payload[0] != POLICY_ADDRESS_VERSION {
+            return Err(IdentityError::InvalidEncoding("Address is not a policy address"));
+        }
+        if payload.len() != POLICY_PAYLOAD_BYTES {
+            return Err(IdentityError::InvalidLength("Address payload has incorrect length"));
+        }
+        if payload[1] == 0 || payload[1] as usize > MAX_POLICY_KEYS {
+            return Err(IdentityError::InvalidEncoding("Policy address has an invalid threshold"));
+        }
+        let mut id = [0u8; POLICY_ID_BYTES];
+        id.copy_from_slice(&payload[2..]);
+        Ok(PolicyAddress { threshold: payload[1], id })
+    }
+}
+
+impl CompactPublicKey {
+    /// The Cubix address of the key, as a string.
+    pub fn address(&self) -> String {
+        Address::from_public_key(self).to_string()
+    }
+
+    /// The fingerprint of the key, in hex.
+    pub fn fingerprint(&self) -> String {
+        Fingerprint::of(self).to_string()
+    }
+}
+
+/// Returns true if `s` is a well-formed Cubix address, of a single key or of a
+/// multisig policy.
+pub fn is_valid_address(s: &str) -> bool {
+    s.parse::<Address>().is_ok() || s.parse::<PolicyAddress>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::compact_key_gen;
+
+    #[test]
+    fn test_bech32m_vectors() {
+        // Valid Bech32m strings from BIP 350.
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            assert!(bech32m_decode(valid).is_ok(), "{} should decode", valid);
+        }
+        // The same strings with a wrong checksum, mixed case, or no separator.
+        for invalid in ["a1lqfn3q", "A1lqfn3a", "lqfn3a", "1lqfn3a", "a1lqfn"] {
+            assert!(bech32m_decode(invalid).is_err(), "{} should not decode", invalid);
+        }
+
+        let (hrp, data) = bech32m_decode("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx").unwrap();
+        assert_eq!(hrp, "abcdef");
+        assert_eq!(data, (0..32).rev().collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_address_roundtrip_and_validation() {
+        let (_csk, cpk) = compact_key_gen(&MayoParams::mayo2()).unwrap();
+        let address = Address::from_public_key(&cpk);
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("cbx1qq"));
+        assert_eq!(encoded.len(), 4 + 36 + CHECKSUM_CHARS);
+        assert_eq!(encoded.parse::<Address>(), Ok(address));
+        assert_eq!(encoded.to_uppercase().parse::<Address>(), Ok(address));
+        assert_eq!(address.params(), &MayoParams::mayo2());
+        assert_eq!(address.version(), ADDRESS_VERSION);
+        assert_eq!(&address.hash()[..], &Fingerprint::of(&cpk).as_bytes()[..ADDRESS_HASH_BYTES]);
+        assert!(address.matches(&cpk));
+        assert_eq!(cpk.address(), encoded);
+        assert_eq!(cpk.fingerprint(), Fingerprint::of(&cpk).to_string());
+        assert!(!address.matches(&compact_key_gen(&MayoParams::mayo2()).unwrap().1));
+
+        // Any single changed character breaks the checksum.
+        for i in 4..encoded.len() {
+            let mut chars: Vec<char> = encoded.chars().collect();
+            chars[i] = if chars[i] == 'q' { 'p' } else { 'q' };
+            assert!(!is_valid_address(&chars.into_iter().collect::<String>()));
+        }
+        assert!(!is_valid_address("0x1f2e3d4c"));
+        assert!(!is_valid_address(&bech32m_encode("btc", &[0u8; PAYLOAD_BYTES])));
+    }
+
+    #[test]
+    fn test_address_rejects_unknown_version_variant_and_length() {
+        let mut payload = [0u8; PAYLOAD_BYTES];
+        payload[1] = MayoParams::mayo1().id();
+        assert!(bech32m_encode(ADDRESS_HRP, &payload).parse::<Address>().is_ok());
+
+        payload[0] = 1;
+        assert_eq!(bech32m_encode(ADDRESS_HRP, &payload).parse::<Address>(),
+                   Err(IdentityError::InvalidEncoding("Unsupported address version")));
+        payload[0] = ADDRESS_VERSION;
+        payload[1] = 4;
+        assert_eq!(bech32m_encode(ADDRESS_HRP, &payload).parse::<Address>(), Err(IdentityError::UnknownVariant));
+        assert_eq!(bech32m_encode(ADDRESS_HRP, &payload[..PAYLOAD_BYTES - 1]).parse::<Address>(),
+                   Err(IdentityError::InvalidLength("Address payload has incorrect length")));
+    }
+
+    #[test]
+    fn test_policy_address_checks_length_and_threshold() {
+        let address = PolicyAddress::new(2, [0xAB; POLICY_ID_BYTES]);
+        let encoded = address.to_string();
+        assert_eq!(encoded.parse::<PolicyAddress>(), Ok(address));
+        assert!(is_valid_address(&encoded));
+
+        // The payload must carry the whole policy id: a prefix of it, even one as long
+        // as a key address hash, is rejected.
+        let mut payload = vec![POLICY_ADDRESS_VERSION, 2];
+        payload.extend_from_slice(&address.id()[..ADDRESS_HASH_BYTES]);
+        let short = bech32m_encode(ADDRESS_HRP, &payload);
+        assert_eq!(short.parse::<PolicyAddress>(), Err(IdentityError::InvalidLength("Address payload has incorrect length")));
+        assert!(!is_valid_address(&short));
+
+        for threshold in [0, MAX_POLICY_KEYS as u8 + 1] {
+            let encoded = PolicyAddress::new(threshold, [0xAB; POLICY_ID_BYTES]).to_string();
+            assert_eq!(encoded.parse::<PolicyAddress>(), Err(IdentityError::InvalidEncoding("Policy address has an invalid threshold")));
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_and_address_are_pinned() {
+        // Guards the scheme against accidental changes, which would orphan every address
+        // already handed out.
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![7u8; MayoParams::mayo1().cpk_bytes()]).unwrap();
+        let fingerprint = Fingerprint::of(&cpk);
+        assert_eq!(fingerprint.to_string(), "bcfa3d9b1e7320c15abbe311e9e57808b36cb1fe209866f3814a03fe499d647b");
+        assert_eq!(Address::from_public_key(&cpk).to_string(), "cbx1qqqme73anv08xgxpt2a7xy0fu4uq3vmvk8lqtlk5jx");
+    }
+}
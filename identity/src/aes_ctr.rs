@@ -2,7 +2,7 @@
 //! primarily for deriving P1 and P2 matrix components in MAYO.
 
 use aes::Aes128;
-use aes::cipher::{generic_array::GenericArray, StreamCipher, KeyIvInit}; // Removed KeyInit
+use aes::cipher::{generic_array::GenericArray, StreamCipher, StreamCipherSeek, KeyIvInit}; // Removed KeyInit
 use ctr::Ctr128BE; // Using Big Endian as is common in cryptographic contexts.
 use crate::types::SeedPK;
 use crate::params::MayoVariantParams;
@@ -10,10 +10,12 @@ use crate::params::MayoVariantParams;
 /// Generates a stream of pseudo-random bytes using AES-128-CTR.
 ///
 /// The AES key is derived from `key_bytes` (typically `seed_pk.0`).
-/// A standard zero IV (nonce) is used for the start of the CTR mode.
+/// A standard zero IV (nonce) is used for the start of the CTR mode, and the returned
+/// bytes are taken from the keystream starting at byte `offset`.
 ///
 /// # Arguments
 /// * `key_bytes` - A 16-byte slice representing the AES-128 key.
+/// * `offset` - The keystream position of the first returned byte.
 /// * `output_len` - The number of pseudo-random bytes to generate.
 ///
 /// # Panics
@@ -22,7 +24,7 @@ use crate::params::MayoVariantParams;
 ///
 /// # Returns
 /// A `Vec<u8>` containing the generated pseudo-random bytes.
-fn aes128_ctr_generate(key_bytes: &[u8], output_len: usize) -> Vec<u8> {
+fn aes128_ctr_generate(key_bytes: &[u8], offset: usize, output_len: usize) -> Vec<u8> {
     if key_bytes.len() != 16 {
         // In a real library, this should be an error type.
         // Aes128::new itself would also panic or error on incorrect key length.
@@ -33,7 +35,8 @@ fn aes128_ctr_generate(key_bytes: &[u8], output_len: usize) -> Vec<u8> {
 
     // Ctr128BE<Aes128> implements the StreamCipher trait.
     let mut cipher = Ctr128BE::<Aes128>::new(key, iv);
-    
+    cipher.seek(offset);
+
     let mut output = vec![0u8; output_len];
     cipher.apply_keystream(&mut output);
     
@@ -56,12 +59,15 @@ pub fn derive_p1_bytes(seed_pk: &SeedPK, params: &MayoVariantParams) -> Vec<u8>
         panic!("SeedPK length {} does not match params.pk_seed_bytes {} for AES-128 key", 
                seed_pk.0.len(), params.pk_seed_bytes);
     }
-    aes128_ctr_generate(&seed_pk.0, params.p1_bytes)
+    aes128_ctr_generate(&seed_pk.0, 0, params.p1_bytes)
 }
 
 /// Derives the bytes for the P2 matrix component from a public key seed (`SeedPK`)
 /// using AES-128-CTR.
 ///
+/// P1 and P2 share a single keystream, so P2 starts right after the `params.p1_bytes`
+/// bytes consumed by P1.
+///
 /// # Arguments
 /// * `seed_pk` - The public key seed, which provides the 16-byte key for AES.
 /// * `params` - The MAYO variant parameters, used to determine `params.p2_bytes`.
@@ -73,5 +79,5 @@ pub fn derive_p2_bytes(seed_pk: &SeedPK, params: &MayoVariantParams) -> Vec<u8>
         panic!("SeedPK length {} does not match params.pk_seed_bytes {} for AES-128 key", 
                seed_pk.0.len(), params.pk_seed_bytes);
     }
-    aes128_ctr_generate(&seed_pk.0, params.p2_bytes)
+    aes128_ctr_generate(&seed_pk.0, params.p1_bytes, params.p2_bytes)
 }
@@ -1,79 +1,130 @@
 //! Implements NIST-like API wrappers for MAYO cryptographic operations.
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
+//!
+//! `sign` and `open` follow the NIST `crypto_sign` / `crypto_sign_open` layout, where
+//! the signed message is `signature || message`; `sign_detached` and `verify` keep the
+//! signature separate, as cubix-chain transactions do. The functions are plain Rust and
+//! return `IdentityError`; with the `wasm` feature, `crate::wasm` exports them to JS,
+//! where the error is thrown as an `Error` carrying the error code.
+//!
+//! Each call expands the compact key again. To sign or verify many messages under one
+//! key, build a `SigningKey` or `VerifyingKey` once and call its `sign` or `verify`.
 
+use crate::error::IdentityError;
 use crate::types::{CompactSecretKey, CompactPublicKey, Message, Signature, ExpandedSecretKey, ExpandedPublicKey};
 use crate::params::MayoParams; // MayoVariantParams is accessed via MayoParams.variant()
 use crate::keygen::{compact_key_gen, expand_sk, expand_pk};
+use crate::derive::MasterSeed;
 use crate::sign::sign_message;
 use crate::verify::verify_signature;
+use crate::batch::{shared_verifier, BatchItem};
 
-#[wasm_bindgen(getter_with_clone)]
-pub struct KeyPairWrapper {
-    pub sk: CompactSecretKey,
-    pub pk: CompactPublicKey,
+/// Generates a compact key pair (secret key, public key) for the MAYO variant `params`.
+/// This wraps `MAYO.CompactKeyGen`.
+pub fn keypair(params: &MayoParams) -> Result<(CompactSecretKey, CompactPublicKey), IdentityError> {
+    compact_key_gen(params)
 }
 
-/// Generates a compact key pair (secret key, public key) for the specified MAYO variant.
-/// This wraps `MAYO.CompactKeyGen`.
-#[wasm_bindgen]
-pub fn keypair(mayo_variant_name: String) -> Result<KeyPairWrapper, JsValue> {
-    let params_enum = MayoParams::get_params_by_name(&mayo_variant_name).map_err(|e| JsValue::from_str(&e))?;
-    let (sk, pk) = compact_key_gen(&params_enum).map_err(|e| JsValue::from_str(e))?;
-    Ok(KeyPairWrapper { sk, pk })
+/// Derives the key pair of the MAYO variant `params` at `path` (e.g. "m/0/0") from a
+/// mnemonic phrase and an optional passphrase (empty if none). See `derive`.
+pub fn keypair_from_mnemonic(params: &MayoParams, mnemonic: &str, passphrase: &str, path: &str) -> Result<(CompactSecretKey, CompactPublicKey), IdentityError> {
+    MasterSeed::from_mnemonic(mnemonic, passphrase)?.derive_keypair(params, &path.parse()?)
+}
+
+/// Derives the key pair of the MAYO variant `params` at `path` from a 16 to 64 byte
+/// master seed. See `derive`.
+pub fn keypair_from_seed(params: &MayoParams, seed: &[u8], path: &str) -> Result<(CompactSecretKey, CompactPublicKey), IdentityError> {
+    MasterSeed::from_bytes(seed)?.derive_keypair(params, &path.parse()?)
 }
 
-/// Signs a message using a compact secret key.
+/// Signs a message using a compact secret key and returns the detached signature.
 /// This involves expanding the secret key and then calling `MAYO.Sign`.
-/// The returned signature does not include the message.
-#[wasm_bindgen]
-pub fn sign(csk: &CompactSecretKey, message: &Message, mayo_variant_name: String) -> Result<Signature, JsValue> {
-    let params_enum = MayoParams::get_params_by_name(&mayo_variant_name).map_err(|e| JsValue::from_str(&e))?;
-    // Note: The problem description mentions ExpandedSecretKey is not used by sign.
-    // However, the provided function signature for sign_message in sign.rs *does* take ExpandedSecretKey.
-    // Algorithm 8 (MAYO.Sign) takes esk as input.
-    // Algorithm 3 (NIST API Sign) takes sk (csk) as input, implying internal expansion.
-    // So, expanding sk to esk here is correct.
-    let esk: ExpandedSecretKey = expand_sk(csk, &params_enum).map_err(|e| JsValue::from_str(e))?;
-    sign_message(&esk, message, &params_enum).map_err(|e| JsValue::from_str(e))
+pub fn sign_detached(csk: &CompactSecretKey, message: &Message) -> Result<Signature, IdentityError> {
+    // Algorithm 8 (MAYO.Sign) takes esk as input, while Algorithm 3 (NIST API Sign)
+    // takes sk (csk), implying internal expansion.
+    let esk: ExpandedSecretKey = expand_sk(csk)?;
+    sign_message(&esk, message)
+}
+
+/// Signs a message using a compact secret key, NIST style: returns the signed message
+/// `sm = signature || message`, which `open` accepts.
+pub fn sign(csk: &CompactSecretKey, message: &Message) -> Result<Vec<u8>, IdentityError> {
+    let signature = sign_detached(csk, message)?;
+    let mut signed_message = Vec::with_capacity(signature.as_bytes().len() + message.0.len());
+    signed_message.extend_from_slice(signature.as_bytes());
+    signed_message.extend_from_slice(&message.0);
+    Ok(signed_message)
+}
+
+/// Verifies a detached signature on a message under a compact public key.
+/// Fails if the signature belongs to a different MAYO variant than `cpk`.
+pub fn verify(cpk: &CompactPublicKey, message: &Message, signature: &Signature) -> Result<bool, IdentityError> {
+    // Algorithm 9 (MAYO.Verify) takes epk as input, while Algorithm 4 (NIST API
+    // Verify/Open) takes pk (cpk), implying internal expansion.
+    let epk: ExpandedPublicKey = expand_pk(cpk)?;
+    verify_signature(&epk, message, signature)
 }
 
 /// Verifies a signature on a "signed message" and recovers the original message if valid.
 /// This corresponds to `sign_open` in some APIs.
-/// Assumes `signed_message` is `signature_bytes || original_message_bytes`.
-#[wasm_bindgen]
-pub fn open(cpk: &CompactPublicKey, signed_message: &[u8], mayo_variant_name: String) -> Result<Option<Message>, JsValue> {
-    let params_enum = MayoParams::get_params_by_name(&mayo_variant_name).map_err(|e| JsValue::from_str(&e))?;
-    let params = params_enum.variant();
-    
-    // Determine signature length: s_bytes_len (n elements) + salt_bytes
-    let s_bytes_len = MayoParams::bytes_for_gf16_elements(params.n);
-    let expected_sig_len = s_bytes_len + params.salt_bytes;
+/// Assumes `signed_message` is `signature_bytes || original_message_bytes`, with the
+/// signature length given by the variant of `cpk`.
+pub fn open(cpk: &CompactPublicKey, signed_message: &[u8]) -> Result<Option<Message>, IdentityError> {
+    let params_enum = cpk.params();
+    let expected_sig_len = params_enum.sig_bytes();
 
     if signed_message.len() < expected_sig_len {
-        return Err(JsValue::from_str("Signed message is too short to contain a signature"));
+        return Err(IdentityError::InvalidLength("Signed message is too short to contain a signature"));
     }
 
-    let sig_bytes = &signed_message[0..expected_sig_len];
-    let message_bytes = &signed_message[expected_sig_len..];
-
-    let signature = Signature(sig_bytes.to_vec());
+    let (sig_bytes, message_bytes) = signed_message.split_at(expected_sig_len);
+    let signature = Signature::from_bytes(*params_enum, sig_bytes.to_vec())?;
     let original_message = Message(message_bytes.to_vec());
 
-    // Note: The problem description mentions ExpandedPublicKey is not used by verify.
-    // However, the provided function signature for verify_signature in verify.rs *does* take ExpandedPublicKey.
-    // Algorithm 9 (MAYO.Verify) takes epk as input.
-    // Algorithm 4 (NIST API Verify/Open) takes pk (cpk) as input, implying internal expansion.
-    // So, expanding pk to epk here is correct.
-    let epk: ExpandedPublicKey = expand_pk(cpk, &params_enum).map_err(|e| JsValue::from_str(e))?;
-    
-    match verify_signature(&epk, &original_message, &signature, &params_enum) {
-        Ok(true) => Ok(Some(original_message)), // Valid signature, return message
-        Ok(false) => Ok(None),                  // Invalid signature
-        Err(e) => Err(JsValue::from_str(e)),                       // Error during verification
+    if verify(cpk, &original_message, &signature)? {
+        Ok(Some(original_message)) // Valid signature, return message
+    } else {
+        Ok(None) // Invalid signature
     }
 }
 
+/// Result of `verify_batch`: whether every signature is valid, and the indices of the
+/// entries that are not (invalid, or not checkable such as a key/signature variant mismatch).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchVerification {
+    valid: bool,
+    failed: Vec<usize>,
+}
+
+impl BatchVerification {
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    pub fn failed(&self) -> &[usize] {
+        &self.failed
+    }
+}
+
+/// Verifies detached signatures in a batch: `signatures[i]` over `messages[i]` under
+/// `public_keys[i]`. Expanded public keys are cached between calls, and the
+/// verifications run in parallel where threads are available.
+pub fn verify_batch(public_keys: &[CompactPublicKey], messages: &[Message], signatures: &[Signature]) -> Result<BatchVerification, IdentityError> {
+    if public_keys.len() != messages.len() || public_keys.len() != signatures.len() {
+        return Err(IdentityError::InvalidInput("Batch inputs must have the same length"));
+    }
+    let items: Vec<BatchItem<'_>> = public_keys
+        .iter()
+        .zip(messages)
+        .zip(signatures)
+        .map(|((public_key, message), signature)| BatchItem { public_key, message, signature })
+        .collect();
+    let report = shared_verifier().verify(&items);
+    Ok(BatchVerification {
+        valid: report.is_valid(),
+        failed: report.failed(),
+    })
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -84,54 +135,82 @@ mod tests {
     #[test]
     fn test_keypair_api() {
         // Test for MAYO1
-        let mayo1_name = "mayo1".to_string();
-        let res1 = keypair(mayo1_name.clone());
-        assert!(res1.is_ok(), "keypair failed for mayo1: {:?}", res1.err().map(|e| e.as_string()));
-        let wrapper1 = res1.unwrap();
-        let csk1 = wrapper1.sk;
-        let cpk1 = wrapper1.pk;
+        let res1 = keypair(&MayoParams::mayo1());
+        assert!(res1.is_ok(), "keypair failed for mayo1: {:?}", res1.err());
+        let (csk1, cpk1) = res1.unwrap();
         let params_mayo1 = MayoParams::mayo1(); // For assertion values
-        assert_eq!(csk1.0.len(), params_mayo1.sk_seed_bytes());
-        assert_eq!(cpk1.0.len(), params_mayo1.pk_seed_bytes() + params_mayo1.p3_bytes());
+        assert_eq!(csk1.as_bytes().len(), params_mayo1.sk_seed_bytes());
+        assert_eq!(cpk1.as_bytes().len(), params_mayo1.pk_seed_bytes() + params_mayo1.p3_bytes());
 
         // Test for MAYO2
-        let mayo2_name = "mayo2".to_string();
-        let res2 = keypair(mayo2_name.clone());
-        assert!(res2.is_ok(), "keypair failed for mayo2: {:?}", res2.err().map(|e| e.as_string()));
-        let wrapper2 = res2.unwrap();
-        let csk2 = wrapper2.sk;
-        let cpk2 = wrapper2.pk;
+        let res2 = keypair(&MayoParams::mayo2());
+        assert!(res2.is_ok(), "keypair failed for mayo2: {:?}", res2.err());
+        let (csk2, cpk2) = res2.unwrap();
         let params_mayo2 = MayoParams::mayo2(); // For assertion values
-        assert_eq!(csk2.0.len(), params_mayo2.sk_seed_bytes());
-        assert_eq!(cpk2.0.len(), params_mayo2.pk_seed_bytes() + params_mayo2.p3_bytes());
+        assert_eq!(csk2.as_bytes().len(), params_mayo2.sk_seed_bytes());
+        assert_eq!(cpk2.as_bytes().len(), params_mayo2.pk_seed_bytes() + params_mayo2.p3_bytes());
+
+        // Test for MAYO3 and MAYO5
+        for params in [MayoParams::mayo3(), MayoParams::mayo5()] {
+            let (sk, pk) = keypair(&params).expect("keypair generation failed");
+            assert_eq!(sk.params(), &params);
+            assert_eq!(sk.as_bytes().len(), params.csk_bytes());
+            assert_eq!(pk.as_bytes().len(), params.cpk_bytes());
+        }
     }
 
     #[test]
-    fn test_sign_api_flow_with_placeholder() {
-        let mayo1_name = "mayo1".to_string();
-        let (csk, _cpk) = keypair(mayo1_name.clone()).expect("keypair generation failed");
+    fn test_sign_api_flow() {
+        let (csk, _cpk) = keypair(&MayoParams::mayo1()).expect("keypair generation failed");
         let message = Message(b"test message for sign api".to_vec());
 
-        let sign_result = sign(&csk, &message, mayo1_name.clone());
-        
-        // Since sign_message -> compute_Y_A_yprime_and_s_components is a placeholder returning Err,
-        // we expect that specific error from sign_message.
-        match sign_result {
-            Err(e) => assert_eq!(e.as_string().expect("Error should be a string"), "MAYO.Sign math core (compute_Y_A_yprime_and_s_components) not implemented"),
-            Ok(_) => panic!("API sign should fail due to placeholder in sign_message"),
-        }
+        let signature = sign_detached(&csk, &message).expect("API sign_detached failed");
+        assert_eq!(signature.as_bytes().len(), MayoParams::mayo1().sig_bytes());
+
+        let signed_message = sign(&csk, &message).expect("API sign failed");
+        assert_eq!(signed_message.len(), MayoParams::mayo1().sig_bytes() + message.0.len());
+        assert_eq!(&signed_message[MayoParams::mayo1().sig_bytes()..], &message.0[..]);
     }
 
     #[test]
-    fn test_open_api_flow_with_placeholder() {
-        let mayo1_name = "mayo1".to_string();
-        let (_csk, cpk) = keypair(mayo1_name.clone()).expect("keypair generation failed");
+    fn test_keypair_derivation_api() {
+        let mnemonic = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let mayo1 = MayoParams::mayo1();
+        let derived = keypair_from_mnemonic(&mayo1, mnemonic, "", "m/0/0").unwrap();
+        let again = keypair_from_mnemonic(&mayo1, mnemonic, "", "m/0/0").unwrap();
+        assert_eq!(derived, again);
+        let next = keypair_from_mnemonic(&mayo1, mnemonic, "", "m/0/1").unwrap();
+        assert_ne!(derived.0, next.0);
+        assert!(keypair_from_mnemonic(&mayo1, mnemonic, "", "0/1").is_err());
+
+        let (_, from_seed) = keypair_from_seed(&MayoParams::mayo2(), &[7u8; 32], "m/3/4").unwrap();
+        assert_eq!(from_seed.as_bytes().len(), MayoParams::mayo2().cpk_bytes());
+        assert_eq!(keypair_from_seed(&MayoParams::mayo2(), &[7u8; 8], "m/3/4").err().map(|e| e.code()),
+                   Some("INVALID_LENGTH"));
+    }
+
+    #[test]
+    fn test_detached_verify_api() {
+        let (csk, cpk) = keypair(&MayoParams::mayo2()).expect("keypair generation failed");
+        let message = Message(b"transaction meta".to_vec());
+        let signature = sign_detached(&csk, &message).expect("API sign_detached failed");
+
+        assert_eq!(verify(&cpk, &message, &signature), Ok(true));
+        assert_eq!(verify(&cpk, &Message(b"other meta".to_vec()), &signature), Ok(false));
+
+        let (_, mayo1_pk) = keypair(&MayoParams::mayo1()).expect("keypair generation failed");
+        assert_eq!(verify(&mayo1_pk, &message, &signature),
+                   Err(IdentityError::VariantMismatch));
+    }
+
+    #[test]
+    fn test_open_api_flow_with_dummy_signature() {
+        let params_enum_for_test = MayoParams::mayo1();
+        let (_csk, cpk) = keypair(&params_enum_for_test).expect("keypair generation failed");
         
         // Create a dummy "signed message"
-        // Signature part: s_bytes (n elements) + salt_bytes
-        let params_enum_for_test = MayoParams::get_params_by_name(&mayo1_name).unwrap(); // To get .n() and .salt_bytes()
-        let s_bytes_len = MayoParams::bytes_for_gf16_elements(params_enum_for_test.variant().n);
-        let expected_sig_len = s_bytes_len + params_enum_for_test.salt_bytes();
+        // Signature part: s_bytes (k vectors of n elements) + salt_bytes
+        let expected_sig_len = params_enum_for_test.sig_bytes();
         
         let dummy_sig_bytes = vec![0u8; expected_sig_len];
         let original_message_text = b"test message for open api";
@@ -140,65 +219,72 @@ mod tests {
         signed_message_bytes.extend_from_slice(&dummy_sig_bytes);
         signed_message_bytes.extend_from_slice(original_message_text);
 
-        let open_result = open(&cpk, &signed_message_bytes, mayo1_name.clone());
+        let open_result = open(&cpk, &signed_message_bytes);
 
-        // Since verify_signature -> compute_p_star_s is a placeholder returning Err,
-        // we expect that specific error from verify_signature.
+        // An all-zero s evaluates to zero, which does not match the derived target.
         match open_result {
-            Err(e) => assert_eq!(e.as_string().expect("Error should be a string"), "MAYO.Verify math core (compute_p_star_s) not implemented"),
-            Ok(_) => panic!("API open should fail due to placeholder in verify_signature"),
+            Ok(None) => {}
+            Ok(Some(_)) => panic!("API open accepted a dummy signature"),
+            Err(e) => panic!("API open failed unexpectedly: {:?}", e),
         }
     }
 
     #[test]
     fn test_open_api_message_too_short() {
-        let mayo1_name = "mayo1".to_string();
-        let (_csk, cpk) = keypair(mayo1_name.clone()).expect("keypair generation failed");
+        let params_enum_for_test = MayoParams::mayo1();
+        let (_csk, cpk) = keypair(&params_enum_for_test).expect("keypair generation failed");
         
-        let params_enum_for_test = MayoParams::get_params_by_name(&mayo1_name).unwrap(); // To get .n() and .salt_bytes()
-        let s_bytes_len = MayoParams::bytes_for_gf16_elements(params_enum_for_test.variant().n);
-        let expected_sig_len = s_bytes_len + params_enum_for_test.salt_bytes();
+        let expected_sig_len = params_enum_for_test.sig_bytes();
         
         let short_signed_message = vec![0u8; expected_sig_len - 1];
         
-        let open_result = open(&cpk, &short_signed_message, mayo1_name.clone());
+        let open_result = open(&cpk, &short_signed_message);
         match open_result {
-            Err(e) => assert_eq!(e.as_string().expect("Error should be a string"), "Signed message is too short to contain a signature"),
+            Err(e) => assert_eq!(e, IdentityError::InvalidLength("Signed message is too short to contain a signature")),
             Ok(_) => panic!("Should have failed due to message too short"),
         }
     }
     
-    // Conceptual test for open with tampered data (depends on functional sign & verify)
-    // #[test]
-    // fn test_open_tampered_flow_conceptual() {
-    //     let params_enum = MayoParams::mayo1();
-    //     let (csk, cpk) = keypair(&params_enum).unwrap();
-    //     let message_text = b"original message";
-    //     let original_message = Message(message_text.to_vec());
-
-    //     // This part requires sign to be functional
-    //     // let signature = sign(&csk, &original_message, &params_enum).expect("sign failed conceptually");
-    //     // let mut signed_message_bytes = Vec::new();
-    //     // signed_message_bytes.extend_from_slice(&signature.0);
-    //     // signed_message_bytes.extend_from_slice(message_text);
-        
-    //     // // Tamper the signature part (e.g., flip a bit)
-    //     // if !signed_message_bytes.is_empty() {
-    //     //     signed_message_bytes[0] ^= 0x01; 
-    //     // }
-        
-    //     // // This part requires verify_signature to be functional beyond placeholder
-    //     // let open_result = open(&cpk, &signed_message_bytes, &params_enum);
-    //     // match open_result {
-    //     //     Ok(None) => { /* Correct for tampered signature */ },
-    //     //     Ok(Some(_)) => panic!("Open succeeded with tampered signature"),
-    //     //     Err(e) if e == "MAYO.Verify math core (compute_p_star_s) not implemented" => { /* Expected current state */ }
-    //     //     Err(e) => panic!("Open failed unexpectedly: {}", e),
-    //     // }
-    // }
-
-    // TODO: Implement Known Answer Tests (KATs) for the full keypair, sign, and open API lifecycle
-    // once the core cryptographic math (compute_Y_A_yprime_and_s_components and compute_p_star_s)
-    // is fully implemented. These tests will use official MAYO test vectors to verify
-    // end-to-end correctness of the API functions.
+    #[test]
+    fn test_sign_open_roundtrip_and_tampered() {
+        let (csk, cpk) = keypair(&MayoParams::mayo2()).expect("keypair generation failed");
+        let message_text = b"original message";
+        let original_message = Message(message_text.to_vec());
+
+        let mut signed_message_bytes = sign(&csk, &original_message).expect("API sign failed");
+
+        match open(&cpk, &signed_message_bytes) {
+            Ok(Some(recovered)) => assert_eq!(recovered, original_message),
+            Ok(None) => panic!("Open rejected a valid signature"),
+            Err(e) => panic!("Open failed unexpectedly: {:?}", e),
+        }
+
+        // Tamper the signature part (flip a bit)
+        signed_message_bytes[0] ^= 0x01;
+        match open(&cpk, &signed_message_bytes) {
+            Ok(None) => { /* Correct for tampered signature */ },
+            Ok(Some(_)) => panic!("Open succeeded with tampered signature"),
+            Err(e) => panic!("Open failed unexpectedly: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_api() {
+        let (csk, cpk) = keypair(&MayoParams::mayo1()).expect("keypair generation failed");
+        let messages = vec![Message(b"tx 1".to_vec()), Message(b"tx 2".to_vec())];
+        let signatures: Vec<Signature> = messages.iter().map(|m| sign_detached(&csk, m).expect("API sign failed")).collect();
+
+        let keys = [cpk.clone(), cpk];
+        let result = verify_batch(&keys, &messages, &signatures).unwrap();
+        assert_eq!(result, BatchVerification { valid: true, failed: vec![] });
+
+        let swapped = [signatures[1].clone(), signatures[0].clone()];
+        let result = verify_batch(&keys, &messages, &swapped).unwrap();
+        assert!(!result.valid());
+        assert_eq!(result.failed(), &[0, 1]);
+        assert_eq!(verify_batch(&keys, &messages[..1], &swapped), Err(IdentityError::InvalidInput("Batch inputs must have the same length")));
+    }
+
+    // Known Answer Tests against the NIST vectors live in `kat.rs`; they drive the
+    // keygen/sign/verify layer underneath this API with the reference DRBG.
 }
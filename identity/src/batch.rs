@@ -0,0 +1,321 @@
+//! Verifies many (public key, message, signature) triples at once.
+//!
+//! `verify_signature` needs the expanded public key, and expanding it from the compact
+//! key (two AES-128-CTR streams of P1 and P2) costs more than many verifications. The
+//! `BatchVerifier` expands each distinct compact key once, keeps the resulting
+//! `VerifyingKey`s in a bounded cache indexed by a hash of the compact key, and runs
+//! the expansions and the verifications in parallel on a rayon thread pool. Where
+//! threads cannot be spawned, as on plain wasm32, rayon's global pool runs the same
+//! work on the calling thread.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+
+use crate::error::IdentityError;
+use crate::types::{CompactPublicKey, Message, Signature};
+use crate::verify::VerifyingKey;
+
+/// Number of expanded public keys kept by `BatchVerifier::default`.
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// Blake2b-256 of the variant id followed by the compact public key bytes.
+pub type CompactKeyHash = [u8; 32];
+
+/// Hashes a compact public key for use as a cache index. The variant id is included so
+/// that equal bytes under different variants never share an entry.
+pub fn compact_key_hash(cpk: &CompactPublicKey) -> CompactKeyHash {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([cpk.params().id()]);
+    hasher.update(cpk.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Decoded expanded public keys by compact key hash, evicting the oldest entry when full.
+#[derive(Debug)]
+pub struct ExpandedKeyCache {
+    capacity: usize,
+    entries: HashMap<CompactKeyHash, Arc<VerifyingKey>>,
+    insertion_order: VecDeque<CompactKeyHash>,
+}
+
+impl ExpandedKeyCache {
+    /// Creates an empty cache holding at most `capacity` keys; 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        ExpandedKeyCache { capacity, entries: HashMap::new(), insertion_order: VecDeque::new() }
+    }
+
+    /// Returns the cached expanded key for `hash`, if any.
+    pub fn get(&self, hash: &CompactKeyHash) -> Option<Arc<VerifyingKey>> {
+        self.entries.get(hash).cloned()
+    }
+
+    /// Caches `key` under `hash`, evicting the oldest entries beyond the capacity.
+    pub fn insert(&mut self, hash: CompactKeyHash, key: Arc<VerifyingKey>) {
+        if self.capacity == 0 || self.entries.insert(hash, key).is_some() {
+            return;
+        }
+        self.insertion_order.push_back(hash);
+        while self.entries.len() > self.capacity {
+            if let Some(oldest) = self.insertion_order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns the number of cached keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no key is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops every cached key.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.insertion_order.clear();
+    }
+}
+
+/// One signature to check in a batch.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchItem<'a> {
+    pub public_key: &'a CompactPublicKey,
+    pub message: &'a Message,
+    pub signature: &'a Signature,
+}
+
+/// The outcome of a batch, one entry per item in input order: `Ok(true)` for a valid
+/// signature, `Ok(false)` for an invalid one, and `Err` if the item could not be checked
+/// (e.g. key and signature of different variants).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchReport {
+    results: Vec<Result<bool, IdentityError>>,
+}
+
+impl BatchReport {
+    /// Returns the result of every item, in input order.
+    pub fn results(&self) -> &[Result<bool, IdentityError>] {
+        &self.results
+    }
+
+    /// Returns true if every signature in the batch is valid.
+    pub fn is_valid(&self) -> bool {
+        self.results.iter().all(|result| *result == Ok(true))
+    }
+
+    /// Returns the indices of the items whose signature is invalid or could not be checked.
+    pub fn failed(&self) -> Vec<usize> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| **result != Ok(true))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Verifies batches of signatures in parallel, caching expanded public keys between
+/// batches. It can be shared between threads.
+#[derive(Debug)]
+pub struct BatchVerifier {
+    cache: Mutex<ExpandedKeyCache>,
+    pool: Option<ThreadPool>,
+}
+
+impl Default for BatchVerifier {
+    /// A verifier with `DEFAULT_CACHE_CAPACITY` that runs on the global rayon pool.
+    fn default() -> Self {
+        BatchVerifier { cache: Mutex::new(ExpandedKeyCache::new(DEFAULT_CACHE_CAPACITY)), pool: None }
+    }
+}
+
+impl BatchVerifier {
+    /// Creates a verifier caching up to `cache_capacity` expanded keys. With
+    /// `num_threads` it runs on a dedicated pool of that many threads, otherwise on the
+    /// global rayon pool.
+    pub fn new(cache_capacity: usize, num_threads: Option<usize>) -> Result<Self, IdentityError> {
+        let pool = match num_threads {
+            Some(n) => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n)
+                    .build()
+                    .map_err(|_| IdentityError::ThreadPool)?,
+            ),
+            None => None,
+        };
+        Ok(BatchVerifier { cache: Mutex::new(ExpandedKeyCache::new(cache_capacity)), pool })
+    }
+
+    /// Returns the number of expanded keys currently cached.
+    pub fn cached_keys(&self) -> usize {
+        self.lock_cache().len()
+    }
+
+    /// Drops every cached expanded key.
+    pub fn clear_cache(&self) {
+        self.lock_cache().clear();
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, ExpandedKeyCache> {
+        // The cache holds no invariants a panicking thread could break halfway.
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn run<R: Send>(&self, job: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(job),
+            None => job(),
+        }
+    }
+
+    /// Verifies every item of `items` and reports the result of each.
+    ///
+    /// Each distinct compact key is expanded at most once per batch, and not at all if
+    /// it is still cached from an earlier batch.
+    pub fn verify(&self, items: &[BatchItem<'_>]) -> BatchReport {
+        self.run(|| {
+            let hashes: Vec<CompactKeyHash> = items.par_iter().map(|item| compact_key_hash(item.public_key)).collect();
+
+            let mut keys: HashMap<CompactKeyHash, Result<Arc<VerifyingKey>, IdentityError>> = HashMap::new();
+            let mut missing: Vec<(CompactKeyHash, &CompactPublicKey)> = Vec::new();
+            {
+                let cache = self.lock_cache();
+                let mut seen = HashSet::new();
+                for (item, hash) in items.iter().zip(&hashes) {
+                    if !seen.insert(*hash) {
+                        continue;
+                    }
+                    match cache.get(hash) {
+                        Some(key) => {
+                            keys.insert(*hash, Ok(key));
+                        }
+                        None => missing.push((*hash, item.public_key)),
+                    }
+                }
+            }
+
+            let expanded: Vec<(CompactKeyHash, Result<VerifyingKey, IdentityError>)> =
+                missing.par_iter().map(|(hash, cpk)| (*hash, VerifyingKey::new(cpk))).collect();
+            {
+                let mut cache = self.lock_cache();
+                for (hash, result) in expanded {
+                    let result = result.map(Arc::new);
+                    if let Ok(key) = &result {
+                        cache.insert(hash, Arc::clone(key));
+                    }
+                    keys.insert(hash, result);
+                }
+            }
+
+            let results = items
+                .par_iter()
+                .zip(&hashes)
+                .map(|(item, hash)| match &keys[hash] {
+                    Ok(key) => key.verify(item.message, item.signature),
+                    Err(e) => Err(*e),
+                })
+                .collect();
+            BatchReport { results }
+        })
+    }
+}
+
+/// The process-wide verifier used by `api::verify_batch` and its wasm export, so that
+/// expanded keys stay cached between calls.
+pub fn shared_verifier() -> &'static BatchVerifier {
+    static SHARED: OnceLock<BatchVerifier> = OnceLock::new();
+    SHARED.get_or_init(BatchVerifier::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::{compact_key_gen, expand_sk};
+    use crate::params::MayoParams;
+    use crate::sign::sign_message;
+
+    fn signed(params: &MayoParams, text: &[u8]) -> (CompactPublicKey, Message, Signature) {
+        let (csk, cpk) = compact_key_gen(params).unwrap();
+        let message = Message(text.to_vec());
+        let signature = sign_message(&expand_sk(&csk).unwrap(), &message).unwrap();
+        (cpk, message, signature)
+    }
+
+    #[test]
+    fn test_batch_reports_failed_entries() {
+        let mayo2 = MayoParams::mayo2();
+        let (cpk_a, msg_a, sig_a) = signed(&mayo2, b"face 1");
+        let (cpk_b, msg_b, sig_b) = signed(&mayo2, b"face 2");
+        let (cpk_c, msg_c, sig_c) = signed(&MayoParams::mayo1(), b"face 3");
+        let other_msg = Message(b"face 4".to_vec());
+
+        let items = [
+            BatchItem { public_key: &cpk_a, message: &msg_a, signature: &sig_a },
+            BatchItem { public_key: &cpk_b, message: &msg_b, signature: &sig_b },
+            BatchItem { public_key: &cpk_a, message: &other_msg, signature: &sig_a },
+            BatchItem { public_key: &cpk_c, message: &msg_c, signature: &sig_c },
+            BatchItem { public_key: &cpk_a, message: &msg_a, signature: &sig_c },
+            BatchItem { public_key: &cpk_b, message: &msg_b, signature: &sig_a },
+        ];
+        let verifier = BatchVerifier::new(16, Some(2)).unwrap();
+        let report = verifier.verify(&items);
+        assert_eq!(report.results(), &[
+            Ok(true),
+            Ok(true),
+            Ok(false),
+            Ok(true),
+            Err(IdentityError::VariantMismatch),
+            Ok(false),
+        ]);
+        assert!(!report.is_valid());
+        assert_eq!(report.failed(), vec![2, 4, 5]);
+        assert_eq!(verifier.cached_keys(), 3);
+
+        // The second pass is served from the cache and gives the same answers.
+        assert_eq!(verifier.verify(&items), report);
+        assert_eq!(verifier.cached_keys(), 3);
+
+        let all_valid = verifier.verify(&items[..2]);
+        assert!(all_valid.is_valid());
+        assert!(all_valid.failed().is_empty());
+        assert!(BatchVerifier::default().verify(&[]).is_valid());
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest_and_keys_by_variant() {
+        let mayo1 = MayoParams::mayo1();
+        let (_csk, cpk) = compact_key_gen(&mayo1).unwrap();
+        let key = Arc::new(VerifyingKey::new(&cpk).unwrap());
+
+        let mut cache = ExpandedKeyCache::new(2);
+        for i in 0..3u8 {
+            cache.insert([i; 32], Arc::clone(&key));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&[0; 32]).is_none());
+        assert!(cache.get(&[2; 32]).is_some());
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let mut disabled = ExpandedKeyCache::new(0);
+        disabled.insert([0; 32], key);
+        assert!(disabled.is_empty());
+
+        // The hash is deterministic and changes with any byte of the key.
+        let bytes = vec![7u8; mayo1.cpk_bytes()];
+        let as_mayo1 = CompactPublicKey::from_bytes(mayo1, bytes.clone()).unwrap();
+        assert_eq!(compact_key_hash(&as_mayo1), compact_key_hash(&as_mayo1));
+        let mut other = bytes;
+        other[0] ^= 1;
+        let other = CompactPublicKey::from_bytes(mayo1, other).unwrap();
+        assert_ne!(compact_key_hash(&as_mayo1), compact_key_hash(&other));
+    }
+}
@@ -1,6 +1,7 @@
 //! Implements data encoding/decoding utilities, primarily for packing GF(16) elements
 //! into byte arrays and decoding matrices/vectors from these byte arrays.
 
+use crate::error::IdentityError;
 use crate::types::{GFElement, GFMatrix, GFVector};
 use crate::params::{MayoVariantParams}; // MayoParams enum not directly needed here if we pass MayoVariantParams
 // For GFMatrix::new_with_data, we need to import GFMatrix itself if methods are not on it.
@@ -8,22 +9,22 @@ use crate::params::{MayoVariantParams}; // MayoParams enum not directly needed h
 // So, we just need GFMatrix type from types.rs.
 
 /// Encodes a vector of GF(16) elements (nibbles) into a byte vector.
-/// Two GFElement (0-15) are packed into each byte.
-/// If there's an odd number of elements, the last nibble of the last byte is zero-padded.
+/// Two GFElement (0-15) are packed into each byte, the even-indexed element in the low
+/// nibble as in the MAYO reference encoding.
+/// If there's an odd number of elements, the high nibble of the last byte is zero-padded.
 pub fn encode_gf_elements(elements: &GFVector) -> Vec<u8> {
-    let num_elements = elements.len();
-    let num_bytes = (num_elements + 1) / 2;
+    let num_bytes = elements.len().div_ceil(2);
     let mut bytes = vec![0u8; num_bytes];
 
-    for i in 0..num_elements {
-        let element_val = elements[i].0 & 0x0F; // Ensure it's a nibble
+    for (i, element) in elements.iter().enumerate() {
+        let element_val = element.0 & 0x0F; // Ensure it's a nibble
         let byte_idx = i / 2;
         if i % 2 == 0 {
-            // High nibble for even index
-            bytes[byte_idx] = element_val << 4;
+            // Low nibble for even index
+            bytes[byte_idx] = element_val;
         } else {
-            // Low nibble for odd index
-            bytes[byte_idx] |= element_val;
+            // High nibble for odd index
+            bytes[byte_idx] |= element_val << 4;
         }
     }
     bytes
@@ -38,10 +39,10 @@ pub fn encode_gf_elements(elements: &GFVector) -> Vec<u8> {
 ///
 /// # Returns
 /// `Ok(GFVector)` if successful, or `Err` if `bytes` length is insufficient for `num_elements`.
-pub fn decode_gf_elements(bytes: &[u8], num_elements: usize) -> Result<GFVector, &'static str> {
-    let expected_num_bytes = (num_elements + 1) / 2;
+pub fn decode_gf_elements(bytes: &[u8], num_elements: usize) -> Result<GFVector, IdentityError> {
+    let expected_num_bytes = num_elements.div_ceil(2);
     if bytes.len() < expected_num_bytes {
-        return Err("Insufficient bytes to decode the specified number of GF elements");
+        return Err(IdentityError::InvalidLength("Insufficient bytes to decode the specified number of GF elements"));
     }
 
     let mut elements = Vec::with_capacity(num_elements);
@@ -49,11 +50,11 @@ pub fn decode_gf_elements(bytes: &[u8], num_elements: usize) -> Result<GFVector,
         let byte_idx = i / 2;
         let byte_val = bytes[byte_idx];
         if i % 2 == 0 {
-            // High nibble for even index
-            elements.push(GFElement((byte_val >> 4) & 0x0F));
-        } else {
-            // Low nibble for odd index
+            // Low nibble for even index
             elements.push(GFElement(byte_val & 0x0F));
+        } else {
+            // High nibble for odd index
+            elements.push(GFElement((byte_val >> 4) & 0x0F));
         }
     }
     Ok(elements)
@@ -61,7 +62,7 @@ pub fn decode_gf_elements(bytes: &[u8], num_elements: usize) -> Result<GFVector,
 
 /// Decodes the O matrix from its byte representation.
 /// Matrix O is `(n-o) x o`.
-pub fn decode_o_matrix(o_bytes: &[u8], params: &MayoVariantParams) -> Result<GFMatrix, &'static str> {
+pub fn decode_o_matrix(o_bytes: &[u8], params: &MayoVariantParams) -> Result<GFMatrix, IdentityError> {
     let rows = params.n - params.o;
     let cols = params.o;
     let num_elements = rows * cols;
@@ -73,9 +74,9 @@ pub fn decode_o_matrix(o_bytes: &[u8], params: &MayoVariantParams) -> Result<GFM
     // if params.o_bytes is used to calculate num_elements.
     // However, num_elements here is calculated from matrix dimensions.
     // We should check if o_bytes *can* provide num_elements.
-    let expected_byte_len = (num_elements + 1) / 2;
+    let expected_byte_len = num_elements.div_ceil(2);
     if o_bytes.len() < expected_byte_len {
-         return Err("Insufficient o_bytes to decode O matrix based on calculated dimensions");
+         return Err(IdentityError::InvalidLength("Insufficient o_bytes to decode O matrix based on calculated dimensions"));
     }
     // If params.o_bytes is also a field in MayoVariantParams, we should use/check against it.
     // Assuming params.o_bytes is the definitive length of the input slice for O.
@@ -94,9 +95,17 @@ pub fn decode_o_matrix(o_bytes: &[u8], params: &MayoVariantParams) -> Result<GFM
 
 // Helper for decoding upper triangular matrices
 // Fills an (n x n) matrix from a list of (n*(n+1)/2) elements for its upper triangular part.
-fn decode_upper_triangular_matrix(elements: &GFVector, size: usize) -> Result<GFMatrix, &'static str> {
+/// Inverse of `decode_o_matrix`: the (n-o) x o matrix O in row-major nibbles.
+pub fn encode_o_matrix(o_matrix: &GFMatrix, params: &MayoVariantParams) -> Result<Vec<u8>, IdentityError> {
+    if o_matrix.num_rows() != params.n - params.o || o_matrix.num_cols() != params.o {
+        return Err(IdentityError::DimensionMismatch("O matrix has incorrect dimensions"));
+    }
+    Ok(encode_gf_elements(&o_matrix.data))
+}
+
+fn decode_upper_triangular_matrix(elements: &GFVector, size: usize) -> Result<GFMatrix, IdentityError> {
     if elements.len() != size * (size + 1) / 2 {
-        return Err("Incorrect number of elements for upper triangular matrix");
+        return Err(IdentityError::DimensionMismatch("Incorrect number of elements for upper triangular matrix"));
     }
     let mut matrix = GFMatrix::zero(size, size);
     let mut k = 0;
@@ -110,142 +119,149 @@ fn decode_upper_triangular_matrix(elements: &GFVector, size: usize) -> Result<GF
 }
 
 
+/// Decodes a sequence of `count` m-vectors, each packed into `ceil(m/2)` bytes.
+/// This is the layout the MAYO specification uses for P1, P2, P3 and L: one m-vector
+/// per matrix entry, holding that entry for each of the m matrices.
+fn decode_m_vectors(bytes: &[u8], count: usize, params: &MayoVariantParams) -> Result<Vec<GFVector>, IdentityError> {
+    let m_vec_bytes = params.m_vec_bytes();
+    if bytes.len() != count * m_vec_bytes {
+        return Err(IdentityError::InvalidLength("Byte length does not correspond to the expected number of m-vectors"));
+    }
+    bytes
+        .chunks_exact(m_vec_bytes)
+        .map(|chunk| decode_gf_elements(chunk, params.m))
+        .collect()
+}
+
+/// Encodes m-vectors back-to-back, each packed into `ceil(m/2)` bytes.
+fn encode_m_vectors(m_vectors: &[GFVector]) -> Vec<u8> {
+    m_vectors.iter().flat_map(encode_gf_elements).collect()
+}
+
+/// Splits m-vectors (one per matrix entry) into the per-matrix element lists, so that
+/// `result[i]` holds the entries of the i-th matrix in the same order as `m_vectors`.
+fn transpose_m_vectors(m_vectors: &[GFVector], m: usize) -> Vec<GFVector> {
+    (0..m)
+        .map(|i| m_vectors.iter().map(|entry| entry[i]).collect())
+        .collect()
+}
+
+/// Decodes `m` full `rows x cols` matrices stored as row-major m-vectors.
+fn decode_full_matrices(bytes: &[u8], rows: usize, cols: usize, params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
+    let m_vectors = decode_m_vectors(bytes, rows * cols, params)?;
+    Ok(transpose_m_vectors(&m_vectors, params.m)
+        .into_iter()
+        .map(|elements| GFMatrix::new_with_data(rows, cols, elements))
+        .collect())
+}
+
+/// Decodes `m` upper triangular `size x size` matrices stored as m-vectors for each
+/// entry (r, c) with r <= c, in row-major order.
+fn decode_upper_triangular_matrices(bytes: &[u8], size: usize, params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
+    let m_vectors = decode_m_vectors(bytes, size * (size + 1) / 2, params)?;
+    transpose_m_vectors(&m_vectors, params.m)
+        .iter()
+        .map(|elements| decode_upper_triangular_matrix(elements, size))
+        .collect()
+}
+
 /// Decodes P1 matrices from byte representation.
 /// P1 consists of `m` matrices, each P(1)i is `(n-o) x (n-o)` and upper triangular.
-/// Assumes simple concatenation of the packed representations of each P(1)i.
-pub fn decode_p1_matrices(p1_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, &'static str> {
-    let size_p1_mat = params.n - params.o; // P(1)i is size_p1_mat x size_p1_mat
-    let num_elements_per_p1_mat_upper_tri = size_p1_mat * (size_p1_mat + 1) / 2;
-    let bytes_per_p1_mat = (num_elements_per_p1_mat_upper_tri + 1) / 2;
-    
-    if p1_bytes.len() != params.m * bytes_per_p1_mat {
-        // This check relies on params.p1_bytes being correctly set to m * bytes_per_p1_mat
-        // Or, if params.p1_bytes is the field from MayoVariantParams, then it should match p1_bytes.len().
-        // Let's assume p1_bytes.len() is the source of truth for the input, and params.p1_bytes is what it should be.
-        if p1_bytes.len() != params.p1_bytes {
-             return Err("p1_bytes length does not match params.p1_bytes field");
+/// Inverse of `decode_full_matrices`: interleaves the matrices entry by entry into
+/// m-vectors. The caller checks the number of matrices.
+fn encode_full_matrices(matrices: &[GFMatrix], rows: usize, cols: usize) -> Vec<u8> {
+    let mut m_vectors = Vec::with_capacity(rows * cols);
+    for r in 0..rows {
+        for c in 0..cols {
+            m_vectors.push(matrices.iter().map(|matrix| matrix.get_unsafe(r, c)).collect());
         }
-        if p1_bytes.len() % bytes_per_p1_mat != 0 || (p1_bytes.len() / bytes_per_p1_mat) != params.m {
-             return Err("p1_bytes length does not correspond to m P1 matrices");
+    }
+    encode_m_vectors(&m_vectors)
+}
+
+/// Inverse of `decode_upper_triangular_matrices`; entries below the diagonal are ignored.
+fn encode_upper_triangular_matrices(matrices: &[GFMatrix], size: usize) -> Vec<u8> {
+    let mut m_vectors = Vec::with_capacity(size * (size + 1) / 2);
+    for r in 0..size {
+        for c in r..size {
+            m_vectors.push(matrices.iter().map(|matrix| matrix.get_unsafe(r, c)).collect());
         }
     }
+    encode_m_vectors(&m_vectors)
+}
 
-    let mut p1_matrices = Vec::with_capacity(params.m);
-    for i in 0..params.m {
-        let start_byte = i * bytes_per_p1_mat;
-        let end_byte = start_byte + bytes_per_p1_mat;
-        let mat_bytes = &p1_bytes[start_byte..end_byte];
-        
-        let elements = decode_gf_elements(mat_bytes, num_elements_per_p1_mat_upper_tri)?;
-        let p1_mat = decode_upper_triangular_matrix(&elements, size_p1_mat)?;
-        p1_matrices.push(p1_mat);
+pub fn decode_p1_matrices(p1_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
+    if p1_bytes.len() != params.p1_bytes {
+        return Err(IdentityError::InvalidLength("p1_bytes length does not match params.p1_bytes field"));
     }
-    Ok(p1_matrices)
+    decode_upper_triangular_matrices(p1_bytes, params.v(), params)
 }
 
 /// Decodes P2 matrices from byte representation.
 /// P2 consists of `m` matrices, each P(2)i is `(n-o) x o`.
-pub fn decode_p2_matrices(p2_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, &'static str> {
-    let rows_p2 = params.n - params.o;
-    let cols_p2 = params.o;
-    let num_elements_per_p2_mat = rows_p2 * cols_p2;
-    let bytes_per_p2_mat = (num_elements_per_p2_mat + 1) / 2;
-
-    if p2_bytes.len() != params.p2_bytes {
-        return Err("p2_bytes length does not match params.p2_bytes field");
-    }
-    if p2_bytes.len() % bytes_per_p2_mat != 0 || (p2_bytes.len() / bytes_per_p2_mat) != params.m {
-        return Err("p2_bytes length does not correspond to m P2 matrices");
+pub fn encode_p1_matrices(p1_matrices: &[GFMatrix], params: &MayoVariantParams) -> Result<Vec<u8>, IdentityError> {
+    if p1_matrices.len() != params.m {
+        return Err(IdentityError::DimensionMismatch("Incorrect number of P1 matrices"));
     }
+    Ok(encode_upper_triangular_matrices(p1_matrices, params.v()))
+}
 
-    let mut p2_matrices = Vec::with_capacity(params.m);
-    for i in 0..params.m {
-        let start_byte = i * bytes_per_p2_mat;
-        let end_byte = start_byte + bytes_per_p2_mat;
-        let mat_bytes = &p2_bytes[start_byte..end_byte];
-
-        let elements = decode_gf_elements(mat_bytes, num_elements_per_p2_mat)?;
-        p2_matrices.push(GFMatrix::new_with_data(rows_p2, cols_p2, elements));
+pub fn decode_p2_matrices(p2_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
+    if p2_bytes.len() != params.p2_bytes {
+        return Err(IdentityError::InvalidLength("p2_bytes length does not match params.p2_bytes field"));
     }
-    Ok(p2_matrices)
+    decode_full_matrices(p2_bytes, params.v(), params.o, params)
 }
 
 /// Decodes P3 matrices from byte representation.
 /// P3 consists of `m` matrices, each P(3)i is `o x o` and upper triangular.
-pub fn decode_p3_matrices(p3_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, &'static str> {
-    let size_p3_mat = params.o; // P(3)i is o x o
-    let num_elements_per_p3_mat_upper_tri = size_p3_mat * (size_p3_mat + 1) / 2;
-    let bytes_per_p3_mat = (num_elements_per_p3_mat_upper_tri + 1) / 2;
+pub fn encode_p2_matrices(p2_matrices: &[GFMatrix], params: &MayoVariantParams) -> Result<Vec<u8>, IdentityError> {
+    if p2_matrices.len() != params.m {
+        return Err(IdentityError::DimensionMismatch("Incorrect number of P2 matrices"));
+    }
+    Ok(encode_full_matrices(p2_matrices, params.v(), params.o))
+}
 
+pub fn decode_p3_matrices(p3_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
     if p3_bytes.len() != params.p3_bytes {
-         return Err("p3_bytes length does not match params.p3_bytes field");
-    }
-    if p3_bytes.len() % bytes_per_p3_mat != 0 || (p3_bytes.len() / bytes_per_p3_mat) != params.m {
-         return Err("p3_bytes length does not correspond to m P3 matrices");
+        return Err(IdentityError::InvalidLength("p3_bytes length does not match params.p3_bytes field"));
     }
+    decode_upper_triangular_matrices(p3_bytes, params.o, params)
+}
 
-    let mut p3_matrices = Vec::with_capacity(params.m);
-    for i in 0..params.m {
-        let start_byte = i * bytes_per_p3_mat;
-        let end_byte = start_byte + bytes_per_p3_mat;
-        let mat_bytes = &p3_bytes[start_byte..end_byte];
-        
-        let elements = decode_gf_elements(mat_bytes, num_elements_per_p3_mat_upper_tri)?;
-        let p3_mat = decode_upper_triangular_matrix(&elements, size_p3_mat)?;
-        p3_matrices.push(p3_mat);
+/// Encodes P3 matrices (upper triangular `o x o`) into their byte representation.
+/// Entries below the diagonal are not encoded.
+pub fn encode_p3_matrices(p3_matrices: &[GFMatrix], params: &MayoVariantParams) -> Result<Vec<u8>, IdentityError> {
+    if p3_matrices.len() != params.m {
+        return Err(IdentityError::DimensionMismatch("Incorrect number of P3 matrices"));
     }
-    Ok(p3_matrices)
+    Ok(encode_upper_triangular_matrices(p3_matrices, params.o))
 }
 
-/// Decodes L matrices from byte representation. (Not typically stored/decoded directly in MAYO standard)
-/// L consists of `m` matrices, each Li is `(n-o) x (n-o)`.
-/// This function is provided as per subtask, but its usage in MAYO needs clarification.
-/// If L matrices are derived during verification and not directly part of keys/signatures,
-/// this might not be used in the main flow.
-pub fn decode_l_matrices(l_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, &'static str> {
-    // TODO: Confirm if L matrices are ever serialized and need direct decoding.
-    // The MAYO paper usually describes L as being computed during verification.
-    // If they are stored, their exact dimensions and structure (e.g. upper triangular?) must be known.
-    // Assuming they are full (n-o)x(n-o) matrices for now.
-    let rows_l = params.n - params.o;
-    let cols_l = params.n - params.o;
-    let num_elements_per_l_mat = rows_l * cols_l;
-    let bytes_per_l_mat = (num_elements_per_l_mat + 1) / 2;
-
-    // Assuming l_bytes.len() should correspond to m * bytes_per_l_mat.
-    // Need a params.l_bytes field or calculate it.
-    if l_bytes.len() % bytes_per_l_mat != 0 || (l_bytes.len() / bytes_per_l_mat) != params.m {
-        return Err("l_bytes length does not correspond to m L matrices of expected size");
+pub fn decode_l_matrices(l_bytes: &[u8], params: &MayoVariantParams) -> Result<Vec<GFMatrix>, IdentityError> {
+    if l_bytes.len() != params.l_bytes() {
+        return Err(IdentityError::InvalidLength("l_bytes length does not correspond to m L matrices of expected size"));
     }
-    
-    let mut l_matrices = Vec::with_capacity(params.m);
-    for i in 0..params.m {
-        let start_byte = i * bytes_per_l_mat;
-        let end_byte = start_byte + bytes_per_l_mat;
-        let mat_bytes = &l_bytes[start_byte..end_byte];
-
-        let elements = decode_gf_elements(mat_bytes, num_elements_per_l_mat)?;
-        l_matrices.push(GFMatrix::new_with_data(rows_l, cols_l, elements));
+    decode_full_matrices(l_bytes, params.v(), params.o, params)
+}
+
+/// Encodes L matrices (`(n-o) x o` each) into their byte representation.
+pub fn encode_l_matrices(l_matrices: &[GFMatrix], params: &MayoVariantParams) -> Result<Vec<u8>, IdentityError> {
+    if l_matrices.len() != params.m {
+        return Err(IdentityError::DimensionMismatch("Incorrect number of L matrices"));
     }
-    Ok(l_matrices)
+    Ok(encode_full_matrices(l_matrices, params.v(), params.o))
 }
 
 
-/// Encodes the solution vector `s` (a GFVector) into bytes.
-/// This is a thin wrapper around `encode_gf_elements`.
 pub fn encode_s_vector(s_vector: &GFVector, _params: &MayoVariantParams) -> Vec<u8> {
-    // s_vector should have length params.n
-    // assert_eq!(s_vector.len(), params.n, "s_vector length mismatch");
     encode_gf_elements(s_vector)
 }
 
-/// Decodes the solution vector `s` (a GFVector) from bytes.
-/// The length of `s` is `params.n`.
+/// Decodes the signature solution `s` (a GFVector of `n * k` elements) from bytes.
 /// This is a thin wrapper around `decode_gf_elements`.
-pub fn decode_s_vector(s_bytes: &[u8], params: &MayoVariantParams) -> Result<GFVector, &'static str> {
-    // s_bytes should have length (params.n+1)/2
-    // assert_eq!(s_bytes.len(), (params.n+1)/2, "s_bytes length mismatch");
-    decode_gf_elements(s_bytes, params.n)
+pub fn decode_s_vector(s_bytes: &[u8], params: &MayoVariantParams) -> Result<GFVector, IdentityError> {
+    decode_gf_elements(s_bytes, params.n * params.k)
 }
 
 
@@ -262,21 +278,21 @@ mod tests {
         // Even number of elements
         let elements1 = vec![gf(0x1), gf(0x2), gf(0x3), gf(0x4)];
         let encoded1 = encode_gf_elements(&elements1);
-        assert_eq!(encoded1, vec![0x12, 0x34]);
+        assert_eq!(encoded1, vec![0x21, 0x43]);
         let decoded1 = decode_gf_elements(&encoded1, elements1.len()).unwrap();
         assert_eq!(decoded1, elements1);
 
         // Odd number of elements
         let elements2 = vec![gf(0xA), gf(0xB), gf(0xC)];
         let encoded2 = encode_gf_elements(&elements2);
-        assert_eq!(encoded2, vec![0xAB, 0xC0]); // Last nibble zero-padded
+        assert_eq!(encoded2, vec![0xBA, 0x0C]); // High nibble zero-padded
         let decoded2 = decode_gf_elements(&encoded2, elements2.len()).unwrap();
         assert_eq!(decoded2, elements2);
         
         // Single element
         let elements3 = vec![gf(0x7)];
         let encoded3 = encode_gf_elements(&elements3);
-        assert_eq!(encoded3, vec![0x70]);
+        assert_eq!(encoded3, vec![0x07]);
         let decoded3 = decode_gf_elements(&encoded3, elements3.len()).unwrap();
         assert_eq!(decoded3, elements3);
 
@@ -294,13 +310,13 @@ mod tests {
 
     #[test]
     fn test_decode_o_matrix_simple() {
-        let params = MayoParams::mayo1().variant().clone(); // n=66, o=8. So O is 58x8.
-        let rows = params.n - params.o; // 58
+        let params = *MayoParams::mayo1().variant(); // n=86, o=8. So O is 78x8.
+        let rows = params.n - params.o; // 78
         let cols = params.o; // 8
-        let num_elements = rows * cols; // 58 * 8 = 464
-        let o_byte_len_expected = (num_elements + 1) / 2; // 232
+        let num_elements = rows * cols; // 78 * 8 = 624
+        let o_byte_len_expected = num_elements.div_ceil(2); // 312
         
-        // Check if params.o_bytes matches this. From Turn 37, MAYO1 o_bytes = 232. Correct.
+        // Check if params.o_bytes matches this.
         assert_eq!(params.o_bytes, o_byte_len_expected);
 
         let o_bytes_sample = vec![0x12; params.o_bytes]; // Sample data
@@ -311,8 +327,8 @@ mod tests {
         assert_eq!(o_matrix.num_rows(), rows);
         assert_eq!(o_matrix.num_cols(), cols);
         assert_eq!(o_matrix.data.len(), num_elements);
-        assert_eq!(o_matrix.get_unsafe(0,0), gf(1));
-        assert_eq!(o_matrix.get_unsafe(0,1), gf(2));
+        assert_eq!(o_matrix.get_unsafe(0,0), gf(2));
+        assert_eq!(o_matrix.get_unsafe(0,1), gf(1));
 
         let too_short_bytes = vec![0x12; params.o_bytes -1];
         assert!(decode_o_matrix(&too_short_bytes, &params).is_err());
@@ -343,96 +359,83 @@ mod tests {
 
     #[test]
     fn test_decode_p_matrices_structure() {
-        // Using MAYO1 parameters from Turn 37 for structural checks
-        // n=66, m=64, o=8, k=9
-        // p1_bytes: 960, p2_bytes: 14848, p3_bytes: 160
-        let params_variant = MayoParams::mayo1().variant().clone();
-
-        // P1: m=64 matrices, each (n-o)x(n-o) = 58x58 upper triangular
-        let size_p1 = params_variant.n - params_variant.o; // 58
-        let elems_p1_upper = size_p1 * (size_p1 + 1) / 2; // 58*59/2 = 1711
-        let bytes_p1_one_mat = (elems_p1_upper + 1) / 2;  // (1711+1)/2 = 856
-        assert_eq!(params_variant.p1_bytes, params_variant.m * bytes_p1_one_mat); // 64 * 856 = 54784. This is NOT 960.
-        // The p1_bytes=960 from Turn 37 is very small. It cannot hold m=64 matrices of 58x58 upper triangular.
-        // 960 bytes can hold 1920 nibbles. 1920 / 64 matrices = 30 nibbles per matrix.
-        // 30 nibbles is not enough for a 58x58 upper triangular matrix (needs 1711 nibbles).
-        // This indicates a mismatch in my understanding or the provided p1_bytes parameter.
-        // For now, the test will use the params.p1_bytes value and assume it's correct for some internal representation.
-        // The logic of decode_p1_matrices will be tested based on its structure.
-        // Let's assume p1_bytes is for a *different* representation, or a smaller example.
-        // For the purpose of testing `decode_p1_matrices` function structure, we need a p1_bytes that aligns.
-        // Let's use a dummy params for this specific test if needed, or acknowledge the discrepancy.
-        // The current code uses params.p1_bytes as the total length.
-        
-        // If params.p1_bytes is 960, and bytes_per_p1_mat is 856, then m cannot be 64.
-        // The test will fail if params.p1_bytes is not m * bytes_per_p1_mat.
-        // This is an issue with parameter consistency. The code for decode_p1_matrices *has* a check:
-        // if p1_bytes.len() != params.p1_bytes ...
-        // And: if p1_bytes.len() % bytes_per_p1_mat != 0 || (p1_bytes.len() / bytes_per_p1_mat) != params.m ...
-        // This means the provided params from Turn 37 (MAYO1 p1_bytes=960) ARE NOT CONSISTENT with m=64 and n-o=58.
-        // p1_bytes should be 54784.
-        // This is a critical issue for actual MAYO implementation.
-        // For this subtask, I will proceed testing the codec functions with the assumption that input bytes
-        // *could* be valid if parameters were consistent.
-
-        // P2: m=64 matrices, each (n-o)xo = 58x8
-        let rows_p2 = params_variant.n - params_variant.o; // 58
-        let cols_p2 = params_variant.o; // 8
-        let elems_p2_one_mat = rows_p2 * cols_p2; // 58*8 = 464
-        let bytes_p2_one_mat = (elems_p2_one_mat + 1)/2; // 232
-        assert_eq!(params_variant.p2_bytes, params_variant.m * bytes_p2_one_mat); // 64 * 232 = 14848. This matches.
+        // MAYO1: n=86, m=78, o=8, so v = n-o = 78.
+        let params_variant = *MayoParams::mayo1().variant();
+        let v = params_variant.v();
+        let o = params_variant.o;
+        let m_vec_bytes = params_variant.m_vec_bytes(); // 39
+
+        // P1: one m-vector per upper triangular entry of a v x v matrix.
+        assert_eq!(params_variant.p1_bytes, v * (v + 1) / 2 * m_vec_bytes);
+        // P2 and L: one m-vector per entry of a v x o matrix.
+        assert_eq!(params_variant.p2_bytes, v * o * m_vec_bytes);
+        assert_eq!(params_variant.l_bytes(), params_variant.p2_bytes);
+        // P3: one m-vector per upper triangular entry of an o x o matrix.
+        assert_eq!(params_variant.p3_bytes, o * (o + 1) / 2 * m_vec_bytes);
+
+        let p1_sample_bytes = vec![0x5A; params_variant.p1_bytes];
+        let p1_mats = decode_p1_matrices(&p1_sample_bytes, &params_variant).unwrap();
+        assert_eq!(p1_mats.len(), params_variant.m);
+        assert_eq!(p1_mats[0].num_rows(), v);
+        assert_eq!(p1_mats[0].num_cols(), v);
+        assert_eq!(p1_mats[0].get_unsafe(1, 0), gf(0)); // Lower part stays zero
+        assert!(decode_p1_matrices(&p1_sample_bytes[1..], &params_variant).is_err());
 
         let p2_sample_bytes = vec![0xAA; params_variant.p2_bytes];
         let p2_mats = decode_p2_matrices(&p2_sample_bytes, &params_variant).unwrap();
         assert_eq!(p2_mats.len(), params_variant.m);
-        assert_eq!(p2_mats[0].num_rows(), rows_p2);
-        assert_eq!(p2_mats[0].num_cols(), cols_p2);
-
-        // P3: m=64 matrices, each oxo = 8x8 upper triangular
-        let size_p3 = params_variant.o; // 8
-        let elems_p3_upper = size_p3 * (size_p3 + 1) / 2; // 8*9/2 = 36
-        let bytes_p3_one_mat = (elems_p3_upper + 1) / 2;  // (36+1)/2 = 18 (rounded up) -> 19 if strict, but (36+1)/2 = 18.5 -> 19 if it were 37. Let's recheck (36+1)/2 = 18. No, it's 18.
-                                                        // (num_elements + 1) / 2. For 36 elements, it's (36+1)/2 = 18.5 -> 19 if strict ceiling. Oh, it's integer division. (36+1)/2 = 18.
-                                                        // (36 elements -> 18 bytes).
-        assert_eq!(bytes_per_p1_mat, 856); // from above, just for reference
-        assert_eq!(bytes_p2_one_mat, 232); // from above
-        assert_eq!(bytes_p3_one_mat, 18);  // 36 nibbles is 18 bytes.
-
-        // Check params.p3_bytes: 160. params.m * bytes_p3_one_mat = 64 * 18 = 1152. This also does not match.
-        // p3_bytes from spec for MAYO1_PK is 160. This is not m * (bytes for one P3_i upper triangular matrix).
-        // This implies P3_bytes in params.rs might be a hash or a different form, not raw concatenated matrices.
-        // Or, the P3 matrices are not all stored, or stored differently.
-        // The subtask states "decode_p3_matrices ... P3 is {P(3)i}i∈[m]. Each P(3)i is o x o and upper triangular."
-        // This suggests the function *should* decode m such matrices.
-        // This is another parameter inconsistency.
-
-        // For L matrices (n-o)x(n-o) = 58x58
-        // If L are full matrices: elems_l_one_mat = 58*58 = 3364. bytes_l_one_mat = 1682.
-        // m * bytes_l_one_mat = 64 * 1682 = 107648.
-        // This is just a structural check for the function decode_l_matrices
-        let l_test_bytes_per_mat = ( (5*5)+1 )/2; // for a 5x5 matrix = 13 bytes
-        let l_test_m = 2;
-        let l_test_bytes = vec![0xFF; l_test_m * l_test_bytes_per_mat];
-        let mut l_dummy_params = params_variant.clone();
-        l_dummy_params.n = 5 + l_dummy_params.o; // so n-o = 5
-        l_dummy_params.m = l_test_m;
-        
-        let l_mats_res = decode_l_matrices(&l_test_bytes, &l_dummy_params);
-        assert!(l_mats_res.is_ok());
-        let l_mats = l_mats_res.unwrap();
-        assert_eq!(l_mats.len(), l_test_m);
-        assert_eq!(l_mats[0].num_rows(), 5);
-        assert_eq!(l_mats[0].num_cols(), 5);
+        assert_eq!(p2_mats[0].num_rows(), v);
+        assert_eq!(p2_mats[0].num_cols(), o);
+
+        let p3_sample_bytes = vec![0x33; params_variant.p3_bytes];
+        let p3_mats = decode_p3_matrices(&p3_sample_bytes, &params_variant).unwrap();
+        assert_eq!(p3_mats.len(), params_variant.m);
+        assert_eq!(p3_mats[0].num_rows(), o);
+        assert_eq!(p3_mats[0].num_cols(), o);
+
+        let l_mats = decode_l_matrices(&p2_sample_bytes, &params_variant).unwrap();
+        assert_eq!(l_mats.len(), params_variant.m);
+        assert_eq!(l_mats[0].num_rows(), v);
+        assert_eq!(l_mats[0].num_cols(), o);
+        assert!(decode_l_matrices(&p2_sample_bytes[1..], &params_variant).is_err());
+    }
+
+    #[test]
+    fn test_m_vector_layout() {
+        // Entry (r, c) of matrix i lives in nibble i of the m-vector for (r, c).
+        let params = *MayoParams::mayo2().variant(); // m = 64, o = 17
+        let mut p3_bytes = vec![0u8; params.p3_bytes];
+        // Second m-vector is entry (0, 1); set matrix 0 and matrix 1 of it.
+        p3_bytes[params.m_vec_bytes()] = 0x9C;
+        let p3_mats = decode_p3_matrices(&p3_bytes, &params).unwrap();
+        let mut expected = decode_gf_elements(&[0x9C], 2).unwrap();
+        assert_eq!(p3_mats[0].get_unsafe(0, 1), expected.remove(0));
+        assert_eq!(p3_mats[1].get_unsafe(0, 1), expected.remove(0));
+        assert_eq!(p3_mats[2].get_unsafe(0, 1), gf(0));
+        assert_eq!(p3_mats[0].get_unsafe(0, 0), gf(0));
+    }
+
+    #[test]
+    fn test_encode_decode_p3_and_l_roundtrip() {
+        let params = *MayoParams::mayo2().variant();
+        let p3_bytes: Vec<u8> = (0..params.p3_bytes).map(|i| (i * 7 + 3) as u8).collect();
+        let p3_mats = decode_p3_matrices(&p3_bytes, &params).unwrap();
+        assert_eq!(encode_p3_matrices(&p3_mats, &params).unwrap(), p3_bytes);
+        assert!(encode_p3_matrices(&p3_mats[1..], &params).is_err());
+
+        let l_bytes: Vec<u8> = (0..params.l_bytes()).map(|i| (i * 13 + 1) as u8).collect();
+        let l_mats = decode_l_matrices(&l_bytes, &params).unwrap();
+        assert_eq!(encode_l_matrices(&l_mats, &params).unwrap(), l_bytes);
     }
 
 
     #[test]
     fn test_encode_decode_s_vector() {
-        let params = MayoParams::mayo1().variant().clone(); // n=66
-        let s_vec_elements: GFVector = (0..(params.n)).map(|i| gf((i % 16) as u8)).collect();
+        let params = *MayoParams::mayo1().variant(); // n=86, k=10
+        let s_vec_elements: GFVector = (0..(params.n * params.k)).map(|i| gf((i % 16) as u8)).collect();
         
         let encoded_s = encode_s_vector(&s_vec_elements, &params);
-        let expected_bytes = (params.n + 1) / 2;
+        let expected_bytes = params.s_bytes();
         assert_eq!(encoded_s.len(), expected_bytes);
 
         let decoded_s_res = decode_s_vector(&encoded_s, &params);
@@ -442,4 +445,87 @@ mod tests {
         let short_bytes = vec![0u8; expected_bytes -1];
         assert!(decode_s_vector(&short_bytes, &params).is_err());
     }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// A MAYO variant and `len(variant)` arbitrary bytes.
+        fn variant_bytes(len: fn(&MayoVariantParams) -> usize) -> impl Strategy<Value = (MayoVariantParams, Vec<u8>)> {
+            (0..MayoParams::all().len()).prop_flat_map(move |i| {
+                let params = *MayoParams::all()[i].variant();
+                let bytes = proptest::collection::vec(any::<u8>(), len(&params));
+                (Just(params), bytes)
+            })
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(16))]
+
+            // Every MAYO variant has even m, (n-o)o and nk, so the encodings below have
+            // no padding nibble and decoding is a bijection on byte strings of the
+            // expected length: re-encoding gives back the input.
+
+            #[test]
+            fn o_matrix_roundtrip((params, bytes) in variant_bytes(|p| p.o_bytes)) {
+                let o_matrix = decode_o_matrix(&bytes, &params).unwrap();
+                prop_assert_eq!(encode_o_matrix(&o_matrix, &params).unwrap(), bytes);
+            }
+
+            #[test]
+            fn p1_matrices_roundtrip((params, bytes) in variant_bytes(|p| p.p1_bytes)) {
+                let p1 = decode_p1_matrices(&bytes, &params).unwrap();
+                prop_assert!(p1.iter().all(|p1_i| (0..p1_i.num_rows()).all(|r| (0..r).all(|c| p1_i.get_unsafe(r, c).0 == 0))));
+                prop_assert_eq!(encode_p1_matrices(&p1, &params).unwrap(), bytes);
+            }
+
+            #[test]
+            fn p2_matrices_roundtrip((params, bytes) in variant_bytes(|p| p.p2_bytes)) {
+                let p2 = decode_p2_matrices(&bytes, &params).unwrap();
+                prop_assert_eq!(encode_p2_matrices(&p2, &params).unwrap(), bytes);
+            }
+
+            #[test]
+            fn p3_matrices_roundtrip((params, bytes) in variant_bytes(|p| p.p3_bytes)) {
+                let p3 = decode_p3_matrices(&bytes, &params).unwrap();
+                prop_assert_eq!(encode_p3_matrices(&p3, &params).unwrap(), bytes);
+            }
+
+            #[test]
+            fn l_matrices_roundtrip((params, bytes) in variant_bytes(|p| p.l_bytes())) {
+                let l = decode_l_matrices(&bytes, &params).unwrap();
+                prop_assert_eq!(encode_l_matrices(&l, &params).unwrap(), bytes);
+            }
+
+            #[test]
+            fn s_vector_roundtrip((params, bytes) in variant_bytes(|p| p.s_bytes())) {
+                let s = decode_s_vector(&bytes, &params).unwrap();
+                prop_assert_eq!(s.len(), params.n * params.k);
+                prop_assert_eq!(encode_s_vector(&s, &params), bytes);
+            }
+
+            #[test]
+            fn decoders_reject_wrong_lengths_without_panicking((params, bytes) in variant_bytes(|p| p.p3_bytes + 1)) {
+                // Too long for P3 and, by one byte or more, the wrong length for the
+                // other exact-length decoders.
+                prop_assert!(decode_p3_matrices(&bytes, &params).is_err());
+                prop_assert!(decode_p3_matrices(&bytes[..bytes.len() - 2], &params).is_err());
+                prop_assert!(decode_p1_matrices(&bytes, &params).is_err());
+                prop_assert!(decode_p2_matrices(&bytes, &params).is_err());
+                prop_assert!(decode_l_matrices(&bytes, &params).is_err());
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn gf_elements_roundtrip(elements in proptest::collection::vec((0u8..16).prop_map(GFElement), 0..200)) {
+                let bytes = encode_gf_elements(&elements);
+                prop_assert_eq!(bytes.len(), elements.len().div_ceil(2));
+                prop_assert_eq!(decode_gf_elements(&bytes, elements.len()).unwrap(), elements.clone());
+                if !elements.is_empty() {
+                    prop_assert!(decode_gf_elements(&bytes[..bytes.len() - 1], elements.len()).is_err());
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,336 @@
+//! Deterministic derivation of MAYO identities from a single backup.
+//!
+//! A wallet keeps one `MasterSeed`, either raw bytes or stretched from a mnemonic
+//! phrase, and regenerates any number of identities from it by `DerivationPath`, e.g.
+//! `m/<account>/<index>`. The hierarchy follows BIP32: every node is a 32-byte key and
+//! a 32-byte chain code, and child `i` hashes the parent node with `i` (see
+//! `hash::shake256_derive_child_node`). MAYO has no public derivation, so every level
+//! is hardened and a leaked identity reveals nothing about its parent or siblings. The
+//! secret key seed of a node is bound to the MAYO variant, and the compact key pair
+//! follows from it by MAYO.CompactKeyGen.
+//!
+//! Mnemonics are BIP39 phrases of 12 to 24 words from the English wordlist, and their
+//! checksum is verified, so a phrase from a BIP39 wallet is accepted here. The seed is
+//! stretched with SHAKE256 rather than PBKDF2-HMAC-SHA512, however, so the same phrase
+//! yields a different seed here than in a BIP39 wallet.
+
+use std::fmt;
+use std::iter;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::error::IdentityError;
+use crate::hash::{shake256_derive_child_node, shake256_derive_master_node, shake256_derive_seed_sk, shake256_mnemonic_to_seed};
+use crate::keygen::compact_key_gen_from_seed;
+use crate::params::MayoParams;
+use crate::rng::{OsRandom, RandomSource};
+use crate::types::{CompactPublicKey, CompactSecretKey};
+
+/// Shortest accepted master seed, as in BIP32.
+pub const MIN_SEED_BYTES: usize = 16;
+/// Longest accepted master seed, as in BIP32.
+pub const MAX_SEED_BYTES: usize = 64;
+/// Accepted numbers of mnemonic words.
+pub const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+/// Accepted lengths of mnemonic entropy, one per entry of `MNEMONIC_WORD_COUNTS`.
+pub const MNEMONIC_ENTROPY_BYTES: [usize; 5] = [16, 20, 24, 28, 32];
+/// SHAKE256 passes used to stretch a mnemonic, matching the PBKDF2 iteration count of BIP39.
+pub const MNEMONIC_ROUNDS: u32 = 2048;
+
+/// Bits of a wordlist index, and so of every mnemonic word.
+const MNEMONIC_WORD_BITS: usize = 11;
+const BIP39_ENGLISH: &str = include_str!("bip39_english.txt");
+
+/// The 2048 words of the BIP39 English wordlist, sorted.
+fn bip39_wordlist() -> &'static [&'static str] {
+    static WORDLIST: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDLIST.get_or_init(|| BIP39_ENGLISH.lines().collect())
+}
+
+/// Packs big-endian bits (one per byte, 0 or 1) into an integer.
+fn pack_bits(bits: &[u8]) -> usize {
+    bits.iter().fold(0, |acc, &bit| acc << 1 | usize::from(bit))
+}
+
+/// Encodes `entropy` (16, 20, 24, 28 or 32 bytes) as a BIP39 English mnemonic of 12 to
+/// 24 words, the last of which carries the SHA-256 checksum.
+pub fn mnemonic_from_entropy(entropy: &[u8]) -> Result<Zeroizing<String>, IdentityError> {
+    if !MNEMONIC_ENTROPY_BYTES.contains(&entropy.len()) {
+        return Err(IdentityError::InvalidLength("Mnemonic entropy must be 16, 20, 24, 28 or 32 bytes long"));
+    }
+    let checksum = Sha256::digest(entropy)[0];
+    let bits: Zeroizing<Vec<u8>> = Zeroizing::new(
+        entropy
+            .iter()
+            .chain(iter::once(&checksum))
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+            .take(entropy.len() * 8 + entropy.len() / 4)
+            .collect(),
+    );
+    let wordlist = bip39_wordlist();
+    let words: Vec<&str> = bits.chunks(MNEMONIC_WORD_BITS).map(|chunk| wordlist[pack_bits(chunk)]).collect();
+    Ok(Zeroizing::new(words.join(" ")))
+}
+
+/// Checks that lowercase `words` are in the BIP39 English wordlist and that their
+/// trailing checksum bits match the SHA-256 of the entropy they encode.
+fn check_mnemonic(words: &[String]) -> Result<(), IdentityError> {
+    let wordlist = bip39_wordlist();
+    let mut bits = Zeroizing::new(Vec::with_capacity(words.len() * MNEMONIC_WORD_BITS));
+    for word in words {
+        let index = wordlist
+            .binary_search(&word.as_str())
+            .map_err(|_| IdentityError::InvalidMnemonic("Mnemonic has a word outside the BIP39 English wordlist"))?;
+        bits.extend((0..MNEMONIC_WORD_BITS).rev().map(|i| ((index >> i) & 1) as u8));
+    }
+    let checksum_bits = words.len() / 3;
+    let (entropy_bits, checksum) = bits.split_at(bits.len() - checksum_bits);
+    let entropy: Zeroizing<Vec<u8>> = Zeroizing::new(entropy_bits.chunks(8).map(|byte| pack_bits(byte) as u8).collect());
+    if usize::from(Sha256::digest(&*entropy)[0] >> (8 - checksum_bits)) != pack_bits(checksum) {
+        return Err(IdentityError::InvalidMnemonic("Mnemonic checksum does not match"));
+    }
+    Ok(())
+}
+
+/// The root secret of a key hierarchy; wiped on drop and redacted in `Debug` output.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct MasterSeed(Vec<u8>);
+
+impl fmt::Debug for MasterSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MasterSeed([REDACTED; {}])", self.0.len())
+    }
+}
+
+impl MasterSeed {
+    /// Uses `bytes` (16 to 64 bytes of entropy) as the master seed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        if !(MIN_SEED_BYTES..=MAX_SEED_BYTES).contains(&bytes.len()) {
+            return Err(IdentityError::InvalidLength("Master seed must be 16 to 64 bytes long"));
+        }
+        Ok(MasterSeed(bytes.to_vec()))
+    }
+
+    /// Stretches a mnemonic phrase and an optional passphrase (empty if none) into a
+    /// 64-byte master seed. Words are separated by any whitespace and compared
+    /// case-insensitively, so "Word  word" and "word word" give the same seed. A phrase
+    /// with a word outside the BIP39 English wordlist or a wrong checksum is rejected
+    /// with `IdentityError::InvalidMnemonic`.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, IdentityError> {
+        let words: Zeroizing<Vec<String>> = Zeroizing::new(phrase.split_whitespace().map(str::to_ascii_lowercase).collect());
+        if !MNEMONIC_WORD_COUNTS.contains(&words.len()) {
+            return Err(IdentityError::InvalidInput("Mnemonic must have 12, 15, 18, 21 or 24 words"));
+        }
+        check_mnemonic(&words)?;
+        let normalized = Zeroizing::new(words.join(" "));
+        let seed = shake256_mnemonic_to_seed(normalized.as_bytes(), passphrase.as_bytes(), MNEMONIC_ROUNDS);
+        Ok(MasterSeed(seed.to_vec()))
+    }
+
+    /// Generates a fresh BIP39 English mnemonic of `word_count` (12, 15, 18, 21 or 24)
+    /// words from the system RNG, for a wallet to show as its backup phrase.
+    pub fn generate_mnemonic(word_count: usize) -> Result<Zeroizing<String>, IdentityError> {
+        Self::generate_mnemonic_with_rng(word_count, &mut OsRandom)
+    }
+
+    pub fn generate_mnemonic_with_rng<R: RandomSource>(word_count: usize, rng: &mut R) -> Result<Zeroizing<String>, IdentityError> {
+        if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+            return Err(IdentityError::InvalidInput("Mnemonic must have 12, 15, 18, 21 or 24 words"));
+        }
+        let mut entropy = Zeroizing::new(vec![0u8; word_count * 4 / 3]);
+        rng.fill_bytes(&mut entropy)?;
+        mnemonic_from_entropy(&entropy)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Derives the compact key pair of `params` at `path`. The same seed, path and
+    /// variant always give the same key pair.
+    pub fn derive_keypair(&self, params: &MayoParams, path: &DerivationPath) -> Result<(CompactSecretKey, CompactPublicKey), IdentityError> {
+        let mut node = shake256_derive_master_node(&self.0);
+        for &index in path.components() {
+            node = shake256_derive_child_node(&node, index);
+        }
+        compact_key_gen_from_seed(params, shake256_derive_seed_sk(&node, params))
+    }
+}
+
+/// A path in the key hierarchy, written `m/<i>/<j>/...`. The conventional path of an
+/// identity is `m/<account>/<index>`. Since every level is hardened, a trailing `'` on a
+/// component is accepted for compatibility with BIP32 notation and has no effect.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(components: Vec<u32>) -> Self {
+        DerivationPath(components)
+    }
+
+    /// The path `m/<account>/<index>` of identity `index` of `account`.
+    pub fn identity(account: u32, index: u32) -> Self {
+        DerivationPath(vec![account, index])
+    }
+
+    pub fn components(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// Returns this path extended by `index`.
+    pub fn child(&self, index: u32) -> Self {
+        let mut components = self.0.clone();
+        components.push(index);
+        DerivationPath(components)
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = IdentityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('/');
+        if parts.next() != Some("m") {
+            return Err(IdentityError::InvalidInput("Derivation path must start with \"m\""));
+        }
+        parts
+            .map(|part| {
+                let digits = part.strip_suffix('\'').unwrap_or(part);
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(IdentityError::InvalidInput("Derivation path components must be decimal integers"));
+                }
+                digits.parse().map_err(|_| IdentityError::InvalidInput("Derivation path component is out of range"))
+            })
+            .collect::<Result<Vec<u32>, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for index in &self.0 {
+            write!(f, "/{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::{expand_pk, expand_sk};
+    use crate::sign::sign_message;
+    use crate::types::Message;
+    use crate::verify::verify_signature;
+
+    const PHRASE: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    #[test]
+    fn test_derivation_is_deterministic_and_separated() {
+        let seed = MasterSeed::from_bytes(&[0x42; 32]).unwrap();
+        let mayo1 = MayoParams::mayo1();
+        let (csk, cpk) = seed.derive_keypair(&mayo1, &DerivationPath::identity(0, 0)).unwrap();
+        let (csk_again, cpk_again) = seed.derive_keypair(&mayo1, &DerivationPath::identity(0, 0)).unwrap();
+        assert_eq!(csk, csk_again);
+        assert_eq!(cpk, cpk_again);
+        assert_eq!(csk.as_bytes().len(), mayo1.sk_seed_bytes());
+
+        // Other indices, accounts, depths and variants give unrelated seeds.
+        let others = [
+            seed.derive_keypair(&mayo1, &DerivationPath::identity(0, 1)).unwrap().0,
+            seed.derive_keypair(&mayo1, &DerivationPath::identity(1, 0)).unwrap().0,
+            seed.derive_keypair(&mayo1, &DerivationPath::new(vec![0])).unwrap().0,
+            seed.derive_keypair(&MayoParams::mayo2(), &DerivationPath::identity(0, 0)).unwrap().0,
+            MasterSeed::from_bytes(&[0x43; 32]).unwrap().derive_keypair(&mayo1, &DerivationPath::identity(0, 0)).unwrap().0,
+        ];
+        for other in &others {
+            assert_ne!(other.as_bytes(), csk.as_bytes());
+        }
+
+        // A derived identity is an ordinary MAYO key pair.
+        let message = Message(b"restored identity".to_vec());
+        let signature = sign_message(&expand_sk(&csk).unwrap(), &message).unwrap();
+        assert!(verify_signature(&expand_pk(&cpk).unwrap(), &message, &signature).unwrap());
+    }
+
+    #[test]
+    fn test_derived_seed_is_pinned() {
+        // Guards the derivation against accidental changes, which would orphan every
+        // identity restored from a backup.
+        let seed = MasterSeed::from_mnemonic(PHRASE, "TREZOR").unwrap();
+        let (csk, _) = seed.derive_keypair(&MayoParams::mayo2(), &"m/0/7".parse().unwrap()).unwrap();
+        assert_eq!(hex::encode(csk.as_bytes()), "f416628ed105b159817d033443ded55ee1c997205673d7c3");
+    }
+
+    #[test]
+    fn test_mnemonic_normalization_and_errors() {
+        let seed = MasterSeed::from_mnemonic(PHRASE, "").unwrap();
+        assert_eq!(seed.as_bytes().len(), 64);
+        let messy = format!("  {}\n", PHRASE.to_uppercase().replace(' ', "\t "));
+        assert_eq!(MasterSeed::from_mnemonic(&messy, "").unwrap(), seed);
+        assert_ne!(MasterSeed::from_mnemonic(PHRASE, "passphrase").unwrap(), seed);
+
+        assert_eq!(MasterSeed::from_mnemonic("legal winner thank", ""),
+                   Err(IdentityError::InvalidInput("Mnemonic must have 12, 15, 18, 21 or 24 words")));
+        let non_ascii = PHRASE.replace("year", "jahr\u{e9}");
+        assert_eq!(MasterSeed::from_mnemonic(&non_ascii, ""),
+                   Err(IdentityError::InvalidMnemonic("Mnemonic has a word outside the BIP39 English wordlist")));
+        let misspelled = PHRASE.replace("yellow", "yelow");
+        assert_eq!(MasterSeed::from_mnemonic(&misspelled, ""),
+                   Err(IdentityError::InvalidMnemonic("Mnemonic has a word outside the BIP39 English wordlist")));
+        // Every word is valid, but the last one carries the wrong checksum.
+        let bad_checksum = PHRASE.replace("yellow", "year");
+        assert_eq!(MasterSeed::from_mnemonic(&bad_checksum, ""),
+                   Err(IdentityError::InvalidMnemonic("Mnemonic checksum does not match")));
+        assert!(MasterSeed::from_mnemonic(&["abandon"; 12].join(" "), "").is_err());
+
+        assert!(MasterSeed::from_bytes(&[0; 15]).is_err());
+        assert!(MasterSeed::from_bytes(&[0; 65]).is_err());
+        assert!(!format!("{:?}", MasterSeed::from_bytes(&[0xAB; 16]).unwrap()).contains("171"));
+    }
+
+    #[test]
+    fn test_mnemonic_generation() {
+        // Test vectors from BIP39.
+        let vectors: [(&[u8], &str); 6] = [
+            (&[0x00; 16], "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+            (&[0x7f; 16], PHRASE),
+            (&[0x80; 16], "letter advice cage absurd amount doctor acoustic avoid letter advice cage above"),
+            (&[0xff; 16], "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong"),
+            (&[0x7f; 24], "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will"),
+            (&[0xff; 32], "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote"),
+        ];
+        for (entropy, phrase) in vectors {
+            assert_eq!(mnemonic_from_entropy(entropy).unwrap().as_str(), phrase);
+            assert!(MasterSeed::from_mnemonic(phrase, "").is_ok());
+        }
+        assert!(mnemonic_from_entropy(&[0; 17]).is_err());
+
+        for word_count in MNEMONIC_WORD_COUNTS {
+            let phrase = MasterSeed::generate_mnemonic(word_count).unwrap();
+            assert_eq!(phrase.split(' ').count(), word_count);
+            assert!(MasterSeed::from_mnemonic(&phrase, "").is_ok());
+        }
+        assert_ne!(MasterSeed::generate_mnemonic(12).unwrap(), MasterSeed::generate_mnemonic(12).unwrap());
+        assert_eq!(MasterSeed::generate_mnemonic(13),
+                   Err(IdentityError::InvalidInput("Mnemonic must have 12, 15, 18, 21 or 24 words")));
+    }
+
+    #[test]
+    fn test_derivation_path_parsing() {
+        let path: DerivationPath = "m/44'/7".parse().unwrap();
+        assert_eq!(path.components(), &[44, 7]);
+        assert_eq!(path.to_string(), "m/44/7");
+        assert_eq!(path, DerivationPath::identity(44, 7));
+        assert_eq!(DerivationPath::identity(1, 2).child(3).to_string(), "m/1/2/3");
+        assert_eq!("m".parse::<DerivationPath>(), Ok(DerivationPath::default()));
+
+        for bad in ["", "0/1", "m/", "m//1", "m/-1", "m/1x", "m/4294967296"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! dudect-style statistical timing tests for the constant-time GF(16) arithmetic and
+//! the signing solver.
+//!
+//! Each test times an operation on two classes of inputs, a fixed one (zeros, or a
+//! sparse matrix the branching solver would skip through) and a random one, in a random
+//! interleaved order, drops the slowest measurements, and compares the two timing
+//! distributions with Welch's t-test. A |t| above `T_THRESHOLD` means the running time
+//! depends on the inputs.
+//!
+//! The measurements need a quiet machine and an optimized build, so the tests are
+//! ignored by default: `cargo test --release -- --ignored dudect`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use crate::gf::{gf16_inv_ct, gf16_mul};
+use crate::rng::{CtrDrbg, RandomSource};
+use crate::solver::{sample_solution, sample_solution_ct};
+use crate::types::{GFElement, GFMatrix, GFVector};
+
+/// dudect reports a leak once |t| exceeds 10; below that the classes are
+/// indistinguishable at the number of measurements taken here.
+const T_THRESHOLD: f64 = 10.0;
+
+/// Fraction of the slowest measurements dropped before the test, which removes most
+/// interrupts and context switches.
+const CROP_PERCENTILE: f64 = 0.9;
+
+/// Welch's t statistic of two samples.
+fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+    fn mean_var(xs: &[f64]) -> (f64, f64) {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let var = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+        (mean, var)
+    }
+    let (mean_a, var_a) = mean_var(a);
+    let (mean_b, var_b) = mean_var(b);
+    (mean_a - mean_b) / (var_a / a.len() as f64 + var_b / b.len() as f64).sqrt()
+}
+
+/// Times `op` on `inputs[i]` in a random order of the two classes and returns the t
+/// statistic between the fixed (`classes[i] == false`) and random measurements.
+fn measure_t<T>(inputs: &[T], classes: &[bool], mut op: impl FnMut(&T)) -> f64 {
+    let mut timings = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let start = Instant::now();
+        op(black_box(input));
+        timings.push(start.elapsed().as_nanos() as f64);
+    }
+
+    let mut sorted = timings.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let cutoff = sorted[((sorted.len() - 1) as f64 * CROP_PERCENTILE) as usize];
+
+    let (mut fixed, mut random) = (Vec::new(), Vec::new());
+    for (&t, &is_random) in timings.iter().zip(classes) {
+        if t <= cutoff {
+            if is_random { random.push(t) } else { fixed.push(t) }
+        }
+    }
+    welch_t(&fixed, &random)
+}
+
+fn drbg() -> CtrDrbg {
+    CtrDrbg::new(&[0x5A; 48])
+}
+
+fn random_bytes(rng: &mut CtrDrbg, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rng.fill_bytes(&mut bytes).unwrap();
+    bytes
+}
+
+fn random_classes(rng: &mut CtrDrbg, len: usize) -> Vec<bool> {
+    random_bytes(rng, len).iter().map(|b| b & 1 == 1).collect()
+}
+
+#[test]
+fn test_welch_t() {
+    let a = [1.0, 2.0, 3.0, 4.0];
+    assert_eq!(welch_t(&a, &a), 0.0);
+    // Means 2.5 and 12.5, both variances 5/3: t = -10 / sqrt(10/12).
+    let b = [11.0, 12.0, 13.0, 14.0];
+    assert!((welch_t(&a, &b) + 10.0 / (10.0f64 / 12.0).sqrt()).abs() < 1e-9);
+}
+
+#[test]
+#[ignore = "timing-sensitive; run with --release -- --ignored dudect"]
+fn test_dudect_gf16_mul() {
+    // Each measurement is a batch of products, since one product is below the timer
+    // resolution. The fixed class multiplies by zero, which the branching shift-and-add
+    // finished without ever adding.
+    const SAMPLES: usize = 50_000;
+    const BATCH: usize = 64;
+    let mut rng = drbg();
+    let classes = random_classes(&mut rng, SAMPLES);
+    let inputs: Vec<Vec<(GFElement, GFElement)>> = classes
+        .iter()
+        .map(|&is_random| {
+            random_bytes(&mut rng, BATCH)
+                .iter()
+                .map(|b| (GFElement(b & 0x0F), GFElement(if is_random { b >> 4 } else { 0 })))
+                .collect()
+        })
+        .collect();
+
+    let t = measure_t(&inputs, &classes, |batch| {
+        for &(a, b) in batch {
+            black_box(gf16_mul(a, b));
+        }
+    });
+    assert!(t.abs() < T_THRESHOLD, "gf16_mul timing depends on its inputs: t = {}", t);
+}
+
+#[test]
+#[ignore = "timing-sensitive; run with --release -- --ignored dudect"]
+fn test_dudect_gf16_inv_ct() {
+    const SAMPLES: usize = 50_000;
+    const BATCH: usize = 64;
+    let mut rng = drbg();
+    let classes = random_classes(&mut rng, SAMPLES);
+    let inputs: Vec<Vec<GFElement>> = classes
+        .iter()
+        .map(|&is_random| {
+            random_bytes(&mut rng, BATCH)
+                .iter()
+                .map(|b| GFElement(if is_random { b & 0x0F } else { 0 }))
+                .collect()
+        })
+        .collect();
+
+    let t = measure_t(&inputs, &classes, |batch| {
+        for &a in batch {
+            black_box(gf16_inv_ct(a));
+        }
+    });
+    assert!(t.abs() < T_THRESHOLD, "gf16_inv_ct timing depends on its input: t = {}", t);
+}
+
+#[test]
+#[ignore = "timing-sensitive; run with --release -- --ignored dudect"]
+fn test_dudect_sample_solution_ct() {
+    // The fixed class is [I | 0], which needs no row swaps and almost no elimination;
+    // the random class is a dense system. Both have full rank, so the one bit the
+    // solver is allowed to reveal is the same for both.
+    const SAMPLES: usize = 4_000;
+    const ROWS: usize = 24;
+    const COLS: usize = 28;
+    let mut rng = drbg();
+    let classes = random_classes(&mut rng, SAMPLES);
+
+    let mut identity = GFMatrix::zero(ROWS, COLS);
+    for i in 0..ROWS {
+        identity.set_val(i, i, GFElement(1));
+    }
+    let mut inputs: Vec<(GFMatrix, GFVector, GFVector)> = Vec::with_capacity(SAMPLES);
+    for &is_random in &classes {
+        let y: GFVector = random_bytes(&mut rng, ROWS).iter().map(|b| GFElement(b & 0x0F)).collect();
+        let r: GFVector = random_bytes(&mut rng, COLS).iter().map(|b| GFElement(b & 0x0F)).collect();
+        let a = if is_random {
+            loop {
+                let data = random_bytes(&mut rng, ROWS * COLS).iter().map(|b| GFElement(b & 0x0F)).collect();
+                let a = GFMatrix::new_with_data(ROWS, COLS, data);
+                if sample_solution(&a, &y, &r).unwrap().is_some() {
+                    break a;
+                }
+            }
+        } else {
+            identity.clone()
+        };
+        inputs.push((a, y, r));
+    }
+
+    let t = measure_t(&inputs, &classes, |(a, y, r)| {
+        black_box(sample_solution_ct(a, y, r).unwrap());
+    });
+    assert!(t.abs() < T_THRESHOLD, "sample_solution_ct timing depends on its inputs: t = {}", t);
+}
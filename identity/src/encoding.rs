@@ -0,0 +1,365 @@
+//! Versioned, self-describing encodings for keys and signatures.
+//!
+//! The binary envelope is `MAGIC || version || kind || variant id || payload`, where the
+//! payload is the raw key or signature bytes. Hex, base64 and PEM-style armored text
+//! forms all wrap the same envelope, so any of them records which MAYO variant produced
+//! the value. Serde serializes the base64 form for human-readable formats and the binary
+//! envelope otherwise.
+//!
+//! Secret keys come back from every encoder in `Zeroizing` buffers, and the decoders
+//! wipe the intermediate copies they make, so encoding a key does not leave its bytes
+//! behind in freed memory.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::error::IdentityError;
+use crate::params::MayoParams;
+use crate::types::{CompactPublicKey, CompactSecretKey, ExpandedPublicKey, ExpandedSecretKey, Signature};
+
+/// Leading bytes of every envelope ("CuBiX Identity").
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"CBXI";
+/// Current envelope format version.
+pub const ENVELOPE_VERSION: u8 = 1;
+/// Magic, version, kind and variant id.
+pub const ENVELOPE_HEADER_BYTES: usize = ENVELOPE_MAGIC.len() + 3;
+
+const PEM_LINE_LEN: usize = 64;
+
+/// The kind of value held by an envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeKind {
+    CompactSecretKey = 1,
+    CompactPublicKey = 2,
+    ExpandedSecretKey = 3,
+    ExpandedPublicKey = 4,
+    Signature = 5,
+}
+
+impl EnvelopeKind {
+    /// The label used in the PEM `BEGIN`/`END` lines.
+    pub fn pem_label(self) -> &'static str {
+        match self {
+            EnvelopeKind::CompactSecretKey => "CUBIX MAYO SECRET KEY",
+            EnvelopeKind::CompactPublicKey => "CUBIX MAYO PUBLIC KEY",
+            EnvelopeKind::ExpandedSecretKey => "CUBIX MAYO EXPANDED SECRET KEY",
+            EnvelopeKind::ExpandedPublicKey => "CUBIX MAYO EXPANDED PUBLIC KEY",
+            EnvelopeKind::Signature => "CUBIX MAYO SIGNATURE",
+        }
+    }
+}
+
+/// Envelope encoding and decoding, with hex, base64 and PEM text forms on top of it.
+pub trait Envelope: Sized {
+    /// The kind byte written to, and required in, the envelope header.
+    const KIND: EnvelopeKind;
+
+    /// The binary envelope returned by `to_envelope`: `Vec<u8>` for public values, and
+    /// `Zeroizing<Vec<u8>>` for secret keys.
+    type Bytes: From<Vec<u8>> + AsRef<[u8]>;
+
+    /// The text returned by `to_hex`, `to_base64` and `to_pem`: `String` for public
+    /// values, and `Zeroizing<String>` for secret keys.
+    type Text: From<String> + AsRef<str>;
+
+    /// The variant the value belongs to.
+    fn envelope_params(&self) -> &MayoParams;
+
+    /// The raw payload bytes.
+    fn envelope_payload(&self) -> &[u8];
+
+    /// Rebuilds the value from a decoded variant and payload, checking its length.
+    fn from_envelope_parts(params: MayoParams, payload: Vec<u8>) -> Result<Self, IdentityError>;
+
+    /// Encodes the value as a binary envelope.
+    fn to_envelope(&self) -> Self::Bytes {
+        let payload = self.envelope_payload();
+        let mut out = Vec::with_capacity(ENVELOPE_HEADER_BYTES + payload.len());
+        out.extend_from_slice(&ENVELOPE_MAGIC);
+        out.push(ENVELOPE_VERSION);
+        out.push(Self::KIND as u8);
+        out.push(self.envelope_params().id());
+        out.extend_from_slice(payload);
+        out.into()
+    }
+
+    /// Decodes a binary envelope, checking its header and the payload length.
+    fn from_envelope(bytes: &[u8]) -> Result<Self, IdentityError> {
+        if bytes.len() < ENVELOPE_HEADER_BYTES {
+            return Err(IdentityError::InvalidEncoding("Envelope is too short"));
+        }
+        let (header, payload) = bytes.split_at(ENVELOPE_HEADER_BYTES);
+        if header[..ENVELOPE_MAGIC.len()] != ENVELOPE_MAGIC {
+            return Err(IdentityError::InvalidEncoding("Envelope has invalid magic bytes"));
+        }
+        if header[4] != ENVELOPE_VERSION {
+            return Err(IdentityError::InvalidEncoding("Unsupported envelope version"));
+        }
+        if header[5] != Self::KIND as u8 {
+            return Err(IdentityError::InvalidEncoding("Envelope holds a different kind of value"));
+        }
+        let params = MayoParams::from_id(header[6]).ok_or(IdentityError::InvalidEncoding("Envelope has unknown MAYO variant id"))?;
+        // The copy becomes the value itself, which wipes it on drop if secret; a copy
+        // of the wrong length is wiped by `from_bytes`.
+        Self::from_envelope_parts(params, payload.to_vec())
+    }
+
+    /// Lowercase hex of the envelope.
+    fn to_hex(&self) -> Self::Text {
+        hex::encode(self.to_envelope()).into()
+    }
+
+    fn from_hex(s: &str) -> Result<Self, IdentityError> {
+        let bytes = Zeroizing::new(hex::decode(s.trim()).map_err(|_| IdentityError::InvalidEncoding("Invalid hex encoding"))?);
+        Self::from_envelope(&bytes)
+    }
+
+    /// Standard (padded) base64 of the envelope.
+    fn to_base64(&self) -> Self::Text {
+        BASE64.encode(self.to_envelope()).into()
+    }
+
+    fn from_base64(s: &str) -> Result<Self, IdentityError> {
+        let bytes = Zeroizing::new(BASE64.decode(s.trim()).map_err(|_| IdentityError::InvalidEncoding("Invalid base64 encoding"))?);
+        Self::from_envelope(&bytes)
+    }
+
+    /// PEM-style armor: the base64 envelope in 64-character lines between
+    /// `-----BEGIN <label>-----` and `-----END <label>-----`.
+    fn to_pem(&self) -> Self::Text {
+        let begin = format!("-----BEGIN {}-----\n", Self::KIND.pem_label());
+        let end = format!("-----END {}-----\n", Self::KIND.pem_label());
+        let base64 = self.to_base64();
+        let body = base64.as_ref();
+        // Allocated once, so that no reallocation leaves a partial copy behind.
+        let mut out = String::with_capacity(begin.len() + body.len() + body.len().div_ceil(PEM_LINE_LEN) + end.len());
+        out.push_str(&begin);
+        for line in body.as_bytes().chunks(PEM_LINE_LEN) {
+            // base64 output is ASCII, so any chunk boundary is a char boundary.
+            out.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+            out.push('\n');
+        }
+        out.push_str(&end);
+        out.into()
+    }
+
+    fn from_pem(s: &str) -> Result<Self, IdentityError> {
+        let label = Self::KIND.pem_label();
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        let begin = lines.next().ok_or(IdentityError::InvalidEncoding("Invalid PEM armor"))?;
+        if !begin.starts_with("-----BEGIN ") {
+            return Err(IdentityError::InvalidEncoding("Invalid PEM armor"));
+        }
+        if begin != format!("-----BEGIN {}-----", label) {
+            return Err(IdentityError::InvalidEncoding("PEM label does not match the expected type"));
+        }
+        // The body is never longer than the input, so it is never reallocated.
+        let mut body = Zeroizing::new(String::with_capacity(s.len()));
+        for line in lines.by_ref() {
+            if line.starts_with("-----END ") {
+                if line != format!("-----END {}-----", label) {
+                    return Err(IdentityError::InvalidEncoding("Invalid PEM armor"));
+                }
+                if lines.next().is_some() {
+                    return Err(IdentityError::InvalidEncoding("Invalid PEM armor"));
+                }
+                return Self::from_base64(&body);
+            }
+            body.push_str(line);
+        }
+        Err(IdentityError::InvalidEncoding("Invalid PEM armor"))
+    }
+}
+
+/// Accepts either the base64 text form or the binary envelope.
+struct EnvelopeVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Envelope> de::Visitor<'de> for EnvelopeVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a base64 string or bytes holding a {} envelope", T::KIND.pem_label())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        T::from_base64(v).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+        T::from_envelope(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        // The length hint comes from the input, so preallocate at most a compact key's worth.
+        let max_hint = ENVELOPE_HEADER_BYTES + MayoParams::mayo5().cpk_bytes();
+        let mut bytes = Zeroizing::new(Vec::with_capacity(seq.size_hint().unwrap_or(0).min(max_hint)));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        T::from_envelope(&bytes).map_err(de::Error::custom)
+    }
+}
+
+macro_rules! impl_envelope {
+    ($ty:ident, $bytes:ty, $text:ty) => {
+        impl Envelope for $ty {
+            const KIND: EnvelopeKind = EnvelopeKind::$ty;
+            type Bytes = $bytes;
+            type Text = $text;
+
+            fn envelope_params(&self) -> &MayoParams {
+                self.params()
+            }
+
+            fn envelope_payload(&self) -> &[u8] {
+                self.as_bytes()
+            }
+
+            fn from_envelope_parts(params: MayoParams, payload: Vec<u8>) -> Result<Self, IdentityError> {
+                $ty::from_bytes(params, payload)
+            }
+        }
+
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(self.to_base64().as_ref())
+                } else {
+                    serializer.serialize_bytes(self.to_envelope().as_ref())
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(EnvelopeVisitor(PhantomData))
+                } else {
+                    deserializer.deserialize_bytes(EnvelopeVisitor(PhantomData))
+                }
+            }
+        }
+    };
+}
+
+impl_envelope!(CompactSecretKey, Zeroizing<Vec<u8>>, Zeroizing<String>);
+impl_envelope!(CompactPublicKey, Vec<u8>, String);
+impl_envelope!(ExpandedSecretKey, Zeroizing<Vec<u8>>, Zeroizing<String>);
+impl_envelope!(ExpandedPublicKey, Vec<u8>, String);
+impl_envelope!(Signature, Vec<u8>, String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::compact_key_gen;
+
+    #[test]
+    fn test_envelope_header_and_roundtrip() {
+        let params = MayoParams::mayo3();
+        let (csk, cpk) = compact_key_gen(&params).unwrap();
+
+        let envelope = cpk.to_envelope();
+        assert_eq!(&envelope[..4], b"CBXI");
+        assert_eq!(envelope[4], ENVELOPE_VERSION);
+        assert_eq!(envelope[5], EnvelopeKind::CompactPublicKey as u8);
+        assert_eq!(envelope[6], 3);
+        assert_eq!(&envelope[ENVELOPE_HEADER_BYTES..], cpk.as_bytes());
+        assert_eq!(CompactPublicKey::from_envelope(&envelope), Ok(cpk.clone()));
+
+        // The variant travels with the envelope, even where the raw length is ambiguous.
+        let mayo1_csk = CompactSecretKey::from_bytes(MayoParams::mayo1(), vec![9u8; 24]).unwrap();
+        let mayo2_csk = CompactSecretKey::from_bytes(MayoParams::mayo2(), vec![9u8; 24]).unwrap();
+        assert_eq!(CompactSecretKey::from_envelope(&mayo1_csk.to_envelope()), Ok(mayo1_csk));
+        assert_eq!(CompactSecretKey::from_envelope(&mayo2_csk.to_envelope()), Ok(mayo2_csk));
+
+        assert_eq!(CompactSecretKey::from_hex(&csk.to_hex()), Ok(csk.clone()));
+        assert_eq!(CompactSecretKey::from_base64(&csk.to_base64()), Ok(csk.clone()));
+
+        // Secret keys are encoded into buffers that wipe themselves on drop, and the PEM
+        // buffer is sized exactly, so building it never reallocated.
+        let envelope: Zeroizing<Vec<u8>> = csk.to_envelope();
+        assert_eq!(&envelope[ENVELOPE_HEADER_BYTES..], csk.as_bytes());
+        let pem: Zeroizing<String> = csk.to_pem();
+        assert_eq!(pem.capacity(), pem.len());
+        assert_eq!(CompactSecretKey::from_pem(&pem), Ok(csk));
+    }
+
+    #[test]
+    fn test_envelope_rejects_bad_headers() {
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![1u8; 1420]).unwrap();
+        let envelope = cpk.to_envelope();
+
+        assert_eq!(CompactPublicKey::from_envelope(&envelope[..5]), Err(IdentityError::InvalidEncoding("Envelope is too short")));
+
+        let mut bad = envelope.clone();
+        bad[0] = b'X';
+        assert_eq!(CompactPublicKey::from_envelope(&bad), Err(IdentityError::InvalidEncoding("Envelope has invalid magic bytes")));
+
+        let mut bad = envelope.clone();
+        bad[4] = 2;
+        assert_eq!(CompactPublicKey::from_envelope(&bad), Err(IdentityError::InvalidEncoding("Unsupported envelope version")));
+
+        assert_eq!(Signature::from_envelope(&envelope), Err(IdentityError::InvalidEncoding("Envelope holds a different kind of value")));
+
+        let mut bad = envelope.clone();
+        bad[6] = 4;
+        assert_eq!(CompactPublicKey::from_envelope(&bad), Err(IdentityError::InvalidEncoding("Envelope has unknown MAYO variant id")));
+
+        // A MAYO1 payload relabelled as MAYO2 fails the length check.
+        let mut bad = envelope.clone();
+        bad[6] = 2;
+        assert_eq!(CompactPublicKey::from_envelope(&bad), Err(IdentityError::InvalidLength("Compact public key has incorrect length")));
+
+        let mut bad = envelope;
+        bad.pop();
+        assert_eq!(CompactPublicKey::from_envelope(&bad), Err(IdentityError::InvalidLength("Compact public key has incorrect length")));
+
+        assert_eq!(CompactPublicKey::from_hex("zz"), Err(IdentityError::InvalidEncoding("Invalid hex encoding")));
+        assert_eq!(CompactPublicKey::from_base64("!!!"), Err(IdentityError::InvalidEncoding("Invalid base64 encoding")));
+    }
+
+    #[test]
+    fn test_pem_roundtrip_and_labels() {
+        let sig = Signature::from_bytes(MayoParams::mayo2(), (0..186).map(|i| i as u8).collect()).unwrap();
+        let pem = sig.to_pem();
+        assert!(pem.starts_with("-----BEGIN CUBIX MAYO SIGNATURE-----\n"));
+        assert!(pem.ends_with("-----END CUBIX MAYO SIGNATURE-----\n"));
+        assert!(pem.lines().all(|line| line.len() <= PEM_LINE_LEN));
+        assert_eq!(Signature::from_pem(&pem), Ok(sig.clone()));
+
+        // Surrounding whitespace and CRLF line endings are tolerated.
+        let crlf = format!("\n  {}", pem.replace('\n', "\r\n"));
+        assert_eq!(Signature::from_pem(&crlf), Ok(sig));
+
+        assert_eq!(CompactPublicKey::from_pem(&pem), Err(IdentityError::InvalidEncoding("PEM label does not match the expected type")));
+        let truncated: String = pem.lines().take(2).collect::<Vec<_>>().join("\n");
+        assert_eq!(Signature::from_pem(&truncated), Err(IdentityError::InvalidEncoding("Invalid PEM armor")));
+        assert_eq!(Signature::from_pem("not pem"), Err(IdentityError::InvalidEncoding("Invalid PEM armor")));
+    }
+
+    #[test]
+    fn test_serde_json_uses_base64_string() {
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo5(), vec![3u8; 5554]).unwrap();
+        let json = serde_json::to_string(&cpk).unwrap();
+        assert_eq!(json, format!("\"{}\"", cpk.to_base64()));
+        let decoded: CompactPublicKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, cpk);
+
+        let wrong: Result<Signature, _> = serde_json::from_str(&json);
+        assert!(wrong.is_err());
+    }
+
+    #[test]
+    fn test_serde_binary_uses_envelope() {
+        let sig = Signature::from_bytes(MayoParams::mayo1(), vec![5u8; 454]).unwrap();
+        let encoded = bincode::serialize(&sig).unwrap();
+        // bincode prefixes byte strings with their u64 length.
+        assert_eq!(&encoded[8..], sig.to_envelope().as_slice());
+        let decoded: Signature = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded, sig);
+    }
+}
@@ -0,0 +1,116 @@
+//! The error type shared by every fallible operation of the crate.
+//!
+//! Each variant is one class of failure, so callers can tell a malformed key apart from
+//! a failing random source without matching on messages. Variants that cover several
+//! call sites carry a short static description of the one that failed. With the `wasm`
+//! feature an `IdentityError` becomes a JS `Error` named `IdentityError` whose `code`
+//! property is the stable string returned by `IdentityError::code`.
+
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::JsValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdentityError {
+    /// The name or id does not denote a MAYO variant (mayo1, mayo2, mayo3 or mayo5).
+    UnknownVariant,
+    /// Inputs that must share a MAYO variant, such as a key and a signature, do not.
+    VariantMismatch,
+    /// A byte length fits more than one MAYO variant, so the variant must be given.
+    AmbiguousVariant,
+    /// A key, signature or other byte string has the wrong length for its variant.
+    InvalidLength(&'static str),
+    /// Matrix or vector dimensions do not fit the operation.
+    DimensionMismatch(&'static str),
+    /// Malformed hex, base64, PEM or envelope input.
+    InvalidEncoding(&'static str),
+    /// Arguments that are individually well-formed but inconsistent with each other.
+    InvalidInput(&'static str),
+    /// A mnemonic phrase has a word outside the BIP39 English wordlist or a wrong checksum.
+    InvalidMnemonic(&'static str),
+    /// The random source failed to produce bytes.
+    RngFailure,
+    /// Signing found no solvable linear system within the retry budget.
+    SigningFailed,
+    /// The zero element of GF(16), or a singular matrix, has no inverse.
+    NotInvertible,
+    /// The verification thread pool could not be started.
+    ThreadPool,
+    /// Authenticated decryption failed: the password is wrong or the ciphertext was altered.
+    DecryptionFailed,
+}
+
+impl IdentityError {
+    /// Returns a stable, machine-readable code for the error class. The codes are part
+    /// of the JS API and do not change between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            IdentityError::UnknownVariant => "UNKNOWN_VARIANT",
+            IdentityError::VariantMismatch => "VARIANT_MISMATCH",
+            IdentityError::AmbiguousVariant => "AMBIGUOUS_VARIANT",
+            IdentityError::InvalidLength(_) => "INVALID_LENGTH",
+            IdentityError::DimensionMismatch(_) => "DIMENSION_MISMATCH",
+            IdentityError::InvalidEncoding(_) => "INVALID_ENCODING",
+            IdentityError::InvalidInput(_) => "INVALID_INPUT",
+            IdentityError::InvalidMnemonic(_) => "INVALID_MNEMONIC",
+            IdentityError::RngFailure => "RNG_FAILURE",
+            IdentityError::SigningFailed => "SIGNING_FAILED",
+            IdentityError::NotInvertible => "NOT_INVERTIBLE",
+            IdentityError::ThreadPool => "THREAD_POOL",
+            IdentityError::DecryptionFailed => "DECRYPTION_FAILED",
+        }
+    }
+}
+
+impl fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdentityError::UnknownVariant => f.write_str("Unknown MAYO variant"),
+            IdentityError::VariantMismatch => f.write_str("Inputs belong to different MAYO variants"),
+            IdentityError::AmbiguousVariant => f.write_str("Length is shared by several MAYO variants"),
+            IdentityError::InvalidLength(detail)
+            | IdentityError::DimensionMismatch(detail)
+            | IdentityError::InvalidEncoding(detail)
+            | IdentityError::InvalidInput(detail)
+            | IdentityError::InvalidMnemonic(detail) => f.write_str(detail),
+            IdentityError::RngFailure => f.write_str("Failed to generate random bytes"),
+            IdentityError::SigningFailed => f.write_str("MAYO.Sign failed after maximum retries"),
+            IdentityError::NotInvertible => f.write_str("Cannot invert zero element or singular matrix"),
+            IdentityError::ThreadPool => f.write_str("Failed to build the verification thread pool"),
+            IdentityError::DecryptionFailed => f.write_str("Wrong password or corrupted ciphertext"),
+        }
+    }
+}
+
+impl std::error::Error for IdentityError {}
+
+#[cfg(feature = "wasm")]
+impl From<IdentityError> for JsValue {
+    /// Builds `new Error(message)` with `name = "IdentityError"` and `code` set to
+    /// `IdentityError::code`. Only callable on wasm targets.
+    fn from(err: IdentityError) -> JsValue {
+        let js_error = js_sys::Error::new(&err.to_string());
+        js_error.set_name("IdentityError");
+        // Setting a property on a fresh Error object cannot fail.
+        let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("code"), &JsValue::from_str(err.code()));
+        js_error.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_messages() {
+        let err = IdentityError::InvalidLength("Signature has incorrect length");
+        assert_eq!(err.code(), "INVALID_LENGTH");
+        assert_eq!(err.to_string(), "Signature has incorrect length");
+        assert_eq!(IdentityError::RngFailure.code(), "RNG_FAILURE");
+        assert_eq!(IdentityError::SigningFailed.to_string(), "MAYO.Sign failed after maximum retries");
+
+        let boxed: Box<dyn std::error::Error> = Box::new(IdentityError::VariantMismatch);
+        assert_eq!(boxed.to_string(), "Inputs belong to different MAYO variants");
+    }
+}
@@ -21,40 +21,49 @@ pub fn gf16_sub(a: GFElement, b: GFElement) -> GFElement {
     GFElement((a.0 ^ b.0) & NIBBLE_MASK) // Identical to add
 }
 
-/// Multiplies two GF(16) elements using bitwise operations (Russian peasant method variant).
+/// Returns 0xFF if `bit` (0 or 1) is set and 0x00 otherwise, without branching.
+/// `black_box` keeps the optimizer from turning the mask back into a branch.
+#[inline]
+pub(crate) fn ct_mask_from_bit(bit: u8) -> u8 {
+    std::hint::black_box(0u8.wrapping_sub(bit & 1))
+}
+
+/// Returns 0xFF if `a` is zero and 0x00 otherwise, without branching.
+#[inline]
+pub(crate) fn ct_is_zero_mask(a: u8) -> u8 {
+    // (a - 1) borrows into bit 8 exactly when a == 0.
+    ct_mask_from_bit(((a as u16).wrapping_sub(1) >> 8) as u8)
+}
+
+/// Multiplies two GF(16) elements in constant time.
 /// Field is GF(2^4) with irreducible polynomial x^4 + x + 1 (F_POLY_U8 = 0b00010011).
+///
+/// Shift-and-add over the 4 bits of `b`: every step runs unconditionally, with the
+/// conditional add of `a` and the reduction by the field polynomial applied through
+/// masks, so the timing does not depend on either operand.
 pub fn gf16_mul(a: GFElement, b: GFElement) -> GFElement {
-    let mut p: u8 = 0; // Accumulator for the product
+    let mut p: u8 = 0;
     let mut val_a = a.0 & NIBBLE_MASK;
-    let mut val_b = b.0 & NIBBLE_MASK;
+    let val_b = b.0 & NIBBLE_MASK;
 
-    // Russian peasant multiplication adapted for GF(2^n)
-    for _ in 0..4 { // Iterate 4 times for 4 bits of b
-        if (val_b & 1) != 0 { // If LSB of b is 1
-            p ^= val_a;      // Add (XOR) a to product
-        }
-        
-        val_b >>= 1; // Shift b to the right (divide by 2)
-        
-        // Check if a needs reduction before next XOR with p
-        // (This is actually about shifting 'a' and reducing it if it overflows)
-        let high_bit_set = (val_a & 0x08) != 0; // Check if 4th bit of a (val_a_3) is set
-        val_a <<= 1; // Shift a to the left (multiply by x)
-        
-        if high_bit_set {
-            val_a ^= F_POLY_U8; // Reduce by XORing with the irreducible polynomial
-        }
-        val_a &= NIBBLE_MASK; // Ensure val_a stays within 4 bits after potential reduction
-                              // This mask is important if F_POLY_U8 itself has bits beyond the 4th if not careful
-                              // For F_POLY_U8 = 0b00010011, it correctly reduces x^4 to x+1.
-                              // Example: if val_a was 0b1000 (x^3), it becomes 0b10000 (x^4).
-                              // Then 0b10000 ^ 0b10011 = 0b0011 (x+1).
-                              // The NIBBLE_MASK here is mostly for safety ensuring intermediate val_a doesn't grow.
-                              // The actual reduction is what keeps it in the field.
+    for i in 0..4 {
+        p ^= val_a & ct_mask_from_bit(val_b >> i);
+        // Multiply a by x, reducing x^4 to x + 1 when the top bit falls off.
+        let high_bit = ct_mask_from_bit(val_a >> 3);
+        val_a = ((val_a << 1) ^ (F_POLY_U8 & high_bit)) & NIBBLE_MASK;
     }
     GFElement(p & NIBBLE_MASK)
 }
 
+/// Computes the multiplicative inverse of a GF(16) element in constant time, as
+/// a^14 = a^8 * a^4 * a^2 with a fixed chain of multiplications. Zero maps to zero.
+pub fn gf16_inv_ct(a: GFElement) -> GFElement {
+    let a2 = gf16_mul(a, a);
+    let a4 = gf16_mul(a2, a2);
+    let a8 = gf16_mul(a4, a4);
+    gf16_mul(gf16_mul(a8, a4), a2)
+}
+
 /// Computes base^exp in GF(16).
 pub fn gf16_pow(base: GFElement, exp: usize) -> GFElement {
     if exp == 0 {
@@ -129,15 +138,12 @@ mod tests {
         assert_eq!(gf16_mul(gf(0xB), gf(0x2)).0, 0x5); // (x^3+x+1)*x = x^4+x^2+x = (x+1)+x^2+x = x^2+1 (0b0101)
 
         // Test some other values
-        // 0x5 * 0x7 = (x^2+1)(x^2+x+1) = x^4+x^3+x^2 + x^2+x+1 = (x+1)+x^3+x = x^3+1 = 0x8^0x1 = 0x9
-        assert_eq!(gf16_mul(gf(0x5), gf(0x7)).0, 0x9);
-        // 0xA * 0xB = (x^3+x^2)(x^3+x+1) = x^6+x^4+x^3 + x^5+x^3+x^2 = x^6+x^5+x^4+x^2
+        // 0x5 * 0x7 = (x^2+1)(x^2+x+1) = x^4+x^3+x^2 + x^2+x+1 = x^4+x^3+x+1 = (x+1)+x^3+x+1 = x^3 = 0x8
+        assert_eq!(gf16_mul(gf(0x5), gf(0x7)).0, 0x8);
+        // 0xA * 0xB = (x^3+x)(x^3+x+1) = x^6+x^4+x^3 + x^4+x^2+x = x^6+x^3+x^2+x
         // x^6 = 0xC (x^3+x^2)
-        // x^5 = 0x6 (x^2+x)
-        // x^4 = 0x3 (x+1)
-        // x^2 = 0x4 (x^2)
-        // 0xC ^ 0x6 ^ 0x3 ^ 0x4 = (1100^0110 = 1010) ^ (0011^0100 = 0111) = 1010^0111 = 1101 = 0xD
-        assert_eq!(gf16_mul(gf(0xA), gf(0xB)).0, 0xD);
+        // 0xC ^ 0x8 ^ 0x4 ^ 0x2 = 0x2
+        assert_eq!(gf16_mul(gf(0xA), gf(0xB)).0, 0x2);
     }
 
     #[test]
@@ -171,6 +177,25 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_gf16_inv_ct() {
+        assert_eq!(gf16_inv_ct(gf(0)).0, 0);
+        for i in 1..16 {
+            assert_eq!(gf16_mul(gf(i), gf16_inv_ct(gf(i))).0, 1, "Inverse failed for {}", i);
+            assert_eq!(gf16_inv_ct(gf(i)), gf16_pow(gf(i), 14));
+        }
+    }
+
+    #[test]
+    fn test_ct_masks() {
+        assert_eq!(ct_mask_from_bit(0), 0x00);
+        assert_eq!(ct_mask_from_bit(1), 0xFF);
+        assert_eq!(ct_is_zero_mask(0), 0xFF);
+        for a in 1..=255u8 {
+            assert_eq!(ct_is_zero_mask(a), 0x00);
+        }
+    }
+
     #[test]
     fn test_gf16_pow() {
         // x = 0x2
@@ -191,4 +216,65 @@ mod tests {
         assert_eq!(gf16_pow(gf(0x5), 3).0, gf16_mul(gf(0x2), gf(0x5)).0); // 0x2 * 0x5 = x(x^2+1) = x^3+x = 0x8^0x2 = 0xA
         assert_eq!(gf16_pow(gf(0x5), 3).0, 0xA);
     }
+
+    mod properties {
+        use super::*;
+        use crate::solver::gf16_inv;
+        use proptest::prelude::*;
+
+        fn element() -> impl Strategy<Value = GFElement> {
+            (0u8..16).prop_map(GFElement)
+        }
+
+        fn nonzero() -> impl Strategy<Value = GFElement> {
+            (1u8..16).prop_map(GFElement)
+        }
+
+        proptest! {
+            #[test]
+            fn addition_is_an_abelian_group(a in element(), b in element(), c in element()) {
+                prop_assert_eq!(gf16_add(a, b), gf16_add(b, a));
+                prop_assert_eq!(gf16_add(gf16_add(a, b), c), gf16_add(a, gf16_add(b, c)));
+                prop_assert_eq!(gf16_add(a, GFElement(0)), a);
+                prop_assert_eq!(gf16_add(a, a), GFElement(0));
+                prop_assert_eq!(gf16_sub(gf16_add(a, b), b), a);
+            }
+
+            #[test]
+            fn multiplication_is_a_commutative_ring(a in element(), b in element(), c in element()) {
+                prop_assert_eq!(gf16_mul(a, b), gf16_mul(b, a));
+                prop_assert_eq!(gf16_mul(gf16_mul(a, b), c), gf16_mul(a, gf16_mul(b, c)));
+                prop_assert_eq!(gf16_mul(a, gf16_add(b, c)), gf16_add(gf16_mul(a, b), gf16_mul(a, c)));
+                prop_assert_eq!(gf16_mul(a, GFElement(1)), a);
+                prop_assert_eq!(gf16_mul(a, GFElement(0)), GFElement(0));
+            }
+
+            #[test]
+            fn nonzero_elements_are_invertible(a in nonzero(), b in nonzero()) {
+                let inv = gf16_inv(a).unwrap();
+                prop_assert_eq!(inv, gf16_inv_ct(a));
+                prop_assert_eq!(gf16_mul(a, inv), GFElement(1));
+                // No zero divisors.
+                prop_assert_ne!(gf16_mul(a, b), GFElement(0));
+            }
+
+            #[test]
+            fn pow_follows_exponent_laws(a in element(), e in 0usize..40, f in 0usize..40) {
+                prop_assert_eq!(gf16_pow(a, e + f), gf16_mul(gf16_pow(a, e), gf16_pow(a, f)));
+                if a.0 != 0 {
+                    // The multiplicative group has order 15.
+                    prop_assert_eq!(gf16_pow(a, 15), GFElement(1));
+                    prop_assert_eq!(gf16_pow(a, 14), gf16_inv_ct(a));
+                }
+            }
+
+            #[test]
+            fn results_are_reduced_nibbles(a in any::<u8>(), b in any::<u8>()) {
+                // High bits of the inputs are ignored rather than leaking into the result.
+                prop_assert_eq!(gf16_mul(GFElement(a), GFElement(b)), gf16_mul(GFElement(a & 15), GFElement(b & 15)));
+                prop_assert!(gf16_add(GFElement(a), GFElement(b)).0 < 16);
+                prop_assert!(gf16_inv_ct(GFElement(a)).0 < 16);
+            }
+        }
+    }
 }
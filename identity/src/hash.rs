@@ -2,7 +2,8 @@
 //! and other parts of the MAYO signature scheme.
 
 use sha3::{Shake256, digest::{Update, ExtendableOutput, XofReader}};
-use crate::types::{MessageDigest, Salt, SeedSK, SeedPK};
+use crate::types::{MessageDigest, Salt, SeedPK, SeedSK};
+use zeroize::Zeroizing;
 use crate::params::MayoParams;
 
 /// Generates a fixed-size message digest using SHAKE256.
@@ -22,47 +23,54 @@ pub fn shake256_digest(input: &[u8], params: &MayoParams) -> MessageDigest {
     MessageDigest(digest_bytes_vec)
 }
 
+/// Incremental form of `shake256_digest`: absorbs the message in chunks and produces
+/// the same digest as hashing it in one piece.
+#[derive(Clone)]
+pub struct MessageHasher {
+    hasher: Shake256,
+    params: MayoParams,
+}
+
+impl MessageHasher {
+    pub fn new(params: &MayoParams) -> Self {
+        MessageHasher { hasher: Shake256::default(), params: *params }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    pub fn finalize(self) -> MessageDigest {
+        let mut reader = self.hasher.finalize_xof();
+        let mut digest_bytes_vec = vec![0u8; self.params.digest_bytes()];
+        reader.read(&mut digest_bytes_vec);
+        MessageDigest(digest_bytes_vec)
+    }
+}
+
 /// Derives a public key seed (`SeedPK`) and bytes for the oil space (`O_bytes`)
 /// from a secret key seed (`SeedSK`) using SHAKE256 XOF (Extendable Output Function).
 ///
 /// # Arguments
-/// * `seed` - The secret key seed (`SeedSK`).
+/// * `seed_sk` - The secret key seed bytes, borrowed so that callers need not copy them.
 /// * `params` - MAYO parameters, used to determine `pk_seed_bytes` and `O_bytes` lengths.
 ///
 /// # Returns
-/// A tuple containing the derived `SeedPK` and a `Vec<u8>` for `O_bytes`.
-pub fn shake256_xof_derive_pk_seed_and_o(seed: &SeedSK, params: &MayoParams) -> (SeedPK, Vec<u8>) {
+/// A tuple containing the derived `SeedPK` and the secret `O_bytes`, wiped on drop.
+pub fn shake256_xof_derive_pk_seed_and_o(seed_sk: &[u8], params: &MayoParams) -> (SeedPK, Zeroizing<Vec<u8>>) {
     let mut hasher = Shake256::default();
-    hasher.update(&seed.0);
+    hasher.update(seed_sk);
     let mut reader = hasher.finalize_xof();
     
     let mut seedpk_bytes_vec = vec![0u8; params.pk_seed_bytes()];
     reader.read(&mut seedpk_bytes_vec);
     
-    let mut o_bytes_vec = vec![0u8; params.o_bytes()]; 
+    let mut o_bytes_vec = Zeroizing::new(vec![0u8; params.o_bytes()]);
     reader.read(&mut o_bytes_vec);
     
     (SeedPK(seedpk_bytes_vec), o_bytes_vec)
 }
 
-/// Derives bytes for the P3 matrix component (`P3_bytes`) from a public key seed (`SeedPK`)
-/// using SHAKE256 XOF.
-///
-/// # Arguments
-/// * `seed_pk` - The public key seed (`SeedPK`).
-/// * `params` - MAYO parameters, used to determine the `P3_bytes` length.
-///
-/// # Returns
-/// A `Vec<u8>` representing the `P3_bytes`.
-pub fn shake256_xof_derive_p3(seed_pk: &SeedPK, params: &MayoParams) -> Vec<u8> {
-    let mut hasher = Shake256::default();
-    hasher.update(&seed_pk.0);
-    let mut reader = hasher.finalize_xof();
-    let mut p3_bytes_vec = vec![0u8; params.p3_bytes()];
-    reader.read(&mut p3_bytes_vec);
-    p3_bytes_vec
-}
-
 /// Derives the target vector `t` from a message digest (`M_digest`) and a salt (`Salt`)
 /// using SHAKE256 XOF. The output length is determined by `params.m` (number of equations),
 /// considering that each element of `t` is in GF(16) (4 bits).
@@ -88,3 +96,139 @@ pub fn shake256_derive_target_t(m_digest: &MessageDigest, salt: &Salt, params: &
     reader.read(&mut t_bytes_vec);
     t_bytes_vec
 }
+
+/// Derives the signature salt from the message digest, a randomizer `R` and the
+/// secret key seed: `salt = SHAKE256(M_digest || R || seed_sk)`.
+///
+/// # Arguments
+/// * `m_digest` - The message digest.
+/// * `randomizer` - `salt_bytes` of fresh randomness (all zero for deterministic signing).
+/// * `seed_sk` - The secret key seed bytes.
+/// * `params` - MAYO parameters, used to determine the `salt_bytes` output length.
+///
+/// # Returns
+/// The derived `Salt`.
+pub fn shake256_derive_salt(m_digest: &MessageDigest, randomizer: &[u8], seed_sk: &[u8], params: &MayoParams) -> Salt {
+    let mut hasher = Shake256::default();
+    hasher.update(&m_digest.0);
+    hasher.update(randomizer);
+    hasher.update(seed_sk);
+    let mut reader = hasher.finalize_xof();
+    let mut salt_bytes_vec = vec![0u8; params.salt_bytes()];
+    reader.read(&mut salt_bytes_vec);
+    Salt(salt_bytes_vec)
+}
+
+/// Derives the bytes for the k vinegar vectors and the randomizer `r` used to sample a
+/// solution, for signing attempt `ctr`: `SHAKE256(M_digest || salt || seed_sk || ctr)`.
+///
+/// # Arguments
+/// * `m_digest` - The message digest.
+/// * `salt` - The signature salt.
+/// * `seed_sk` - The secret key seed bytes.
+/// * `ctr` - The signing attempt counter.
+/// * `params` - MAYO parameters, used to determine the output length.
+///
+/// # Returns
+/// `k * ceil((n-o)/2) + ceil(k*o/2)` secret bytes, wiped on drop: the k encoded vinegar
+/// vectors followed by the encoded `r`.
+pub fn shake256_derive_vinegar(m_digest: &MessageDigest, salt: &Salt, seed_sk: &[u8], ctr: u8, params: &MayoParams) -> Zeroizing<Vec<u8>> {
+    let variant = params.variant();
+    let mut hasher = Shake256::default();
+    hasher.update(&m_digest.0);
+    hasher.update(&salt.0);
+    hasher.update(seed_sk);
+    hasher.update(&[ctr]);
+    let mut reader = hasher.finalize_xof();
+    let len = variant.k * variant.v_bytes() + MayoParams::bytes_for_gf16_elements(variant.k * variant.o);
+    let mut v_bytes_vec = Zeroizing::new(vec![0u8; len]);
+    reader.read(&mut v_bytes_vec);
+    v_bytes_vec
+}
+
+/// Length of a hierarchical derivation node: a 32-byte key followed by a 32-byte chain code.
+pub const DERIVATION_NODE_BYTES: usize = 64;
+
+/// Absorbs `part` preceded by its length as a 4-byte big-endian integer, so that
+/// consecutive variable-length inputs cannot be shifted into one another.
+fn update_length_prefixed(hasher: &mut Shake256, part: &[u8]) {
+    hasher.update(&(part.len() as u32).to_be_bytes());
+    hasher.update(part);
+}
+
+/// Stretches a mnemonic phrase and an optional passphrase into a 64-byte master seed:
+/// `SHAKE256("cubix/mnemonic" || phrase || passphrase)` followed by `rounds - 1`
+/// further SHAKE256 passes over the 64-byte state.
+///
+/// # Arguments
+/// * `phrase` - The normalized mnemonic phrase.
+/// * `passphrase` - The optional passphrase; empty if none.
+/// * `rounds` - Number of SHAKE256 passes, at least 1.
+///
+/// # Returns
+/// The master seed, wiped on drop.
+pub fn shake256_mnemonic_to_seed(phrase: &[u8], passphrase: &[u8], rounds: u32) -> Zeroizing<[u8; DERIVATION_NODE_BYTES]> {
+    let mut state = Zeroizing::new([0u8; DERIVATION_NODE_BYTES]);
+    let mut hasher = Shake256::default();
+    hasher.update(b"cubix/mnemonic");
+    update_length_prefixed(&mut hasher, phrase);
+    update_length_prefixed(&mut hasher, passphrase);
+    hasher.finalize_xof().read(&mut state[..]);
+    for _ in 1..rounds {
+        let mut hasher = Shake256::default();
+        hasher.update(&state[..]);
+        hasher.finalize_xof().read(&mut state[..]);
+    }
+    state
+}
+
+/// Derives the root node of the key hierarchy from a master seed:
+/// `SHAKE256("cubix/hd/master" || seed)`.
+pub fn shake256_derive_master_node(seed: &[u8]) -> Zeroizing<[u8; DERIVATION_NODE_BYTES]> {
+    let mut node = Zeroizing::new([0u8; DERIVATION_NODE_BYTES]);
+    let mut hasher = Shake256::default();
+    hasher.update(b"cubix/hd/master");
+    update_length_prefixed(&mut hasher, seed);
+    hasher.finalize_xof().read(&mut node[..]);
+    node
+}
+
+/// Derives child `index` of a node: `SHAKE256("cubix/hd/child" || chain_code || key || index)`.
+/// Every child depends on the parent's secret key, so no child can be derived from
+/// public data alone.
+pub fn shake256_derive_child_node(parent: &[u8; DERIVATION_NODE_BYTES], index: u32) -> Zeroizing<[u8; DERIVATION_NODE_BYTES]> {
+    let (key, chain_code) = parent.split_at(DERIVATION_NODE_BYTES / 2);
+    let mut node = Zeroizing::new([0u8; DERIVATION_NODE_BYTES]);
+    let mut hasher = Shake256::default();
+    hasher.update(b"cubix/hd/child");
+    hasher.update(chain_code);
+    hasher.update(key);
+    hasher.update(&index.to_be_bytes());
+    hasher.finalize_xof().read(&mut node[..]);
+    node
+}
+
+/// Derives the MAYO secret key seed of a node for one variant:
+/// `SHAKE256("cubix/hd/mayo" || variant_id || key)`, `sk_seed_bytes` long. The variant
+/// id keeps the keys of different variants at the same path unrelated.
+pub fn shake256_derive_seed_sk(node: &[u8; DERIVATION_NODE_BYTES], params: &MayoParams) -> SeedSK {
+    let mut hasher = Shake256::default();
+    hasher.update(b"cubix/hd/mayo");
+    hasher.update(&[params.id()]);
+    hasher.update(&node[..DERIVATION_NODE_BYTES / 2]);
+    let mut seed_sk = SeedSK(vec![0u8; params.sk_seed_bytes()]);
+    hasher.finalize_xof().read(&mut seed_sk.0);
+    seed_sk
+}
+
+/// Derives the `rounds` coin flips of a non-interactive trisection proof from its
+/// transcript (public angle, context and commitments): bit `i` of
+/// `SHAKE256("cubix/trisection" || transcript)`, least significant bit first.
+pub fn shake256_trisection_coins(transcript: &[u8], rounds: usize) -> Vec<bool> {
+    let mut hasher = Shake256::default();
+    hasher.update(b"cubix/trisection");
+    hasher.update(transcript);
+    let mut bytes = vec![0u8; rounds.div_ceil(8)];
+    hasher.finalize_xof().read(&mut bytes);
+    (0..rounds).map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1).collect()
+}
@@ -0,0 +1,175 @@
+//! Known-answer tests against the NIST `PQCsignKAT_*.rsp` files of the MAYO reference
+//! implementation, bundled under `src/KAT`.
+//!
+//! Each entry is replayed by seeding a `CtrDrbg` with its `seed` and running key
+//! generation and signing with it, exactly as `PQCgenKAT_sign` does; pk, sk and sm must
+//! match byte for byte, and sm must open under pk.
+
+use crate::params::MayoParams;
+use crate::rng::{CtrDrbg, DRBG_SEED_BYTES};
+use crate::keygen::{compact_key_gen_with_rng, expand_pk, expand_sk};
+use crate::sign::sign_message_with_rng;
+use crate::verify::verify_signature;
+use crate::types::{Message, Signature};
+
+/// Number of entries checked per variant by default; debug builds sign slowly, so the
+/// full files are covered by the ignored `*_all_entries` tests.
+const DEFAULT_KAT_ENTRIES: usize = 3;
+
+/// One `count = ...` block of a `.rsp` file.
+#[derive(Debug, Default)]
+struct KatEntry {
+    count: usize,
+    seed: Vec<u8>,
+    mlen: usize,
+    msg: Vec<u8>,
+    pk: Vec<u8>,
+    sk: Vec<u8>,
+    smlen: usize,
+    sm: Vec<u8>,
+}
+
+fn decode_hex(s: &str) -> Vec<u8> {
+    assert!(s.len().is_multiple_of(2), "odd-length hex string in KAT file");
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("invalid hex in KAT file"))
+        .collect()
+}
+
+/// Parses the NIST `.rsp` format: a `# <name>` header followed by blank-line separated
+/// blocks of `key = value` lines.
+fn parse_rsp(contents: &str) -> Vec<KatEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<KatEntry> = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once('=').expect("malformed line in KAT file");
+        let (key, value) = (key.trim(), value.trim());
+        if key == "count" {
+            entries.extend(current.take());
+            current = Some(KatEntry { count: value.parse().expect("invalid count"), ..Default::default() });
+            continue;
+        }
+        let entry = current.as_mut().expect("KAT field before the first count");
+        match key {
+            "seed" => entry.seed = decode_hex(value),
+            "msg" => entry.msg = decode_hex(value),
+            "pk" => entry.pk = decode_hex(value),
+            "sk" => entry.sk = decode_hex(value),
+            "sm" => entry.sm = decode_hex(value),
+            "mlen" => entry.mlen = value.parse().expect("invalid mlen"),
+            "smlen" => entry.smlen = value.parse().expect("invalid smlen"),
+            _ => panic!("unknown KAT field {}", key),
+        }
+    }
+    entries.extend(current);
+    entries
+}
+
+fn check_entry(entry: &KatEntry, params_enum: &MayoParams) {
+    let seed: &[u8; DRBG_SEED_BYTES] = entry.seed.as_slice().try_into().expect("KAT seed must be 48 bytes");
+    let mut drbg = CtrDrbg::new(seed);
+
+    let (csk, cpk) = compact_key_gen_with_rng(params_enum, &mut drbg).unwrap();
+    assert_eq!(cpk.as_bytes(), entry.pk, "pk mismatch at count {}", entry.count);
+    assert_eq!(csk.as_bytes(), entry.sk, "sk mismatch at count {}", entry.count);
+
+    let message = Message(entry.msg.clone());
+    let esk = expand_sk(&csk).unwrap();
+    let signature = sign_message_with_rng(&esk, &message, &mut drbg).unwrap();
+    let mut sm = signature.as_bytes().to_vec();
+    sm.extend_from_slice(&message.0);
+    assert_eq!(sm, entry.sm, "sm mismatch at count {}", entry.count);
+
+    let epk = expand_pk(&cpk).unwrap();
+    let sig_len = params_enum.sig_bytes();
+    let kat_signature = Signature::from_bytes(*params_enum, entry.sm[..sig_len].to_vec()).unwrap();
+    assert_eq!(verify_signature(&epk, &message, &kat_signature), Ok(true),
+               "KAT signature does not verify at count {}", entry.count);
+}
+
+fn check_kat_file(contents: &str, params_enum: &MayoParams, max_entries: usize) {
+    let entries = parse_rsp(contents);
+    assert_eq!(entries.len(), 100);
+    for entry in entries.iter().take(max_entries) {
+        check_entry(entry, params_enum);
+    }
+}
+
+const MAYO1_RSP: &str = include_str!("KAT/PQCsignKAT_24_MAYO_1.rsp");
+const MAYO2_RSP: &str = include_str!("KAT/PQCsignKAT_24_MAYO_2.rsp");
+const MAYO3_RSP: &str = include_str!("KAT/PQCsignKAT_32_MAYO_3.rsp");
+const MAYO5_RSP: &str = include_str!("KAT/PQCsignKAT_40_MAYO_5.rsp");
+
+#[test]
+fn test_parse_rsp() {
+    let files = [
+        (MAYO1_RSP, MayoParams::mayo1()),
+        (MAYO2_RSP, MayoParams::mayo2()),
+        (MAYO3_RSP, MayoParams::mayo3()),
+        (MAYO5_RSP, MayoParams::mayo5()),
+    ];
+    for (contents, params) in files {
+        let entries = parse_rsp(contents);
+        assert_eq!(entries.len(), 100);
+        assert_eq!(entries[0].count, 0);
+        assert_eq!(entries[99].count, 99);
+        assert_eq!(entries[0].seed.len(), DRBG_SEED_BYTES);
+        assert_eq!(entries[0].msg.len(), 33);
+        for entry in &entries {
+            assert_eq!(entry.msg.len(), entry.mlen);
+            assert_eq!(entry.sm.len(), entry.smlen);
+            assert_eq!(entry.pk.len(), params.cpk_bytes());
+            assert_eq!(entry.sk.len(), params.csk_bytes());
+            assert_eq!(entry.sm.len(), params.sig_bytes() + entry.msg.len());
+        }
+    }
+}
+
+#[test]
+fn test_kat_mayo1() {
+    check_kat_file(MAYO1_RSP, &MayoParams::mayo1(), DEFAULT_KAT_ENTRIES);
+}
+
+#[test]
+fn test_kat_mayo2() {
+    check_kat_file(MAYO2_RSP, &MayoParams::mayo2(), DEFAULT_KAT_ENTRIES);
+}
+
+#[test]
+fn test_kat_mayo3() {
+    check_kat_file(MAYO3_RSP, &MayoParams::mayo3(), 1);
+}
+
+#[test]
+fn test_kat_mayo5() {
+    check_kat_file(MAYO5_RSP, &MayoParams::mayo5(), 1);
+}
+
+#[test]
+#[ignore = "slow in debug builds; run with --release -- --ignored"]
+fn test_kat_mayo1_all_entries() {
+    check_kat_file(MAYO1_RSP, &MayoParams::mayo1(), usize::MAX);
+}
+
+#[test]
+#[ignore = "slow in debug builds; run with --release -- --ignored"]
+fn test_kat_mayo2_all_entries() {
+    check_kat_file(MAYO2_RSP, &MayoParams::mayo2(), usize::MAX);
+}
+
+#[test]
+#[ignore = "slow in debug builds; run with --release -- --ignored"]
+fn test_kat_mayo3_all_entries() {
+    check_kat_file(MAYO3_RSP, &MayoParams::mayo3(), usize::MAX);
+}
+
+#[test]
+#[ignore = "slow in debug builds; run with --release -- --ignored"]
+fn test_kat_mayo5_all_entries() {
+    check_kat_file(MAYO5_RSP, &MayoParams::mayo5(), usize::MAX);
+}
@@ -0,0 +1,392 @@
+//! Password-encrypted keystore files for compact secret keys.
+//!
+//! A keystore is a JSON document in the spirit of the Ethereum keystore: the secret key
+//! seed is encrypted with AES-256-GCM under a key stretched from the password with
+//! scrypt, next to the MAYO variant, the compact public key and the Cubix address in
+//! the clear, so a node can tell which identity a file holds without unlocking it. The
+//! variant and public key are bound to the ciphertext as associated data, so neither
+//! can be swapped without the password.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "variant": "mayo2",
+//!   "address": "cbx1...",
+//!   "public_key": "<hex>",
+//!   "crypto": {
+//!     "kdf": "scrypt",
+//!     "kdfparams": { "log_n": 17, "r": 8, "p": 1, "salt": "<hex>" },
+//!     "cipher": "aes-256-gcm",
+//!     "nonce": "<hex>",
+//!     "ciphertext": "<hex of the encrypted seed and the 16-byte tag>"
+//!   }
+//! }
+//! ```
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::address::Address;
+use crate::error::IdentityError;
+use crate::keygen::compact_key_gen_from_seed;
+use crate::params::MayoParams;
+use crate::rng::{OsRandom, RandomSource};
+use crate::types::{CompactPublicKey, CompactSecretKey, SeedSK};
+
+/// Current keystore format version.
+pub const KEYSTORE_VERSION: u32 = 1;
+/// Length of the random scrypt salt.
+pub const KEYSTORE_SALT_BYTES: usize = 32;
+/// Length of the AES-256-GCM nonce.
+pub const KEYSTORE_NONCE_BYTES: usize = 12;
+/// Largest accepted scrypt cost exponent.
+pub const MAX_KDF_LOG_N: u8 = 20;
+/// Largest accepted scrypt block size factor.
+pub const MAX_KDF_R: u32 = 32;
+/// Largest accepted scrypt parallelism factor.
+pub const MAX_KDF_P: u32 = 16;
+/// Largest accepted scrypt working memory, 128 * r * N bytes. Files asking for more
+/// time or memory than these limits are rejected rather than allowed to make `unlock`
+/// allocate gigabytes or spin.
+pub const MAX_KDF_MEMORY_BYTES: u64 = 1 << 30;
+
+const KDF_NAME: &str = "scrypt";
+const CIPHER_NAME: &str = "aes-256-gcm";
+const AAD_TAG: &[u8] = b"cubix/keystore";
+const KEY_BYTES: usize = 32;
+const TAG_BYTES: usize = 16;
+
+/// The scrypt cost parameters of a keystore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl Default for KdfParams {
+    /// N = 2^17, r = 8, p = 1: 128 MiB of memory per unlock.
+    fn default() -> Self {
+        KdfParams { log_n: 17, r: 8, p: 1 }
+    }
+}
+
+impl KdfParams {
+    /// Cost parameters N = 2^`log_n`, `r` and `p`.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, IdentityError> {
+        if log_n == 0 || log_n > MAX_KDF_LOG_N {
+            return Err(IdentityError::InvalidInput("scrypt log_n must be between 1 and 20"));
+        }
+        if r == 0 || r > MAX_KDF_R {
+            return Err(IdentityError::InvalidInput("scrypt r must be between 1 and 32"));
+        }
+        if p == 0 || p > MAX_KDF_P {
+            return Err(IdentityError::InvalidInput("scrypt p must be between 1 and 16"));
+        }
+        if (128 * u64::from(r)) << log_n > MAX_KDF_MEMORY_BYTES {
+            return Err(IdentityError::InvalidInput("scrypt parameters need more than 1 GiB of memory"));
+        }
+        scrypt::Params::new(log_n, r, p, KEY_BYTES).map_err(|_| IdentityError::InvalidInput("Invalid scrypt parameters"))?;
+        Ok(KdfParams { log_n, r, p })
+    }
+
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+
+    fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; KEY_BYTES]>, IdentityError> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, KEY_BYTES).map_err(|_| IdentityError::InvalidInput("Invalid scrypt parameters"))?;
+        let mut key = Zeroizing::new([0u8; KEY_BYTES]);
+        scrypt::scrypt(password.as_bytes(), salt, &params, key.as_mut()).map_err(|_| IdentityError::InvalidInput("Invalid scrypt output length"))?;
+        Ok(key)
+    }
+}
+
+/// An encrypted compact secret key together with its public key and address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keystore {
+    cpk: CompactPublicKey,
+    kdf: KdfParams,
+    salt: [u8; KEYSTORE_SALT_BYTES],
+    nonce: [u8; KEYSTORE_NONCE_BYTES],
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypts `csk` under `password` with the default scrypt parameters and fresh
+    /// randomness from the operating system.
+    pub fn create(csk: &CompactSecretKey, password: &str) -> Result<Self, IdentityError> {
+        Self::create_with_rng(csk, password, KdfParams::default(), &mut OsRandom)
+    }
+
+    /// Encrypts `csk` under `password` with the given scrypt parameters, drawing the salt
+    /// and nonce from `rng`. The public key is recomputed from `csk`.
+    pub fn create_with_rng<R: RandomSource>(csk: &CompactSecretKey, password: &str, kdf: KdfParams, rng: &mut R) -> Result<Self, IdentityError> {
+        let (_, cpk) = compact_key_gen_from_seed(csk.params(), SeedSK(csk.as_bytes().to_vec()))?;
+        let mut salt = [0u8; KEYSTORE_SALT_BYTES];
+        let mut nonce = [0u8; KEYSTORE_NONCE_BYTES];
+        rng.fill_bytes(&mut salt)?;
+        rng.fill_bytes(&mut nonce)?;
+
+        let key = kdf.derive_key(password, &salt)?;
+        let aad = associated_data(&cpk);
+        let ciphertext = Aes256Gcm::new(key.as_ref().into())
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: csk.as_bytes(), aad: &aad })
+            .map_err(|_| IdentityError::InvalidInput("Failed to encrypt the secret key"))?;
+        Ok(Keystore { cpk, kdf, salt, nonce, ciphertext })
+    }
+
+    /// Decrypts the secret key. Fails with `DecryptionFailed` if the password is wrong
+    /// or the file was tampered with.
+    pub fn unlock(&self, password: &str) -> Result<CompactSecretKey, IdentityError> {
+        let key = self.kdf.derive_key(password, &self.salt)?;
+        let aad = associated_data(&self.cpk);
+        let seed = Zeroizing::new(
+            Aes256Gcm::new(key.as_ref().into())
+                .decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &self.ciphertext, aad: &aad })
+                .map_err(|_| IdentityError::DecryptionFailed)?,
+        );
+        let (csk, cpk) = compact_key_gen_from_seed(self.cpk.params(), SeedSK(seed.to_vec()))?;
+        if cpk != self.cpk {
+            return Err(IdentityError::InvalidInput("Keystore secret key does not match its public key"));
+        }
+        Ok(csk)
+    }
+
+    /// Re-encrypts the secret key under `new_password` with a fresh salt and nonce,
+    /// keeping the scrypt parameters. Fails, leaving the keystore unchanged, if
+    /// `old_password` does not unlock it.
+    pub fn change_password(&mut self, old_password: &str, new_password: &str) -> Result<(), IdentityError> {
+        self.change_password_with_rng(old_password, new_password, &mut OsRandom)
+    }
+
+    pub fn change_password_with_rng<R: RandomSource>(&mut self, old_password: &str, new_password: &str, rng: &mut R) -> Result<(), IdentityError> {
+        let csk = self.unlock(old_password)?;
+        *self = Self::create_with_rng(&csk, new_password, self.kdf, rng)?;
+        Ok(())
+    }
+
+    pub fn params(&self) -> &MayoParams {
+        self.cpk.params()
+    }
+
+    pub fn public_key(&self) -> &CompactPublicKey {
+        &self.cpk
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from_public_key(&self.cpk)
+    }
+
+    pub fn kdf_params(&self) -> &KdfParams {
+        &self.kdf
+    }
+
+    /// Serializes the keystore as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        let file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            variant: self.params().name().to_string(),
+            address: self.address().to_string(),
+            public_key: hex::encode(self.cpk.as_bytes()),
+            crypto: CryptoSection {
+                kdf: KDF_NAME.to_string(),
+                kdfparams: KdfSection { log_n: self.kdf.log_n, r: self.kdf.r, p: self.kdf.p, salt: hex::encode(self.salt) },
+                cipher: CIPHER_NAME.to_string(),
+                nonce: hex::encode(self.nonce),
+                ciphertext: hex::encode(&self.ciphertext),
+            },
+        };
+        serde_json::to_string_pretty(&file).expect("keystore fields always serialize")
+    }
+
+    /// Parses a keystore, checking the version, algorithms, field lengths and that the
+    /// address belongs to the public key. The password is only checked by `unlock`.
+    pub fn from_json(json: &str) -> Result<Self, IdentityError> {
+        let file: KeystoreFile = serde_json::from_str(json).map_err(|_| IdentityError::InvalidEncoding("Invalid keystore JSON"))?;
+        if file.version != KEYSTORE_VERSION {
+            return Err(IdentityError::InvalidEncoding("Unsupported keystore version"));
+        }
+        if file.crypto.kdf != KDF_NAME || file.crypto.cipher != CIPHER_NAME {
+            return Err(IdentityError::InvalidEncoding("Unsupported keystore KDF or cipher"));
+        }
+        let params = MayoParams::get_params_by_name(&file.variant)?;
+        let cpk = CompactPublicKey::from_bytes(params, decode_hex(&file.public_key)?)?;
+        let address: Address = file.address.parse()?;
+        if !address.matches(&cpk) {
+            return Err(IdentityError::InvalidInput("Keystore address does not match its public key"));
+        }
+        let section = &file.crypto.kdfparams;
+        let kdf = KdfParams::new(section.log_n, section.r, section.p)?;
+        let salt = decode_hex(&section.salt)?
+            .try_into()
+            .map_err(|_| IdentityError::InvalidLength("Keystore salt has incorrect length"))?;
+        let nonce = decode_hex(&file.crypto.nonce)?
+            .try_into()
+            .map_err(|_| IdentityError::InvalidLength("Keystore nonce has incorrect length"))?;
+        let ciphertext = decode_hex(&file.crypto.ciphertext)?;
+        if ciphertext.len() != params.sk_seed_bytes() + TAG_BYTES {
+            return Err(IdentityError::InvalidLength("Keystore ciphertext has incorrect length"));
+        }
+        Ok(Keystore { cpk, kdf, salt, nonce, ciphertext })
+    }
+}
+
+/// `AAD_TAG || version || variant id || cpk`.
+fn associated_data(cpk: &CompactPublicKey) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(AAD_TAG.len() + 5 + cpk.as_bytes().len());
+    aad.extend_from_slice(AAD_TAG);
+    aad.extend_from_slice(&KEYSTORE_VERSION.to_be_bytes());
+    aad.push(cpk.params().id());
+    aad.extend_from_slice(cpk.as_bytes());
+    aad
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, IdentityError> {
+    hex::decode(s).map_err(|_| IdentityError::InvalidEncoding("Invalid hex encoding"))
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    variant: String,
+    address: String,
+    public_key: String,
+    crypto: CryptoSection,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CryptoSection {
+    kdf: String,
+    kdfparams: KdfSection,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KdfSection {
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+/// Encrypts `csk` under `password` and returns the keystore JSON.
+pub fn create_keystore(csk: &CompactSecretKey, password: &str) -> Result<String, IdentityError> {
+    Ok(Keystore::create(csk, password)?.to_json())
+}
+
+/// Decrypts the secret key held by keystore JSON.
+pub fn unlock_keystore(json: &str, password: &str) -> Result<CompactSecretKey, IdentityError> {
+    Keystore::from_json(json)?.unlock(password)
+}
+
+/// Re-encrypts keystore JSON under `new_password` and returns the new JSON.
+pub fn change_keystore_password(json: &str, old_password: &str, new_password: &str) -> Result<String, IdentityError> {
+    let mut keystore = Keystore::from_json(json)?;
+    keystore.change_password(old_password, new_password)?;
+    Ok(keystore.to_json())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::compact_key_gen;
+    use crate::rng::CtrDrbg;
+
+    // Cheap parameters keep the tests fast; real keystores use `KdfParams::default()`.
+    fn test_kdf() -> KdfParams {
+        KdfParams::new(10, 8, 1).unwrap()
+    }
+
+    fn test_keystore(password: &str) -> (CompactSecretKey, Keystore) {
+        let (csk, _) = compact_key_gen(&MayoParams::mayo2()).unwrap();
+        let keystore = Keystore::create_with_rng(&csk, password, test_kdf(), &mut OsRandom).unwrap();
+        (csk, keystore)
+    }
+
+    #[test]
+    fn test_create_unlock_round_trip() {
+        let (csk, keystore) = test_keystore("correct horse");
+        let json = keystore.to_json();
+        let loaded = Keystore::from_json(&json).unwrap();
+        assert_eq!(loaded, keystore);
+        assert_eq!(loaded.unlock("correct horse").unwrap(), csk);
+        assert_eq!(loaded.params().name(), "mayo2");
+        assert!(json.contains(&loaded.address().to_string()));
+        assert!(!json.contains(&hex::encode(csk.as_bytes())));
+
+        assert_eq!(loaded.unlock("wrong horse"), Err(IdentityError::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_change_password() {
+        let (csk, mut keystore) = test_keystore("old");
+        let before = keystore.clone();
+        assert_eq!(keystore.change_password("not old", "new"), Err(IdentityError::DecryptionFailed));
+        assert_eq!(keystore, before);
+
+        keystore.change_password("old", "new").unwrap();
+        assert_ne!(keystore.salt, before.salt);
+        assert_eq!(keystore.unlock("new").unwrap(), csk);
+        assert_eq!(keystore.unlock("old"), Err(IdentityError::DecryptionFailed));
+        assert_eq!(keystore.public_key(), before.public_key());
+    }
+
+    #[test]
+    fn test_deterministic_creation_with_rng() {
+        let (csk, _) = compact_key_gen(&MayoParams::mayo1()).unwrap();
+        let create = || Keystore::create_with_rng(&csk, "pw", test_kdf(), &mut CtrDrbg::new(&[7; 48])).unwrap();
+        assert_eq!(create().to_json(), create().to_json());
+    }
+
+    #[test]
+    fn test_tampered_keystores_are_rejected() {
+        let (_, keystore) = test_keystore("pw");
+        let json = keystore.to_json();
+        let edit = |pointer: &str, value: serde_json::Value| edit_json(&json, pointer, value);
+
+        // A flipped ciphertext bit, or a public key swapped together with its address,
+        // is caught by the authentication tag.
+        let mut ciphertext = keystore.ciphertext.clone();
+        ciphertext[0] ^= 1;
+        let flipped = Keystore::from_json(&edit("/crypto/ciphertext", hex::encode(ciphertext).into())).unwrap();
+        assert_eq!(flipped.unlock("pw"), Err(IdentityError::DecryptionFailed));
+        let (_, other) = test_keystore("pw");
+        let swapped = edit("/public_key", hex::encode(other.cpk.as_bytes()).into());
+        let swapped = edit_json(&swapped, "/address", other.address().to_string().into());
+        let swapped = Keystore::from_json(&swapped).unwrap();
+        assert_eq!(swapped.unlock("pw"), Err(IdentityError::DecryptionFailed));
+
+        // Inconsistent or malformed fields are rejected before any decryption.
+        assert_eq!(Keystore::from_json(&edit("/address", other.address().to_string().into())),
+                   Err(IdentityError::InvalidInput("Keystore address does not match its public key")));
+        assert_eq!(Keystore::from_json(&edit("/version", 2.into())), Err(IdentityError::InvalidEncoding("Unsupported keystore version")));
+        assert_eq!(Keystore::from_json(&edit("/crypto/kdf", "pbkdf2".into())), Err(IdentityError::InvalidEncoding("Unsupported keystore KDF or cipher")));
+        assert_eq!(Keystore::from_json(&edit("/crypto/kdfparams/log_n", 30.into())), Err(IdentityError::InvalidInput("scrypt log_n must be between 1 and 20")));
+        assert_eq!(Keystore::from_json(&edit("/crypto/kdfparams/r", 1_048_576.into())), Err(IdentityError::InvalidInput("scrypt r must be between 1 and 32")));
+        assert_eq!(Keystore::from_json(&edit("/crypto/kdfparams/p", 4_000_000_000u32.into())), Err(IdentityError::InvalidInput("scrypt p must be between 1 and 16")));
+        assert_eq!(KdfParams::new(20, 16, 1), Err(IdentityError::InvalidInput("scrypt parameters need more than 1 GiB of memory")));
+        assert!(KdfParams::new(20, 8, 1).is_ok());
+        assert_eq!(Keystore::from_json(&edit("/crypto/nonce", "00".into())), Err(IdentityError::InvalidLength("Keystore nonce has incorrect length")));
+        assert_eq!(Keystore::from_json(&edit("/variant", "mayo9".into())), Err(IdentityError::UnknownVariant));
+        assert_eq!(Keystore::from_json("{}"), Err(IdentityError::InvalidEncoding("Invalid keystore JSON")));
+    }
+
+    fn edit_json(json: &str, pointer: &str, value: serde_json::Value) -> String {
+        let mut edited: serde_json::Value = serde_json::from_str(json).unwrap();
+        *edited.pointer_mut(pointer).unwrap() = value;
+        edited.to_string()
+    }
+}
@@ -1,23 +1,45 @@
 // use wasm_bindgen::prelude::*; // Removed as per compiler warning
 // use blake2::{Blake2b512, Digest}; // Removed as per compiler warning
 
+pub mod error;
 pub mod params;
 pub mod types;
 pub mod hash;
+pub mod rng;
 pub mod aes_ctr;
 pub mod gf;
 pub mod matrix;
+pub mod packed;
 pub mod codec;
 pub mod keygen;
 pub mod solver;
 pub mod sign;
 pub mod verify;
+pub mod stream;
+pub mod batch;
+pub mod encoding;
+pub mod derive;
+pub mod trisection;
+pub mod address;
+pub mod multisig;
+pub mod keystore;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(test)]
+mod kat;
+#[cfg(test)]
+mod dudect;
+
+pub use error::IdentityError;
+pub use sign::SigningKey;
+pub use verify::VerifyingKey;
 
 pub mod api;
-pub use api::{keypair, sign, open};
+pub use api::{keypair, keypair_from_mnemonic, keypair_from_seed, sign, sign_detached, open, verify, verify_batch};
 
 pub mod spacetime_hash;
-pub use spacetime_hash::hash_compact_secret_key;
+pub use spacetime_hash::{hash_compact_secret_key, hash_message, hash_public_key, hash_public_key_at};
 
 // Placeholder for any top-level library functions or re-exports if needed in the future.
 
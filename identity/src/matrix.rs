@@ -1,7 +1,8 @@
 //! Implements matrix operations over GF(16).
 
+use crate::error::IdentityError;
 use crate::types::{GFElement, GFMatrix, GFVector};
-use crate::gf::{gf16_add, gf16_mul, gf16_sub}; // gf16_sub is same as gf16_add
+use crate::gf::{gf16_add, gf16_inv_ct, gf16_mul, gf16_sub}; // gf16_sub is same as gf16_add
 
 // --- Implementation of GFMatrix helper functions ---
 // The GFMatrix struct is defined in types.rs. Here we add methods to it.
@@ -111,9 +112,9 @@ impl GFMatrix {
 
 /// Adds two matrices over GF(16).
 /// Returns Err if dimensions are incompatible.
-pub fn matrix_add(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, &'static str> {
+pub fn matrix_add(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, IdentityError> {
     if a.num_rows() != b.num_rows() || a.num_cols() != b.num_cols() {
-        return Err("Matrices must have the same dimensions for addition");
+        return Err(IdentityError::DimensionMismatch("Matrices must have the same dimensions for addition"));
     }
     let mut result_data = Vec::with_capacity(a.data.len());
     for i in 0..a.data.len() {
@@ -125,7 +126,7 @@ pub fn matrix_add(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, &'static str>
 /// Subtracts matrix b from matrix a over GF(16).
 /// (Identical to addition in GF(2^n)).
 /// Returns Err if dimensions are incompatible.
-pub fn matrix_sub(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, &'static str> {
+pub fn matrix_sub(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, IdentityError> {
     matrix_add(a, b) // In GF(2^n), subtraction is XOR, same as addition
 }
 
@@ -140,9 +141,9 @@ pub fn matrix_scalar_mul(scalar: GFElement, matrix: &GFMatrix) -> GFMatrix {
 
 /// Multiplies two matrices (a * b) over GF(16).
 /// Returns Err if dimensions are incompatible (a.cols != b.rows).
-pub fn matrix_mul(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, &'static str> {
+pub fn matrix_mul(a: &GFMatrix, b: &GFMatrix) -> Result<GFMatrix, IdentityError> {
     if a.num_cols() != b.num_rows() {
-        return Err("Number of columns in the first matrix must equal number of rows in the second");
+        return Err(IdentityError::DimensionMismatch("Number of columns in the first matrix must equal number of rows in the second"));
     }
     let result_rows = a.num_rows();
     let result_cols = b.num_cols();
@@ -176,15 +177,15 @@ pub fn matrix_transpose(matrix: &GFMatrix) -> GFMatrix {
 /// Multiplies a matrix by a vector (matrix * vector) over GF(16).
 /// Treats the vector as a column vector.
 /// Returns Err if dimensions are incompatible (matrix.cols != vector.len()).
-pub fn matrix_vec_mul(matrix: &GFMatrix, vector: &GFVector) -> Result<GFVector, &'static str> {
+pub fn matrix_vec_mul(matrix: &GFMatrix, vector: &GFVector) -> Result<GFVector, IdentityError> {
     if matrix.num_cols() != vector.len() {
-        return Err("Matrix columns must match vector length for multiplication");
+        return Err(IdentityError::DimensionMismatch("Matrix columns must match vector length for multiplication"));
     }
     let mut result_vector = Vec::with_capacity(matrix.num_rows());
     for r in 0..matrix.num_rows() {
         let mut sum = GFElement(0);
-        for c in 0..matrix.num_cols() {
-            sum = gf16_add(sum, gf16_mul(matrix.get_unsafe(r, c), vector[c]));
+        for (c, v_c) in vector.iter().enumerate() {
+            sum = gf16_add(sum, gf16_mul(matrix.get_unsafe(r, c), *v_c));
         }
         result_vector.push(sum);
     }
@@ -193,9 +194,9 @@ pub fn matrix_vec_mul(matrix: &GFMatrix, vector: &GFVector) -> Result<GFVector,
 
 /// Subtracts vector `b` from vector `a` over GF(16) (element-wise).
 /// Returns Err if dimensions are incompatible.
-pub fn matrix_sub_vectors_gfvector(a: &GFVector, b: &GFVector) -> Result<GFVector, &'static str> {
+pub fn matrix_sub_vectors_gfvector(a: &GFVector, b: &GFVector) -> Result<GFVector, IdentityError> {
     if a.len() != b.len() {
-        return Err("Vector dimensions must match for subtraction");
+        return Err(IdentityError::DimensionMismatch("Vector dimensions must match for subtraction"));
     }
     let mut result = Vec::with_capacity(a.len());
     for i in 0..a.len() {
@@ -209,9 +210,9 @@ pub fn matrix_sub_vectors_gfvector(a: &GFVector, b: &GFVector) -> Result<GFVecto
 /// Off-diagonal elements are M[i,j]+M[j,i].
 /// If M is upper triangular, M_sym[i,i]=M[i,i], M_sym[i,j]=M[i,j] for i<j, M_sym[j,i]=M[i,j] for j<i.
 /// This function computes M_sym = M + M^T directly.
-pub fn matrix_symmetrize(matrix: &GFMatrix) -> Result<GFMatrix, &'static str> {
+pub fn matrix_symmetrize(matrix: &GFMatrix) -> Result<GFMatrix, IdentityError> {
     if matrix.num_rows() != matrix.num_cols() {
-        return Err("Matrix must be square to be symmetrized");
+        return Err(IdentityError::DimensionMismatch("Matrix must be square to be symmetrized"));
     }
     let n = matrix.num_rows();
     let mut sym_matrix = GFMatrix::zero(n, n);
@@ -226,29 +227,48 @@ pub fn matrix_symmetrize(matrix: &GFMatrix) -> Result<GFMatrix, &'static str> {
 }
 
 
+/// Computes Upper(M) for a square matrix M: the upper triangular matrix with the same
+/// quadratic form, i.e. Upper(M)[i,i] = M[i,i], Upper(M)[i,j] = M[i,j] + M[j,i] for i < j,
+/// and zero below the diagonal.
+pub fn matrix_upper(matrix: &GFMatrix) -> Result<GFMatrix, IdentityError> {
+    if matrix.num_rows() != matrix.num_cols() {
+        return Err(IdentityError::DimensionMismatch("Matrix must be square to compute Upper(M)"));
+    }
+    let n = matrix.num_rows();
+    let mut upper = GFMatrix::zero(n, n);
+    for r in 0..n {
+        upper.set_val(r, r, matrix.get_unsafe(r, r));
+        for c in (r + 1)..n {
+            upper.set_val(r, c, gf16_add(matrix.get_unsafe(r, c), matrix.get_unsafe(c, r)));
+        }
+    }
+    Ok(upper)
+}
+
+
 /// Multiplies a row vector (transpose of GFVector) by a matrix: v^T * M.
 /// vector_lhs is treated as a 1xN row vector. matrix_rhs is NxK. Result is 1xK (GFVector).
-pub fn matrix_vec_mul_transpose_gfvector(vector_lhs: &GFVector, matrix_rhs: &GFMatrix) -> Result<GFVector, &'static str> {
+pub fn matrix_vec_mul_transpose_gfvector(vector_lhs: &GFVector, matrix_rhs: &GFMatrix) -> Result<GFVector, IdentityError> {
     if vector_lhs.len() != matrix_rhs.num_rows() {
-        return Err("Vector length must match matrix rows for v^T * M multiplication");
+        return Err(IdentityError::DimensionMismatch("Vector length must match matrix rows for v^T * M multiplication"));
     }
     let num_cols_result = matrix_rhs.num_cols();
     let mut result_vector = vec![GFElement(0); num_cols_result];
 
-    for c_res in 0..num_cols_result { // For each column in the result vector (and matrix_rhs)
+    for (c_res, result_c) in result_vector.iter_mut().enumerate() { // For each column in the result vector (and matrix_rhs)
         let mut sum = GFElement(0);
-        for r_m_idx in 0..matrix_rhs.num_rows() { // Summing down the column of matrix_rhs
-            sum = gf16_add(sum, gf16_mul(vector_lhs[r_m_idx], matrix_rhs.get_unsafe(r_m_idx, c_res)));
+        for (r_m_idx, v_r) in vector_lhs.iter().enumerate() { // Summing down the column of matrix_rhs
+            sum = gf16_add(sum, gf16_mul(*v_r, matrix_rhs.get_unsafe(r_m_idx, c_res)));
         }
-        result_vector[c_res] = sum;
+        *result_c = sum;
     }
     Ok(result_vector)
 }
 
 /// Computes the dot product of two vectors: a^T * b.
-pub fn vector_dot_product(a: &GFVector, b: &GFVector) -> Result<GFElement, &'static str> {
+pub fn vector_dot_product(a: &GFVector, b: &GFVector) -> Result<GFElement, IdentityError> {
     if a.len() != b.len() {
-        return Err("Vectors must have the same length for dot product");
+        return Err(IdentityError::DimensionMismatch("Vectors must have the same length for dot product"));
     }
     if a.is_empty() { // Or b.is_empty(), since lengths must match
         return Ok(GFElement(0)); // Dot product of empty vectors is 0
@@ -260,6 +280,111 @@ pub fn vector_dot_product(a: &GFVector, b: &GFVector) -> Result<GFElement, &'sta
     Ok(sum)
 }
 
+/// Multiplies an m-vector by E^power, where E is the m x m matrix representing
+/// multiplication by z in GF(16)[z]/(f(z)), with f(z) = z^m + f_tail(z).
+/// The vector `u` is read as the polynomial u_0 + u_1 z + ... + u_{m-1} z^{m-1}.
+pub fn e_matrix_pow_vec_mul(u: &GFVector, power: usize, f_tail: &[u8]) -> GFVector {
+    let mut result = u.clone();
+    if result.is_empty() {
+        return result;
+    }
+    let m = result.len();
+    for _ in 0..power {
+        // z * u(z): shift every coefficient up and fold z^m back in as f_tail(z).
+        let overflow = result[m - 1];
+        result.rotate_right(1);
+        result[0] = GFElement(0);
+        for (coeff, tail) in result.iter_mut().zip(f_tail) {
+            *coeff = gf16_add(*coeff, gf16_mul(overflow, GFElement(*tail)));
+        }
+    }
+    result
+}
+
+/// Multiplies a matrix with m rows by E^power, applying `e_matrix_pow_vec_mul` to each column.
+pub fn e_matrix_pow_mul(matrix: &GFMatrix, power: usize, f_tail: &[u8]) -> GFMatrix {
+    let columns: Vec<GFVector> = matrix_transpose(matrix)
+        .to_vectors()
+        .iter()
+        .map(|column| e_matrix_pow_vec_mul(column, power, f_tail))
+        .collect();
+    if columns.is_empty() {
+        return GFMatrix::zero(matrix.num_rows(), 0);
+    }
+    matrix_transpose(&GFMatrix::from_vectors(columns))
+}
+
+
+/// Computes the determinant of a square matrix over GF(16) by Gaussian elimination.
+/// Row swaps do not change the sign, since -1 = 1 in characteristic 2.
+pub fn matrix_determinant(matrix: &GFMatrix) -> Result<GFElement, IdentityError> {
+    if matrix.num_rows() != matrix.num_cols() {
+        return Err(IdentityError::DimensionMismatch("Determinant requires a square matrix"));
+    }
+    let size = matrix.num_rows();
+    let mut work = matrix.clone();
+    let mut det = GFElement(1);
+    for col in 0..size {
+        let Some(pivot_row) = (col..size).find(|&r| work.get_unsafe(r, col).0 != 0) else {
+            return Ok(GFElement(0));
+        };
+        swap_rows(&mut work, col, pivot_row);
+        let pivot = work.get_unsafe(col, col);
+        det = gf16_mul(det, pivot);
+        let inv_pivot = gf16_inv_ct(pivot);
+        for r in col + 1..size {
+            let factor = gf16_mul(work.get_unsafe(r, col), inv_pivot);
+            for c in col..size {
+                let term = gf16_mul(factor, work.get_unsafe(col, c));
+                work.set_val(r, c, gf16_sub(work.get_unsafe(r, c), term));
+            }
+        }
+    }
+    Ok(det)
+}
+
+/// Computes the inverse of a square matrix over GF(16) by Gauss-Jordan elimination
+/// on [M | I]. Returns `NotInvertible` if the matrix is singular.
+pub fn matrix_inverse(matrix: &GFMatrix) -> Result<GFMatrix, IdentityError> {
+    if matrix.num_rows() != matrix.num_cols() {
+        return Err(IdentityError::DimensionMismatch("Inverse requires a square matrix"));
+    }
+    let size = matrix.num_rows();
+    let mut work = matrix.clone();
+    let mut inverse = GFMatrix::identity(size);
+    for col in 0..size {
+        let pivot_row = (col..size).find(|&r| work.get_unsafe(r, col).0 != 0).ok_or(IdentityError::NotInvertible)?;
+        swap_rows(&mut work, col, pivot_row);
+        swap_rows(&mut inverse, col, pivot_row);
+
+        let inv_pivot = gf16_inv_ct(work.get_unsafe(col, col));
+        for c in 0..size {
+            work.set_val(col, c, gf16_mul(work.get_unsafe(col, c), inv_pivot));
+            inverse.set_val(col, c, gf16_mul(inverse.get_unsafe(col, c), inv_pivot));
+        }
+        for r in (0..size).filter(|&r| r != col) {
+            let factor = work.get_unsafe(r, col);
+            if factor.0 == 0 {
+                continue;
+            }
+            for c in 0..size {
+                work.set_val(r, c, gf16_sub(work.get_unsafe(r, c), gf16_mul(factor, work.get_unsafe(col, c))));
+                inverse.set_val(r, c, gf16_sub(inverse.get_unsafe(r, c), gf16_mul(factor, inverse.get_unsafe(col, c))));
+            }
+        }
+    }
+    Ok(inverse)
+}
+
+fn swap_rows(matrix: &mut GFMatrix, a: usize, b: usize) {
+    if a != b {
+        let cols = matrix.num_cols();
+        for c in 0..cols {
+            matrix.data.swap(a * cols + c, b * cols + c);
+        }
+    }
+}
+
 
 // --- Unit Tests ---
 #[cfg(test)]
@@ -310,12 +435,12 @@ mod tests {
         //        [2, 5],
         //        [3, 6]]
         // v^T * M = [ (1*1 + 2*2 + 3*3), (1*4 + 2*5 + 3*6) ]
-        //         = [ (1^4^5), (4^A^2) ] (using 3*3=5, 2*5=A, 3*6=A^4=2)
-        //         = [ (0), (4^A=E ^2 = C) ] = [0, C]
+        //         = [ (1^4^5), (4^A^A) ] (using 3*3=5, 2*5=A, 3*6=x^3+x=A)
+        //         = [ (0), (4) ] = [0, 4]
         let v = vec_gf(vec![gf(1), gf(2), gf(3)]);
         let m_data = vec![gf(1),gf(4), gf(2),gf(5), gf(3),gf(6)];
         let m = GFMatrix::new_with_data(3,2,m_data);
-        let expected = vec_gf(vec![gf(0), gf(0xC)]);
+        let expected = vec_gf(vec![gf(0), gf(0x4)]);
         assert_eq!(matrix_vec_mul_transpose_gfvector(&v, &m).unwrap(), expected);
 
         let v_short = vec_gf(vec![gf(1), gf(2)]);
@@ -326,8 +451,8 @@ mod tests {
     fn test_vector_dot_product() {
         let v1 = vec_gf(vec![gf(1), gf(2), gf(3)]);
         let v2 = vec_gf(vec![gf(4), gf(5), gf(6)]);
-        // 1*4 + 2*5 + 3*6 = 4 ^ A ^ (A^4=2) = 4^A=E ^2 = C
-        assert_eq!(vector_dot_product(&v1, &v2).unwrap(), gf(0xC));
+        // 1*4 + 2*5 + 3*6 = 4 ^ A ^ A = 4
+        assert_eq!(vector_dot_product(&v1, &v2).unwrap(), gf(0x4));
         
         let v_empty1 = vec_gf(vec![]);
         let v_empty2 = vec_gf(vec![]);
@@ -500,4 +625,90 @@ mod tests {
         let v3 = vec_gf(vec![gf(1)]);
         assert!(matrix_sub_vectors_gfvector(&v1, &v3).is_err());
     }
+
+    #[test]
+    fn test_e_matrix_pow_vec_mul() {
+        // f(z) = z^4 + x*z + 1 over a toy m = 4.
+        let f_tail = [1, 2, 0, 0];
+        let u = vec_gf(vec![gf(1), gf(0), gf(0), gf(0)]); // u(z) = 1
+        assert_eq!(e_matrix_pow_vec_mul(&u, 0, &f_tail), u);
+        assert_eq!(e_matrix_pow_vec_mul(&u, 3, &f_tail), vec_gf(vec![gf(0), gf(0), gf(0), gf(1)])); // z^3
+        // z^4 = x*z + 1
+        assert_eq!(e_matrix_pow_vec_mul(&u, 4, &f_tail), vec_gf(vec![gf(1), gf(2), gf(0), gf(0)]));
+        // z^5 = x*z^2 + z
+        assert_eq!(e_matrix_pow_vec_mul(&u, 5, &f_tail), vec_gf(vec![gf(0), gf(1), gf(2), gf(0)]));
+        // E^a E^b = E^(a+b)
+        let w = vec_gf(vec![gf(7), gf(0xA), gf(3), gf(0xF)]);
+        let twice = e_matrix_pow_vec_mul(&e_matrix_pow_vec_mul(&w, 6, &f_tail), 5, &f_tail);
+        assert_eq!(twice, e_matrix_pow_vec_mul(&w, 11, &f_tail));
+    }
+
+    #[test]
+    fn test_e_matrix_pow_mul_columns() {
+        let f_tail = [8, 1, 1, 0];
+        let m = GFMatrix::from_vectors(vec![
+            vec![gf(1), gf(2)],
+            vec![gf(3), gf(4)],
+            vec![gf(5), gf(6)],
+            vec![gf(7), gf(8)],
+        ]);
+        let em = e_matrix_pow_mul(&m, 3, &f_tail);
+        let columns = matrix_transpose(&m).to_vectors();
+        let em_columns = matrix_transpose(&em).to_vectors();
+        for (column, em_column) in columns.iter().zip(&em_columns) {
+            assert_eq!(&e_matrix_pow_vec_mul(column, 3, &f_tail), em_column);
+        }
+    }
+
+    #[test]
+    fn test_matrix_upper() {
+        // M = [[1,2],[3,4]] -> Upper(M) = [[1, 2^3=1],[0,4]]
+        let m = GFMatrix::new_with_data(2, 2, vec![gf(1), gf(2), gf(3), gf(4)]);
+        let upper = matrix_upper(&m).unwrap();
+        assert_eq!(upper.data, vec![gf(1), gf(1), gf(0), gf(4)]);
+
+        // Same quadratic form: x^T M x == x^T Upper(M) x
+        let x = vec_gf(vec![gf(7), gf(0xB)]);
+        let q_m = vector_dot_product(&x, &matrix_vec_mul(&m, &x).unwrap()).unwrap();
+        let q_upper = vector_dot_product(&x, &matrix_vec_mul(&upper, &x).unwrap()).unwrap();
+        assert_eq!(q_m, q_upper);
+
+        assert!(matrix_upper(&GFMatrix::zero(2, 3)).is_err());
+    }
+
+    #[test]
+    fn test_matrix_determinant() {
+        // det [[a, b], [c, d]] = ad - bc = ad + bc.
+        let m = GFMatrix::from_vectors(vec![vec![gf(0x2), gf(0x3)], vec![gf(0x5), gf(0x7)]]);
+        let expected = gf16_add(gf16_mul(gf(0x2), gf(0x7)), gf16_mul(gf(0x3), gf(0x5)));
+        assert_eq!(matrix_determinant(&m).unwrap(), expected);
+        assert_eq!(matrix_determinant(&GFMatrix::identity(4)).unwrap(), gf(1));
+        assert_eq!(matrix_determinant(&GFMatrix::zero(0, 0)).unwrap(), gf(1));
+
+        // A repeated row makes the matrix singular; a zero leading entry needs a swap.
+        let singular = GFMatrix::from_vectors(vec![vec![gf(1), gf(2)], vec![gf(1), gf(2)]]);
+        assert_eq!(matrix_determinant(&singular).unwrap(), gf(0));
+        let swapped = GFMatrix::from_vectors(vec![vec![gf(0), gf(3)], vec![gf(4), gf(9)]]);
+        assert_eq!(matrix_determinant(&swapped).unwrap(), gf16_mul(gf(3), gf(4)));
+        assert!(matrix_determinant(&GFMatrix::zero(2, 3)).is_err());
+    }
+
+    #[test]
+    fn test_matrix_inverse() {
+        let m = GFMatrix::from_vectors(vec![
+            vec![gf(0x0), gf(0x3), gf(0x1)],
+            vec![gf(0x7), gf(0x2), gf(0x0)],
+            vec![gf(0x1), gf(0xB), gf(0x5)],
+        ]);
+        assert_ne!(matrix_determinant(&m).unwrap(), gf(0));
+        let inverse = matrix_inverse(&m).unwrap();
+        assert_eq!(matrix_mul(&m, &inverse).unwrap(), GFMatrix::identity(3));
+        assert_eq!(matrix_mul(&inverse, &m).unwrap(), GFMatrix::identity(3));
+        // det(M^-1) = det(M)^-1.
+        assert_eq!(gf16_mul(matrix_determinant(&m).unwrap(), matrix_determinant(&inverse).unwrap()), gf(1));
+
+        let singular = GFMatrix::from_vectors(vec![vec![gf(1), gf(2)], vec![gf(2), gf(4)]]);
+        assert_eq!(matrix_inverse(&singular), Err(IdentityError::NotInvertible));
+        assert!(matrix_inverse(&GFMatrix::zero(3, 2)).is_err());
+    }
 }
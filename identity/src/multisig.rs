@@ -0,0 +1,337 @@
+//! k-of-n multi-signatures over Cubix identities.
+//!
+//! A `MultisigPolicy` is a threshold k and an ordered list of n distinct compact public
+//! keys, possibly of different MAYO variants. Its canonical encoding is
+//! `version || k || n || (variant_id || cpk)*` and its id is
+//! `Blake2b-256("cubix/multisig/policy" || encoding)`, so reordering the keys or changing
+//! k gives a different policy. A group holds funds under the `PolicyAddress` of its
+//! policy, a Bech32m "cbx" address with version 1 whose payload is
+//! `1 || k || id`, the full 32-byte id, so the address commits to the policy with
+//! 128-bit collision resistance.
+//!
+//! A `MultisigEnvelope` carries the policy and one ordinary MAYO signature per signing
+//! key, identified by its position in the policy. Each signer signs
+//! `"cubix/multisig/message" || id || message` rather than the bare message, so a share
+//! cannot be replayed under another policy. The envelope verifies if it holds at least
+//! k signatures from distinct keys and every one of them is valid.
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+
+pub use crate::address::{PolicyAddress, MAX_POLICY_KEYS, POLICY_ADDRESS_VERSION, POLICY_ID_BYTES};
+use crate::error::IdentityError;
+use crate::keygen::expand_pk;
+use crate::params::MayoParams;
+use crate::sign::sign_message;
+use crate::types::{CompactPublicKey, ExpandedSecretKey, Message, Signature};
+use crate::verify::verify_signature;
+
+/// Current policy encoding version.
+pub const POLICY_VERSION: u8 = 1;
+
+const POLICY_TAG: &[u8] = b"cubix/multisig/policy";
+const MESSAGE_TAG: &[u8] = b"cubix/multisig/message";
+
+/// A threshold and the ordered public keys allowed to sign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigPolicy {
+    threshold: u8,
+    keys: Vec<CompactPublicKey>,
+}
+
+impl MultisigPolicy {
+    /// A policy requiring `threshold` of `keys`. Fails unless 1 <= threshold <= n <= 16
+    /// and the keys are distinct.
+    pub fn new(threshold: usize, keys: Vec<CompactPublicKey>) -> Result<Self, IdentityError> {
+        if keys.is_empty() || keys.len() > MAX_POLICY_KEYS {
+            return Err(IdentityError::InvalidInput("Policy must have between 1 and 16 keys"));
+        }
+        if threshold == 0 || threshold > keys.len() {
+            return Err(IdentityError::InvalidInput("Policy threshold must be between 1 and the number of keys"));
+        }
+        if keys.iter().enumerate().any(|(i, key)| keys[..i].contains(key)) {
+            return Err(IdentityError::InvalidInput("Policy keys must be distinct"));
+        }
+        Ok(MultisigPolicy { threshold: threshold as u8, keys })
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    pub fn keys(&self) -> &[CompactPublicKey] {
+        &self.keys
+    }
+
+    /// Returns the position of `cpk` in the policy, if it is one of the keys.
+    pub fn index_of(&self, cpk: &CompactPublicKey) -> Option<usize> {
+        self.keys.iter().position(|key| key == cpk)
+    }
+
+    /// The canonical encoding `version || k || n || (variant_id || cpk)*`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![POLICY_VERSION, self.threshold, self.keys.len() as u8];
+        for key in &self.keys {
+            out.push(key.params().id());
+            out.extend_from_slice(key.as_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        let (policy, rest) = Self::parse(bytes)?;
+        if !rest.is_empty() {
+            return Err(IdentityError::InvalidLength("Policy has trailing bytes"));
+        }
+        Ok(policy)
+    }
+
+    /// Parses a policy from the front of `bytes`, returning it and the remaining bytes.
+    fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), IdentityError> {
+        let [version, threshold, count, tail @ ..] = bytes else {
+            return Err(IdentityError::InvalidLength("Policy is too short"));
+        };
+        let mut rest = tail;
+        if *version != POLICY_VERSION {
+            return Err(IdentityError::InvalidEncoding("Unsupported policy version"));
+        }
+        let mut keys = Vec::with_capacity(*count as usize);
+        for _ in 0..*count {
+            let (&id, tail) = rest.split_first().ok_or(IdentityError::InvalidLength("Policy is too short"))?;
+            let params = MayoParams::from_id(id).ok_or(IdentityError::UnknownVariant)?;
+            if tail.len() < params.cpk_bytes() {
+                return Err(IdentityError::InvalidLength("Policy is too short"));
+            }
+            let (key, tail) = tail.split_at(params.cpk_bytes());
+            keys.push(CompactPublicKey::from_bytes(params, key.to_vec())?);
+            rest = tail;
+        }
+        Ok((MultisigPolicy::new(*threshold as usize, keys)?, rest))
+    }
+
+    /// `Blake2b-256("cubix/multisig/policy" || to_bytes())`.
+    pub fn id(&self) -> [u8; POLICY_ID_BYTES] {
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update(POLICY_TAG);
+        hasher.update(self.to_bytes());
+        hasher.finalize().into()
+    }
+
+    pub fn address(&self) -> PolicyAddress {
+        PolicyAddress::new(self.threshold, self.id())
+    }
+
+    /// The message each signer actually signs for `message` under this policy.
+    pub fn signing_message(&self, message: &[u8]) -> Message {
+        let mut bytes = Vec::with_capacity(MESSAGE_TAG.len() + POLICY_ID_BYTES + message.len());
+        bytes.extend_from_slice(MESSAGE_TAG);
+        bytes.extend_from_slice(&self.id());
+        bytes.extend_from_slice(message);
+        Message(bytes)
+    }
+}
+
+impl PolicyAddress {
+    /// Returns true if `policy` is the policy behind this address.
+    pub fn matches(&self, policy: &MultisigPolicy) -> bool {
+        policy.address() == *self
+    }
+}
+
+/// A policy and the signature shares collected for one message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigEnvelope {
+    policy: MultisigPolicy,
+    /// Shares sorted by key index, at most one per key.
+    signatures: Vec<(u8, Signature)>,
+}
+
+impl MultisigEnvelope {
+    /// An envelope for `policy` with no signatures yet.
+    pub fn new(policy: MultisigPolicy) -> Self {
+        MultisigEnvelope { policy, signatures: Vec::new() }
+    }
+
+    pub fn policy(&self) -> &MultisigPolicy {
+        &self.policy
+    }
+
+    /// The collected shares as (key index, signature) pairs, by key index.
+    pub fn signatures(&self) -> &[(u8, Signature)] {
+        &self.signatures
+    }
+
+    /// Adds the share of key `index`, which must be a signature of
+    /// `policy.signing_message(message)`. Fails if the index is out of range, the
+    /// signature is of another variant than the key, or the key already signed.
+    pub fn add_signature(&mut self, index: usize, signature: Signature) -> Result<(), IdentityError> {
+        let key = self.policy.keys.get(index).ok_or(IdentityError::InvalidInput("Signer index is not in the policy"))?;
+        if signature.params() != key.params() {
+            return Err(IdentityError::VariantMismatch);
+        }
+        match self.signatures.binary_search_by_key(&(index as u8), |(i, _)| *i) {
+            Ok(_) => Err(IdentityError::InvalidInput("Key has already signed")),
+            Err(position) => {
+                self.signatures.insert(position, (index as u8, signature));
+                Ok(())
+            }
+        }
+    }
+
+    /// Signs `message` as key `index` with its expanded secret key and adds the share.
+    pub fn sign(&mut self, index: usize, esk: &ExpandedSecretKey, message: &[u8]) -> Result<(), IdentityError> {
+        let signature = sign_message(esk, &self.policy.signing_message(message))?;
+        self.add_signature(index, signature)
+    }
+
+    /// Returns true if at least `threshold` distinct keys signed `message` and every
+    /// share in the envelope is valid.
+    pub fn verify(&self, message: &[u8]) -> Result<bool, IdentityError> {
+        if self.signatures.len() < self.policy.threshold() {
+            return Ok(false);
+        }
+        let signed = self.policy.signing_message(message);
+        for (index, signature) in &self.signatures {
+            let epk = expand_pk(&self.policy.keys[*index as usize])?;
+            if !verify_signature(&epk, &signed, signature)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// `policy || count || (index || signature)*`, where each signature has the length
+    /// of its key's variant.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.policy.to_bytes();
+        out.push(self.signatures.len() as u8);
+        for (index, signature) in &self.signatures {
+            out.push(*index);
+            out.extend_from_slice(signature.as_bytes());
+        }
+        out
+    }
+
+    /// Decodes an envelope, applying the same checks as `add_signature` to every share.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        let (policy, rest) = MultisigPolicy::parse(bytes)?;
+        let (&count, mut rest) = rest.split_first().ok_or(IdentityError::InvalidLength("Multisig envelope is too short"))?;
+        let mut envelope = MultisigEnvelope::new(policy);
+        for _ in 0..count {
+            let (&index, tail) = rest.split_first().ok_or(IdentityError::InvalidLength("Multisig envelope is too short"))?;
+            let params = *envelope.policy.keys.get(index as usize).ok_or(IdentityError::InvalidInput("Signer index is not in the policy"))?.params();
+            if tail.len() < params.sig_bytes() {
+                return Err(IdentityError::InvalidLength("Multisig envelope is too short"));
+            }
+            let (signature, tail) = tail.split_at(params.sig_bytes());
+            envelope.add_signature(index as usize, Signature::from_bytes(params, signature.to_vec())?)?;
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(IdentityError::InvalidLength("Multisig envelope has trailing bytes"));
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::{is_valid_address, Address};
+    use crate::keygen::{compact_key_gen, expand_sk};
+    use crate::types::CompactSecretKey;
+
+    fn group(variants: &[MayoParams]) -> (Vec<ExpandedSecretKey>, Vec<CompactPublicKey>) {
+        variants
+            .iter()
+            .map(|params| {
+                let (csk, cpk): (CompactSecretKey, CompactPublicKey) = compact_key_gen(params).unwrap();
+                (expand_sk(&csk).unwrap(), cpk)
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn test_two_of_three() {
+        let (esks, cpks) = group(&[MayoParams::mayo1(), MayoParams::mayo2(), MayoParams::mayo1()]);
+        let policy = MultisigPolicy::new(2, cpks).unwrap();
+        let message = b"move 5 CBX from the treasury";
+
+        let mut envelope = MultisigEnvelope::new(policy.clone());
+        envelope.sign(2, &esks[2], message).unwrap();
+        assert!(!envelope.verify(message).unwrap());
+        envelope.sign(0, &esks[0], message).unwrap();
+        assert!(envelope.verify(message).unwrap());
+        assert!(!envelope.verify(b"move 500 CBX from the treasury").unwrap());
+        assert_eq!(envelope.signatures().iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 2]);
+
+        let decoded = MultisigEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert!(decoded.verify(message).unwrap());
+
+        assert_eq!(envelope.sign(0, &esks[0], message), Err(IdentityError::InvalidInput("Key has already signed")));
+        assert_eq!(envelope.sign(3, &esks[0], message), Err(IdentityError::InvalidInput("Signer index is not in the policy")));
+        assert_eq!(envelope.sign(1, &esks[0], message), Err(IdentityError::VariantMismatch));
+    }
+
+    #[test]
+    fn test_shares_are_bound_to_the_signer_and_policy() {
+        let (esks, cpks) = group(&[MayoParams::mayo1(), MayoParams::mayo1()]);
+        let message = b"payload";
+        let policy = MultisigPolicy::new(1, cpks.clone()).unwrap();
+
+        // A share filed under the wrong key, or one signed for another policy, fails.
+        let mut misfiled = MultisigEnvelope::new(policy.clone());
+        misfiled.sign(1, &esks[0], message).unwrap();
+        assert!(!misfiled.verify(message).unwrap());
+
+        let other = MultisigPolicy::new(2, cpks).unwrap();
+        let replayed = sign_message(&esks[0], &other.signing_message(message)).unwrap();
+        let mut envelope = MultisigEnvelope::new(policy);
+        envelope.add_signature(0, replayed).unwrap();
+        assert!(!envelope.verify(message).unwrap());
+    }
+
+    #[test]
+    fn test_policy_validation_and_encoding() {
+        let (_, cpks) = group(&[MayoParams::mayo1(), MayoParams::mayo2()]);
+        assert!(MultisigPolicy::new(0, cpks.clone()).is_err());
+        assert!(MultisigPolicy::new(3, cpks.clone()).is_err());
+        assert!(MultisigPolicy::new(1, vec![]).is_err());
+        assert_eq!(MultisigPolicy::new(1, vec![cpks[0].clone(), cpks[0].clone()]),
+                   Err(IdentityError::InvalidInput("Policy keys must be distinct")));
+
+        let policy = MultisigPolicy::new(2, cpks.clone()).unwrap();
+        let bytes = policy.to_bytes();
+        assert_eq!(MultisigPolicy::from_bytes(&bytes), Ok(policy.clone()));
+        assert_eq!(policy.index_of(&cpks[1]), Some(1));
+        assert!(MultisigPolicy::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MultisigPolicy::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let reordered = MultisigPolicy::new(2, vec![cpks[1].clone(), cpks[0].clone()]).unwrap();
+        assert_ne!(reordered.id(), policy.id());
+        assert_ne!(MultisigPolicy::new(1, cpks).unwrap().id(), policy.id());
+    }
+
+    #[test]
+    fn test_policy_address() {
+        let cpks: Vec<CompactPublicKey> = (1..=3u8)
+            .map(|b| CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![b; MayoParams::mayo1().cpk_bytes()]).unwrap())
+            .collect();
+        let policy = MultisigPolicy::new(2, cpks).unwrap();
+        let address = policy.address();
+        let encoded = address.to_string();
+        assert!(encoded.starts_with("cbx1"));
+        assert_eq!(encoded.parse::<PolicyAddress>(), Ok(address));
+        assert_eq!(address.threshold(), 2);
+        assert_eq!(address.id(), &policy.id());
+        assert_eq!(encoded.len(), 4 + 55 + 6);
+        assert!(address.matches(&policy));
+        assert!(is_valid_address(&encoded));
+
+        // Key and policy addresses are never confused with each other.
+        assert!(encoded.parse::<Address>().is_err());
+        let key_address = Address::from_public_key(&policy.keys()[0]).to_string();
+        assert_eq!(key_address.parse::<PolicyAddress>(), Err(IdentityError::InvalidEncoding("Address is not a policy address")));
+    }
+}
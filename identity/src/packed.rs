@@ -0,0 +1,448 @@
+//! Implements the packed ("bitsliced") representation of m-vectors used on the hot path
+//! of key generation, signing and verification.
+//!
+//! The public map consists of m matrices P_0, ..., P_{m-1}, and the MAYO encoding stores
+//! them entry by entry: the m-vector of entry (r, c) holds P_0[r][c], ..., P_{m-1}[r][c].
+//! As in the MAYO reference implementation, an m-vector is kept as `ceil(m/16)` u64
+//! limbs with element a in nibble a % 16 of limb a / 16, so that adding two m-vectors is
+//! a XOR per limb and multiplying one by a GF(16) scalar takes a handful of shifts and
+//! masks per limb. All m equations are then processed together, instead of one
+//! `GFMatrix` per equation.
+//!
+//! Scalar multiplication selects its terms with masks, never with branches, so the
+//! kernels stay constant-time in the scalars, which are secret during signing.
+
+use std::hint::black_box;
+use zeroize::Zeroize;
+use crate::error::IdentityError;
+use crate::types::{GFElement, GFMatrix, GFVector};
+
+/// Number of GF(16) elements held by one u64 limb.
+const NIBBLES_PER_LIMB: usize = 16;
+
+/// Number of u64 limbs holding one m-vector.
+pub fn m_vec_limbs(m: usize) -> usize {
+    m.div_ceil(NIBBLES_PER_LIMB)
+}
+
+/// Returns an all-ones u64 if `bit` (0 or 1) is set and zero otherwise, without
+/// branching.
+#[inline]
+fn limb_mask(bit: u8) -> u64 {
+    black_box(0u64.wrapping_sub((bit & 1) as u64))
+}
+
+/// Multiplies 16 packed GF(16) elements at once by x, reducing x^4 to x + 1.
+#[inline]
+fn gf16v_mul_x(a: u64) -> u64 {
+    const MSB: u64 = 0x8888_8888_8888_8888;
+    let msb = a & MSB;
+    // Multiplying the isolated bits by 3 never carries into the next nibble.
+    ((a ^ msb) << 1) ^ ((msb >> 3) * 3)
+}
+
+/// Multiplies each of the 16 GF(16) elements packed in `a` by `b`, in constant time.
+pub fn gf16v_mul_u64(a: u64, b: GFElement) -> u64 {
+    let mut a = a;
+    let mut r = a & limb_mask(b.0);
+    for i in 1..4 {
+        a = gf16v_mul_x(a);
+        r ^= a & limb_mask(b.0 >> i);
+    }
+    r
+}
+
+/// acc += a, for packed m-vectors.
+#[inline]
+pub fn m_vec_add(acc: &mut [u64], a: &[u64]) {
+    for (acc_l, a_l) in acc.iter_mut().zip(a) {
+        *acc_l ^= a_l;
+    }
+}
+
+/// acc += b * a, for packed m-vectors and a scalar b, in constant time.
+#[inline]
+pub fn m_vec_mul_add(acc: &mut [u64], a: &[u64], b: GFElement) {
+    let masks = [limb_mask(b.0), limb_mask(b.0 >> 1), limb_mask(b.0 >> 2), limb_mask(b.0 >> 3)];
+    for (acc_l, &a_l) in acc.iter_mut().zip(a) {
+        let a1 = gf16v_mul_x(a_l);
+        let a2 = gf16v_mul_x(a1);
+        let a3 = gf16v_mul_x(a2);
+        *acc_l ^= (a_l & masks[0]) ^ (a1 & masks[1]) ^ (a2 & masks[2]) ^ (a3 & masks[3]);
+    }
+}
+
+/// Multiplies a packed m-vector by E, i.e. by z in GF(16)[z]/(f(z)) with
+/// f(z) = z^m + f_tail(z): every element moves up one position and the one pushed
+/// out of position m-1 is folded back in as a multiple of f_tail.
+/// This is the packed form of `e_matrix_pow_vec_mul` with power 1.
+pub fn m_vec_mul_z(v: &mut [u64], m: usize, f_tail: &[u8]) {
+    let top_limb = (m - 1) / NIBBLES_PER_LIMB;
+    let top_shift = 4 * ((m - 1) % NIBBLES_PER_LIMB);
+    let overflow = GFElement(((v[top_limb] >> top_shift) & 0x0F) as u8);
+    v[top_limb] &= !(0x0F << top_shift);
+
+    for l in (1..v.len()).rev() {
+        v[l] = (v[l] << 4) | (v[l - 1] >> 60);
+    }
+    v[0] <<= 4;
+
+    let tail = f_tail.iter().enumerate().fold(0u64, |acc, (i, &t)| acc | ((t as u64 & 0x0F) << (4 * i)));
+    v[0] ^= gf16v_mul_u64(tail, overflow);
+}
+
+/// Packs m GF(16) elements into limbs.
+pub fn pack_m_vector(elements: &GFVector) -> Vec<u64> {
+    let mut limbs = vec![0u64; m_vec_limbs(elements.len())];
+    for (a, e) in elements.iter().enumerate() {
+        limbs[a / NIBBLES_PER_LIMB] |= ((e.0 & 0x0F) as u64) << (4 * (a % NIBBLES_PER_LIMB));
+    }
+    limbs
+}
+
+/// Unpacks the first m GF(16) elements of a packed m-vector.
+pub fn unpack_m_vector(limbs: &[u64], m: usize) -> GFVector {
+    (0..m)
+        .map(|a| GFElement(((limbs[a / NIBBLES_PER_LIMB] >> (4 * (a % NIBBLES_PER_LIMB))) & 0x0F) as u8))
+        .collect()
+}
+
+/// The pairs (i, j), i <= j, of the k whipped vectors in the order MAYO assigns the
+/// powers E^0, E^1, ... to them: i ascending and, for each i, j from k-1 down to i.
+pub fn whipped_pairs(k: usize) -> Vec<(usize, usize)> {
+    (0..k).flat_map(|i| (i..k).rev().map(move |j| (i, j))).collect()
+}
+
+/// A `rows x cols` matrix whose entries are packed m-vectors, i.e. m matrices of the
+/// same shape stored entry by entry (row-major).
+#[derive(Debug, Clone, PartialEq, Eq, Zeroize)]
+pub struct PackedMatrix {
+    rows: usize,
+    cols: usize,
+    m: usize,
+    limbs: usize,
+    data: Vec<u64>,
+}
+
+impl PackedMatrix {
+    /// Creates a matrix of zero m-vectors.
+    pub fn zero(rows: usize, cols: usize, m: usize) -> Self {
+        let limbs = m_vec_limbs(m);
+        PackedMatrix { rows, cols, m, limbs, data: vec![0u64; rows * cols * limbs] }
+    }
+
+    /// Returns the number of rows in the matrix.
+    pub fn num_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns in the matrix.
+    pub fn num_cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the number m of elements in each entry.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the packed m-vector at (r, c). Panics if out of bounds.
+    pub fn entry(&self, r: usize, c: usize) -> &[u64] {
+        assert!(r < self.rows && c < self.cols, "PackedMatrix index out of bounds");
+        let start = (r * self.cols + c) * self.limbs;
+        &self.data[start..start + self.limbs]
+    }
+
+    /// Returns the packed m-vector at (r, c) for writing. Panics if out of bounds.
+    pub fn entry_mut(&mut self, r: usize, c: usize) -> &mut [u64] {
+        assert!(r < self.rows && c < self.cols, "PackedMatrix index out of bounds");
+        let start = (r * self.cols + c) * self.limbs;
+        &mut self.data[start..start + self.limbs]
+    }
+
+    /// Returns the `rows x cols` matrix made of element `a` of every entry, i.e. the
+    /// a-th of the m matrices.
+    pub fn matrix(&self, a: usize) -> GFMatrix {
+        let data = (0..self.rows * self.cols)
+            .map(|i| {
+                let limb = self.data[i * self.limbs + a / NIBBLES_PER_LIMB];
+                GFElement(((limb >> (4 * (a % NIBBLES_PER_LIMB))) & 0x0F) as u8)
+            })
+            .collect();
+        GFMatrix::new_with_data(self.rows, self.cols, data)
+    }
+
+    fn unpack_entry(bytes: &[u8], m: usize, limbs: &mut [u64]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            // Odd m: the last high nibble is padding and must not become element m.
+            let byte = if 2 * i + 1 == m { byte & 0x0F } else { *byte };
+            limbs[i / 8] |= (byte as u64) << (8 * (i % 8));
+        }
+    }
+
+    fn pack_entry(limbs: &[u64], m: usize, out: &mut Vec<u8>) {
+        for i in 0..m.div_ceil(2) {
+            let mut byte = (limbs[i / 8] >> (8 * (i % 8))) as u8;
+            if 2 * i + 1 == m {
+                byte &= 0x0F; // Odd m: the last high nibble is padding
+            }
+            out.push(byte);
+        }
+    }
+
+    /// Decodes a full `rows x cols` matrix of m-vectors, each encoded in `ceil(m/2)` bytes.
+    pub fn decode(bytes: &[u8], rows: usize, cols: usize, m: usize) -> Result<Self, IdentityError> {
+        let m_vec_bytes = m.div_ceil(2);
+        if bytes.len() != rows * cols * m_vec_bytes {
+            return Err(IdentityError::InvalidLength("Byte length does not correspond to the expected number of m-vectors"));
+        }
+        let mut matrix = Self::zero(rows, cols, m);
+        for (i, chunk) in bytes.chunks_exact(m_vec_bytes).enumerate() {
+            Self::unpack_entry(chunk, m, matrix.entry_mut(i / cols, i % cols));
+        }
+        Ok(matrix)
+    }
+
+    /// Decodes an upper triangular `size x size` matrix of m-vectors, stored for each
+    /// entry (r, c) with r <= c in row-major order. Entries below the diagonal are zero.
+    pub fn decode_upper_triangular(bytes: &[u8], size: usize, m: usize) -> Result<Self, IdentityError> {
+        let m_vec_bytes = m.div_ceil(2);
+        if bytes.len() != size * (size + 1) / 2 * m_vec_bytes {
+            return Err(IdentityError::InvalidLength("Byte length does not correspond to the expected number of m-vectors"));
+        }
+        let mut matrix = Self::zero(size, size, m);
+        let mut chunks = bytes.chunks_exact(m_vec_bytes);
+        for r in 0..size {
+            for c in r..size {
+                Self::unpack_entry(chunks.next().unwrap_or_default(), m, matrix.entry_mut(r, c));
+            }
+        }
+        Ok(matrix)
+    }
+
+    /// Encodes every entry in row-major order; the inverse of `decode`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.rows * self.cols * self.m.div_ceil(2));
+        for entry in self.data.chunks_exact(self.limbs.max(1)) {
+            Self::pack_entry(entry, self.m, &mut out);
+        }
+        out
+    }
+
+    /// Encodes Upper(self) of a square matrix: the diagonal entries and, for r < c, the
+    /// sum of entries (r, c) and (c, r), in row-major order.
+    pub fn encode_upper(&self) -> Result<Vec<u8>, IdentityError> {
+        if self.rows != self.cols {
+            return Err(IdentityError::DimensionMismatch("Upper requires a square matrix"));
+        }
+        let mut out = Vec::with_capacity(self.rows * (self.rows + 1) / 2 * self.m.div_ceil(2));
+        let mut sum = vec![0u64; self.limbs];
+        for r in 0..self.rows {
+            for c in r..self.cols {
+                sum.copy_from_slice(self.entry(r, c));
+                if r != c {
+                    m_vec_add(&mut sum, self.entry(c, r));
+                }
+                Self::pack_entry(&sum, self.m, &mut out);
+            }
+        }
+        Ok(out)
+    }
+
+    /// self += other.
+    pub fn add_assign(&mut self, other: &PackedMatrix) -> Result<(), IdentityError> {
+        if self.rows != other.rows || self.cols != other.cols || self.m != other.m {
+            return Err(IdentityError::DimensionMismatch("Packed matrix dimensions must match for addition"));
+        }
+        m_vec_add(&mut self.data, &other.data);
+        Ok(())
+    }
+
+    /// Returns self + self^T for a square matrix.
+    pub fn symmetrize(&self) -> Result<PackedMatrix, IdentityError> {
+        if self.rows != self.cols {
+            return Err(IdentityError::DimensionMismatch("Symmetrize requires a square matrix"));
+        }
+        let mut result = self.clone();
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                let (start, transposed) = ((r * self.cols + c) * self.limbs, self.entry(c, r));
+                m_vec_add(&mut result.data[start..start + self.limbs], transposed);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Computes A * B for a matrix A of m-vectors and a matrix B of scalars, i.e. A_a * B
+/// for each of the m matrices A_a at once.
+pub fn packed_matrix_mul(a: &PackedMatrix, b: &GFMatrix) -> Result<PackedMatrix, IdentityError> {
+    if a.cols != b.num_rows() {
+        return Err(IdentityError::DimensionMismatch("Matrix dimensions incompatible for multiplication"));
+    }
+    let mut result = PackedMatrix::zero(a.rows, b.num_cols(), a.m);
+    for r in 0..a.rows {
+        for k in 0..a.cols {
+            let a_rk = a.entry(r, k);
+            for c in 0..b.num_cols() {
+                m_vec_mul_add(result.entry_mut(r, c), a_rk, b.data[k * b.num_cols() + c]);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Computes B * A for a matrix B of scalars and a matrix A of m-vectors, i.e. B * A_a
+/// for each of the m matrices A_a at once.
+pub fn matrix_packed_mul(b: &GFMatrix, a: &PackedMatrix) -> Result<PackedMatrix, IdentityError> {
+    if b.num_cols() != a.rows {
+        return Err(IdentityError::DimensionMismatch("Matrix dimensions incompatible for multiplication"));
+    }
+    let mut result = PackedMatrix::zero(b.num_rows(), a.cols, a.m);
+    for r in 0..b.num_rows() {
+        for k in 0..b.num_cols() {
+            let b_rk = b.data[r * b.num_cols() + k];
+            for c in 0..a.cols {
+                m_vec_mul_add(result.entry_mut(r, c), a.entry(k, c), b_rk);
+            }
+        }
+    }
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gf::gf16_mul;
+    use crate::matrix::{e_matrix_pow_vec_mul, matrix_add, matrix_mul, matrix_transpose};
+    use crate::codec::{decode_p1_matrices, decode_p2_matrices, encode_p3_matrices, encode_l_matrices};
+    use crate::params::MayoParams;
+    use crate::rng::{CtrDrbg, RandomSource};
+
+    fn random_elements(rng: &mut CtrDrbg, len: usize) -> GFVector {
+        let mut bytes = vec![0u8; len];
+        rng.fill_bytes(&mut bytes).unwrap();
+        bytes.iter().map(|b| GFElement(b & 0x0F)).collect()
+    }
+
+    fn random_matrix(rng: &mut CtrDrbg, rows: usize, cols: usize) -> GFMatrix {
+        GFMatrix::new_with_data(rows, cols, random_elements(rng, rows * cols))
+    }
+
+    fn random_packed(rng: &mut CtrDrbg, rows: usize, cols: usize, m: usize) -> PackedMatrix {
+        let mut bytes = vec![0u8; rows * cols * m.div_ceil(2)];
+        rng.fill_bytes(&mut bytes).unwrap();
+        PackedMatrix::decode(&bytes, rows, cols, m).unwrap()
+    }
+
+    #[test]
+    fn test_gf16v_mul_u64_matches_gf16_mul() {
+        let a: GFVector = (0..16).map(GFElement).collect();
+        let packed = pack_m_vector(&a)[0];
+        for b in 0..16 {
+            let product = unpack_m_vector(&[gf16v_mul_u64(packed, GFElement(b))], 16);
+            let expected: GFVector = a.iter().map(|a_i| gf16_mul(*a_i, GFElement(b))).collect();
+            assert_eq!(product, expected, "b = {}", b);
+        }
+    }
+
+    #[test]
+    fn test_m_vec_mul_add_and_pack_roundtrip() {
+        let mut rng = CtrDrbg::new(&[1u8; 48]);
+        let a = random_elements(&mut rng, 78);
+        let acc = random_elements(&mut rng, 78);
+        assert_eq!(unpack_m_vector(&pack_m_vector(&a), 78), a);
+
+        let mut packed_acc = pack_m_vector(&acc);
+        m_vec_mul_add(&mut packed_acc, &pack_m_vector(&a), GFElement(0xB));
+        let expected: GFVector = acc.iter().zip(&a).map(|(c, a_i)| GFElement(c.0 ^ gf16_mul(*a_i, GFElement(0xB)).0)).collect();
+        assert_eq!(unpack_m_vector(&packed_acc, 78), expected);
+    }
+
+    #[test]
+    fn test_m_vec_mul_z_matches_e_matrix() {
+        let mut rng = CtrDrbg::new(&[2u8; 48]);
+        for params in MayoParams::all() {
+            let p = params.variant();
+            let u = random_elements(&mut rng, p.m);
+            let mut packed = pack_m_vector(&u);
+            for power in 1..=20 {
+                m_vec_mul_z(&mut packed, p.m, &p.f_tail);
+                assert_eq!(unpack_m_vector(&packed, p.m), e_matrix_pow_vec_mul(&u, power, &p.f_tail));
+            }
+        }
+    }
+
+    #[test]
+    fn test_whipped_pairs_order() {
+        assert_eq!(whipped_pairs(3), vec![(0, 2), (0, 1), (0, 0), (1, 2), (1, 1), (2, 2)]);
+        assert_eq!(whipped_pairs(10).len(), 55);
+    }
+
+    #[test]
+    fn test_decode_matches_codec() {
+        let params = MayoParams::mayo1();
+        let p = params.variant();
+        let mut rng = CtrDrbg::new(&[3u8; 48]);
+        let mut p1_bytes = vec![0u8; p.p1_bytes];
+        let mut p2_bytes = vec![0u8; p.p2_bytes];
+        rng.fill_bytes(&mut p1_bytes).unwrap();
+        rng.fill_bytes(&mut p2_bytes).unwrap();
+
+        let p1 = PackedMatrix::decode_upper_triangular(&p1_bytes, p.v(), p.m).unwrap();
+        let p2 = PackedMatrix::decode(&p2_bytes, p.v(), p.o, p.m).unwrap();
+        let p1_matrices = decode_p1_matrices(&p1_bytes, p).unwrap();
+        let p2_matrices = decode_p2_matrices(&p2_bytes, p).unwrap();
+        for a in 0..p.m {
+            assert_eq!(p1.matrix(a), p1_matrices[a]);
+            assert_eq!(p2.matrix(a), p2_matrices[a]);
+        }
+
+        assert_eq!(p2.encode(), p2_bytes);
+        assert_eq!(p2.encode(), encode_l_matrices(&p2_matrices, p).unwrap());
+        assert!(PackedMatrix::decode(&p2_bytes[1..], p.v(), p.o, p.m).is_err());
+        assert!(PackedMatrix::decode_upper_triangular(&p1_bytes[1..], p.v(), p.m).is_err());
+    }
+
+    #[test]
+    fn test_encode_upper_matches_codec() {
+        let mut rng = CtrDrbg::new(&[4u8; 48]);
+        let p = MayoParams::mayo2();
+        let p = p.variant();
+        let square = random_packed(&mut rng, p.o, p.o, p.m);
+        let upper_matrices: Vec<GFMatrix> = (0..p.m)
+            .map(|a| crate::matrix::matrix_upper(&square.matrix(a)).unwrap())
+            .collect();
+        assert_eq!(square.encode_upper().unwrap(), encode_p3_matrices(&upper_matrices, p).unwrap());
+    }
+
+    #[test]
+    fn test_packed_products_match_matrix_mul() {
+        let mut rng = CtrDrbg::new(&[5u8; 48]);
+        let m = 78;
+        let a = random_packed(&mut rng, 7, 5, m);
+        let b = random_matrix(&mut rng, 5, 3);
+        let c = random_matrix(&mut rng, 4, 7);
+
+        let ab = packed_matrix_mul(&a, &b).unwrap();
+        let ca = matrix_packed_mul(&c, &a).unwrap();
+        let sym = a.symmetrize();
+        assert!(sym.is_err());
+        let square = random_packed(&mut rng, 5, 5, m);
+        let sym = square.symmetrize().unwrap();
+        for i in 0..m {
+            assert_eq!(ab.matrix(i), matrix_mul(&a.matrix(i), &b).unwrap());
+            assert_eq!(ca.matrix(i), matrix_mul(&c, &a.matrix(i)).unwrap());
+            let s = square.matrix(i);
+            assert_eq!(sym.matrix(i), matrix_add(&s, &matrix_transpose(&s)).unwrap());
+        }
+
+        let mut sum = ab.clone();
+        sum.add_assign(&ab).unwrap();
+        assert_eq!(sum, PackedMatrix::zero(7, 3, m));
+        assert!(sum.add_assign(&ca).is_err());
+        assert!(packed_matrix_mul(&a, &c).is_err());
+        assert!(matrix_packed_mul(&b, &a).is_err());
+    }
+}
@@ -1,20 +1,25 @@
 //! Defines parameters for different MAYO security levels.
 
+use crate::error::IdentityError;
+
 /// Irreducible polynomial for GF(16): x^4 + x + 1
 /// (coefficients in little-endian for degree, e.g., 0b...c3 c2 c1 c0)
 /// x^4 + x + 1 is 1*x^4 + 0*x^3 + 0*x^2 + 1*x^1 + 1*x^0 -> 10011
 pub const F_POLY_U8: u8 = 0b0001_0011; // As u8, used in some contexts if operations are byte-wise
 pub const F_POLY_U16: u16 = 0x13;     // As u16, matching the subtask description (0x13 = 19 = 0b10011)
 
-/// Holds the specific parameters for a MAYO variant (e.g., MAYO1, MAYO2).
+/// Number of coefficients in the tail of the reduction polynomial f(z).
+pub const F_TAIL_LEN: usize = 4;
+
+/// Holds the specific parameters for a MAYO variant (e.g., MAYO1, MAYO2, MAYO3, MAYO5).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MayoVariantParams {
     // Core MQ parameters
     pub n: usize, // Number of variables (elements in a solution vector s)
     pub m: usize, // Number of equations in P (elements in t)
-    pub o: usize, // Number of vinegar variables
-    pub k: usize, // Number of solutions to find / oil variables used in G
-    
+    pub o: usize, // Dimension of the oil space
+    pub k: usize, // Whipping parameter: number of vectors s_i combined into one signature
+
     // Byte lengths for seeds, salts, digests
     pub sk_seed_bytes: usize,   // Security parameter lambda
     pub pk_seed_bytes: usize,   // For PK seed (Note: AES key size for P1/P2 derivation)
@@ -22,17 +27,46 @@ pub struct MayoVariantParams {
     pub digest_bytes: usize,    // For message digest (e.g., SHAKE256 output length)
 
     // Byte lengths for various components derived from seeds or used in the scheme
-    pub o_bytes: usize,         // Serialized oil variables component (e.g., G or its seed)
-    pub p1_bytes: usize,        // Serialized P1 matrix component (derived via AES-CTR from pk_seed)
-    pub p2_bytes: usize,        // Serialized P2 matrix component (derived via AES-CTR from pk_seed)
-    pub p3_bytes: usize,        // Serialized P3 matrix component (derived via SHAKE from pk_seed)
-    
-    // TODO: Add any other derived byte lengths if useful, e.g., bytes for csk, cpk, esk, epk, sig.
-    // These would be calculated based on n, m, o, k, and q (field_elements_to_bytes).
-    // For example:
-    // csk_bytes: sk_seed_bytes
-    // cpk_bytes: pk_seed_bytes + bytes_for_gf16_elements(m * (n-o)*(n-o+1)/2) for P3, or its hash.
-    // sig_bytes: salt_bytes + bytes_for_gf16_elements(n) for solution s.
+    pub o_bytes: usize,         // Serialized (n-o) x o oil matrix O
+    pub p1_bytes: usize,        // Serialized P1 matrices (derived via AES-CTR from pk_seed)
+    pub p2_bytes: usize,        // Serialized P2 matrices (derived via AES-CTR from pk_seed)
+    pub p3_bytes: usize,        // Serialized P3 matrices (part of the compact public key)
+
+    /// Coefficients of z^0..z^3 in f(z) = z^m + f_tail(z), the polynomial defining
+    /// the E-matrix used to combine the k whipped evaluations.
+    pub f_tail: [u8; F_TAIL_LEN],
+}
+
+impl MayoVariantParams {
+    /// Number of vinegar variables (n - o).
+    pub fn v(&self) -> usize { self.n - self.o }
+
+    /// Bytes needed to store one vinegar vector of n - o elements.
+    pub fn v_bytes(&self) -> usize { MayoParams::bytes_for_gf16_elements(self.v()) }
+
+    /// Bytes needed to store one m-vector (one entry of all m matrices P_i).
+    pub fn m_vec_bytes(&self) -> usize { MayoParams::bytes_for_gf16_elements(self.m) }
+
+    /// Serialized L matrices; each L_i has the same (n-o) x o shape as P2_i.
+    pub fn l_bytes(&self) -> usize { self.p2_bytes }
+
+    /// Compact secret key length (seed_sk).
+    pub fn csk_bytes(&self) -> usize { self.sk_seed_bytes }
+
+    /// Compact public key length (seed_pk || P3 bytes).
+    pub fn cpk_bytes(&self) -> usize { self.pk_seed_bytes + self.p3_bytes }
+
+    /// Expanded secret key length (seed_sk || O bytes || P1 bytes || L bytes).
+    pub fn esk_bytes(&self) -> usize { self.sk_seed_bytes + self.o_bytes + self.p1_bytes + self.l_bytes() }
+
+    /// Expanded public key length (P1 bytes || P2 bytes || P3 bytes).
+    pub fn epk_bytes(&self) -> usize { self.p1_bytes + self.p2_bytes + self.p3_bytes }
+
+    /// Encoded solution length: k vectors of n elements each.
+    pub fn s_bytes(&self) -> usize { MayoParams::bytes_for_gf16_elements(self.n * self.k) }
+
+    /// Signature length (encoded s || salt).
+    pub fn sig_bytes(&self) -> usize { self.s_bytes() + self.salt_bytes }
 }
 
 /// Enum to select a specific set of MAYO parameters.
@@ -40,7 +74,8 @@ pub struct MayoVariantParams {
 pub enum MayoParams {
     MAYO1(MayoVariantParams),
     MAYO2(MayoVariantParams),
-    // Potentially MAYO3, MAYO5 in the future
+    MAYO3(MayoVariantParams),
+    MAYO5(MayoVariantParams),
 }
 
 impl MayoParams {
@@ -48,52 +83,119 @@ impl MayoParams {
     pub const Q: usize = 16;
     // F_POLY is defined as a top-level constant in this file (F_POLY_U16 or F_POLY_U8).
 
-    /// Parameters for MAYO1 (NIST Level 1 equivalent).
+    /// Parameters for MAYO1 (NIST Level 1).
     pub fn mayo1() -> Self {
         MayoParams::MAYO1(MayoVariantParams {
-            n: 66, m: 64, o: 8, k: 9,
-            sk_seed_bytes: 24,  // Corresponds to NIST's rho parameter for MAYO1
+            n: 86, m: 78, o: 8, k: 10,
+            sk_seed_bytes: 24,
             pk_seed_bytes: 16,  // AES-128 key size
-            salt_bytes: 24,     // Corresponds to NIST's salt parameter for MAYO1
-            digest_bytes: 32,   // For a 256-bit digest (e.g. SHAKE256/256)
-            
-            o_bytes: 232,       // From MAYO spec, Table 1 (G_bytes for MAYO1_PK)
-            p1_bytes: 960,      // From MAYO spec, Table 1
-            p2_bytes: 14848,    // From MAYO spec, Table 1
-            p3_bytes: 160,      // From MAYO spec, Table 1 (P3_bytes for MAYO1_PK)
+            salt_bytes: 24,
+            digest_bytes: 32,
+
+            o_bytes: 312,       // (n-o)*o nibbles
+            p1_bytes: 120159,   // m * (n-o)(n-o+1)/2 nibbles
+            p2_bytes: 24336,    // m * (n-o)*o nibbles
+            p3_bytes: 1404,     // m * o(o+1)/2 nibbles
+            f_tail: [8, 1, 1, 0], // f(z) = z^78 + z^2 + z + x^3
         })
     }
 
-    /// Parameters for MAYO2 (NIST Level 3 equivalent, if mapping directly).
-    /// Note: The parameters provided (n=78, m=64, o=18, k=4) align with "mayo_2" from some reference implementations.
-    /// These values are also consistent with NIST Level 3 parameters for MAYO.
+    /// Parameters for MAYO2 (NIST Level 1, smaller signatures and larger keys than MAYO1).
     pub fn mayo2() -> Self {
         MayoParams::MAYO2(MayoVariantParams {
-            n: 78, m: 64, o: 18, k: 4,
-            sk_seed_bytes: 24,  // Corresponds to NIST's rho parameter for MAYO2 (assuming it's MAYO-L3 mapping)
+            n: 81, m: 64, o: 17, k: 4,
+            sk_seed_bytes: 24,
+            pk_seed_bytes: 16,  // AES-128 key size
+            salt_bytes: 24,
+            digest_bytes: 32,
+
+            o_bytes: 544,
+            p1_bytes: 66560,
+            p2_bytes: 34816,
+            p3_bytes: 4896,
+            f_tail: [8, 0, 2, 8], // f(z) = z^64 + x^3*z^3 + x*z^2 + x^3
+        })
+    }
+
+    /// Parameters for MAYO3 (NIST Level 3).
+    pub fn mayo3() -> Self {
+        MayoParams::MAYO3(MayoVariantParams {
+            n: 118, m: 108, o: 10, k: 11,
+            sk_seed_bytes: 32,
+            pk_seed_bytes: 16,  // AES-128 key size
+            salt_bytes: 32,
+            digest_bytes: 48,
+
+            o_bytes: 540,
+            p1_bytes: 317844,
+            p2_bytes: 58320,
+            p3_bytes: 2970,
+            f_tail: [8, 0, 1, 7], // f(z) = z^108 + (x^2+x+1)*z^3 + z^2 + x^3
+        })
+    }
+
+    /// Parameters for MAYO5 (NIST Level 5).
+    pub fn mayo5() -> Self {
+        MayoParams::MAYO5(MayoVariantParams {
+            n: 154, m: 142, o: 12, k: 12,
+            sk_seed_bytes: 40,
             pk_seed_bytes: 16,  // AES-128 key size
-            salt_bytes: 24,     // Corresponds to NIST's salt parameter for MAYO2
-            digest_bytes: 32,   // For a 256-bit digest
-            
-            o_bytes: 540,       // From MAYO spec, Table 1 (G_bytes for MAYO2_PK)
-            p1_bytes: 992,      // From MAYO spec, Table 1
-            p2_bytes: 34560,    // From MAYO spec, Table 1
-            p3_bytes: 320,      // From MAYO spec, Table 1 (P3_bytes for MAYO2_PK)
+            salt_bytes: 40,
+            digest_bytes: 64,
+
+            o_bytes: 852,
+            p1_bytes: 720863,
+            p2_bytes: 120984,
+            p3_bytes: 5538,
+            f_tail: [4, 0, 8, 1], // f(z) = z^142 + z^3 + x^3*z^2 + x^2
         })
     }
 
+    /// All supported variants, in increasing security level.
+    pub fn all() -> [MayoParams; 4] {
+        [MayoParams::mayo1(), MayoParams::mayo2(), MayoParams::mayo3(), MayoParams::mayo5()]
+    }
+
+    /// The lowercase variant name accepted by `get_params_by_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MayoParams::MAYO1(_) => "mayo1",
+            MayoParams::MAYO2(_) => "mayo2",
+            MayoParams::MAYO3(_) => "mayo3",
+            MayoParams::MAYO5(_) => "mayo5",
+        }
+    }
+
+    /// Stable one-byte identifier of the variant (its NIST security level), used in
+    /// serialized envelopes.
+    pub fn id(&self) -> u8 {
+        match self {
+            MayoParams::MAYO1(_) => 1,
+            MayoParams::MAYO2(_) => 2,
+            MayoParams::MAYO3(_) => 3,
+            MayoParams::MAYO5(_) => 5,
+        }
+    }
+
+    /// Looks up a variant by the identifier returned from `id`.
+    pub fn from_id(id: u8) -> Option<MayoParams> {
+        MayoParams::all().into_iter().find(|p| p.id() == id)
+    }
+
     /// Accessor method to get the underlying `MayoVariantParams`.
     pub fn variant(&self) -> &MayoVariantParams {
         match self {
             MayoParams::MAYO1(p) => p,
             MayoParams::MAYO2(p) => p,
+            MayoParams::MAYO3(p) => p,
+            MayoParams::MAYO5(p) => p,
         }
     }
 
     /// Helper method to calculate bytes needed to store a given number of GF(16) elements.
     /// Each GF(16) element is 4 bits (a nibble).
     pub fn bytes_for_gf16_elements(num_elements: usize) -> usize {
-        (num_elements + 1) / 2
+        num_elements.div_ceil(2)
     }
 
     // Convenience accessors delegated to the variant
@@ -109,13 +211,20 @@ impl MayoParams {
     pub fn p1_bytes(&self) -> usize { self.variant().p1_bytes }
     pub fn p2_bytes(&self) -> usize { self.variant().p2_bytes }
     pub fn p3_bytes(&self) -> usize { self.variant().p3_bytes }
+    pub fn l_bytes(&self) -> usize { self.variant().l_bytes() }
+    pub fn csk_bytes(&self) -> usize { self.variant().csk_bytes() }
+    pub fn cpk_bytes(&self) -> usize { self.variant().cpk_bytes() }
+    pub fn esk_bytes(&self) -> usize { self.variant().esk_bytes() }
+    pub fn epk_bytes(&self) -> usize { self.variant().epk_bytes() }
+    pub fn sig_bytes(&self) -> usize { self.variant().sig_bytes() }
 
-    pub fn get_params_by_name(name: &str) -> Result<MayoParams, String> {
+    pub fn get_params_by_name(name: &str) -> Result<MayoParams, IdentityError> {
         match name.to_lowercase().as_str() {
             "mayo1" => Ok(MayoParams::mayo1()),
             "mayo2" => Ok(MayoParams::mayo2()),
-            // Add other variants if they exist in the future
-            _ => Err(format!("Unknown MAYO variant name: {}", name)),
+            "mayo3" => Ok(MayoParams::mayo3()),
+            "mayo5" => Ok(MayoParams::mayo5()),
+            _ => Err(IdentityError::UnknownVariant),
         }
     }
 }
@@ -127,3 +236,44 @@ impl MayoParams {
 // let p1_bytes_val = specific_variant_params.p1_bytes;
 // or directly:
 // let p1_bytes_val_direct = params_mayo1.p1_bytes();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_lengths_match_dimensions() {
+        for params in MayoParams::all() {
+            let p = params.variant();
+            let (v, o, m) = (p.v(), p.o, p.m);
+            assert_eq!(p.o_bytes, MayoParams::bytes_for_gf16_elements(v * o));
+            assert_eq!(p.p1_bytes, p.m_vec_bytes() * v * (v + 1) / 2);
+            assert_eq!(p.p2_bytes, p.m_vec_bytes() * v * o);
+            assert_eq!(p.p3_bytes, p.m_vec_bytes() * o * (o + 1) / 2);
+            assert!(p.k * o >= m, "k*o must be at least m for the system to be solvable");
+        }
+    }
+
+    #[test]
+    fn test_spec_key_and_signature_sizes() {
+        // (cpk, csk, sig) sizes from the MAYO specification.
+        let expected = [(1420, 24, 454), (4912, 24, 186), (2986, 32, 681), (5554, 40, 964)];
+        for (params, (cpk, csk, sig)) in MayoParams::all().iter().zip(expected) {
+            assert_eq!(params.cpk_bytes(), cpk);
+            assert_eq!(params.csk_bytes(), csk);
+            assert_eq!(params.sig_bytes(), sig);
+        }
+    }
+
+    #[test]
+    fn test_get_params_by_name() {
+        assert_eq!(MayoParams::get_params_by_name("mayo3"), Ok(MayoParams::mayo3()));
+        assert_eq!(MayoParams::get_params_by_name("MAYO5"), Ok(MayoParams::mayo5()));
+        assert_eq!(MayoParams::get_params_by_name("mayo4"), Err(IdentityError::UnknownVariant));
+        for params in MayoParams::all() {
+            assert_eq!(MayoParams::get_params_by_name(params.name()), Ok(params));
+            assert_eq!(MayoParams::from_id(params.id()), Some(params));
+        }
+        assert_eq!(MayoParams::from_id(4), None);
+    }
+}
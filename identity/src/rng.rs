@@ -0,0 +1,144 @@
+//! Randomness sources for key generation and signing: the operating system RNG, and the
+//! NIST AES-256 CTR_DRBG used by the reference implementation to generate the KAT files.
+
+use aes::Aes256;
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use getrandom::getrandom;
+
+use crate::error::IdentityError;
+
+/// Length of the entropy input (seed) of the NIST CTR_DRBG.
+pub const DRBG_SEED_BYTES: usize = 48;
+
+/// A source of random bytes for `compact_key_gen_with_rng` and `sign_message_with_rng`.
+pub trait RandomSource {
+    /// Fills `dest` with random bytes.
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), IdentityError>;
+}
+
+/// Randomness from the operating system, via `getrandom`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), IdentityError> {
+        getrandom(dest).map_err(|_| IdentityError::RngFailure)
+    }
+}
+
+/// The deterministic AES-256 CTR_DRBG (without derivation function) from the NIST PQC
+/// `rng.c`, as used by `PQCgenKAT_sign`.
+///
+/// Seeding it with the `seed` of a KAT entry reproduces the randomness consumed by the
+/// reference `crypto_sign_keypair` and `crypto_sign` calls for that entry.
+#[derive(Clone)]
+pub struct CtrDrbg {
+    key: [u8; 32],
+    v: [u8; 16],
+}
+
+impl CtrDrbg {
+    /// Instantiates the DRBG from a 48-byte entropy input, with no personalization string.
+    pub fn new(entropy_input: &[u8; DRBG_SEED_BYTES]) -> Self {
+        let mut drbg = CtrDrbg { key: [0u8; 32], v: [0u8; 16] };
+        drbg.update(Some(entropy_input));
+        drbg
+    }
+
+    /// Increments V as a 128-bit big-endian counter.
+    fn increment_v(&mut self) {
+        for byte in self.v.iter_mut().rev() {
+            if *byte == 0xFF {
+                *byte = 0x00;
+            } else {
+                *byte += 1;
+                break;
+            }
+        }
+    }
+
+    /// Encrypts the current V under the current key.
+    fn encrypt_v(&self) -> [u8; 16] {
+        let cipher = Aes256::new(GenericArray::from_slice(&self.key));
+        let mut block = GenericArray::clone_from_slice(&self.v);
+        cipher.encrypt_block(&mut block);
+        block.into()
+    }
+
+    /// CTR_DRBG_Update: derives a fresh (key, V) from 48 bytes of keystream, optionally
+    /// XORed with `provided_data`.
+    fn update(&mut self, provided_data: Option<&[u8; DRBG_SEED_BYTES]>) {
+        let mut temp = [0u8; DRBG_SEED_BYTES];
+        for chunk in temp.chunks_exact_mut(16) {
+            self.increment_v();
+            chunk.copy_from_slice(&self.encrypt_v());
+        }
+        if let Some(data) = provided_data {
+            for (t, d) in temp.iter_mut().zip(data) {
+                *t ^= d;
+            }
+        }
+        self.key.copy_from_slice(&temp[..32]);
+        self.v.copy_from_slice(&temp[32..]);
+    }
+}
+
+impl RandomSource for CtrDrbg {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), IdentityError> {
+        for chunk in dest.chunks_mut(16) {
+            self.increment_v();
+            let block = self.encrypt_v();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.update(None);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_ctr_drbg_matches_pqcgenkat_seeds() {
+        // PQCgenKAT_sign seeds the DRBG with 0, 1, ..., 47 and draws the 48-byte seed and
+        // the message of each KAT entry from it; these are count 0 and the seed of count 1.
+        let mut entropy_input = [0u8; DRBG_SEED_BYTES];
+        for (i, byte) in entropy_input.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut drbg = CtrDrbg::new(&entropy_input);
+
+        let mut seed = [0u8; DRBG_SEED_BYTES];
+        drbg.fill_bytes(&mut seed).unwrap();
+        assert_eq!(seed.to_vec(), hex("061550234D158C5EC95595FE04EF7A25767F2E24CC2BC479D09D86DC9ABCFDE7056A8C266F9EF97ED08541DBD2E1FFA1"));
+        let mut msg = [0u8; 33];
+        drbg.fill_bytes(&mut msg).unwrap();
+        assert_eq!(msg.to_vec(), hex("D81C4D8D734FCBFBEADE3D3F8A039FAA2A2C9957E835AD55B22E75BF57BB556AC8"));
+        drbg.fill_bytes(&mut seed).unwrap();
+        assert_eq!(seed.to_vec(), hex("64335BF29E5DE62842C941766BA129B0643B5E7121CA26CFC190EC7DC3543830557FDD5C03CF123A456D48EFEA43C868"));
+    }
+
+    #[test]
+    fn test_ctr_drbg_partial_blocks() {
+        // A request is served from whole blocks and then the state is updated, so short
+        // requests do not consume the remainder of the last block.
+        let entropy_input = [7u8; DRBG_SEED_BYTES];
+        let mut a = CtrDrbg::new(&entropy_input);
+        let mut b = CtrDrbg::new(&entropy_input);
+
+        let mut short = [0u8; 5];
+        let mut long = [0u8; 16];
+        a.fill_bytes(&mut short).unwrap();
+        b.fill_bytes(&mut long).unwrap();
+        assert_eq!(short, long[..5]);
+
+        a.fill_bytes(&mut short).unwrap();
+        b.fill_bytes(&mut long).unwrap();
+        assert_eq!(short, long[..5]);
+    }
+}
@@ -1,309 +1,361 @@
 //! Implements MAYO.Sign (Algorithm 8).
 
-use crate::types::{
-    ExpandedSecretKey, Message, Signature, GFVector, Salt, SeedSK, // Removed MessageDigest
-    GFElement // For random vinegar variables
-};
+use crate::error::IdentityError;
+use crate::types::{CompactSecretKey, ExpandedSecretKey, GFMatrix, GFVector, Message, MessageDigest, Signature};
 use crate::params::{MayoParams, MayoVariantParams};
-use crate::hash::{shake256_digest, shake256_derive_target_t, shake256_xof_derive_pk_seed_and_o, shake256_xof_derive_p3};
-use crate::aes_ctr::derive_p2_bytes; // Removed derive_p1_bytes
-use crate::codec::{
-    decode_o_matrix, decode_p1_matrices, decode_p2_matrices, decode_l_matrices,
-    decode_gf_elements, encode_s_vector, decode_p3_matrices
-};
-use crate::types::GFMatrix;
-use crate::matrix::{
-    matrix_sub_vectors_gfvector, matrix_symmetrize, 
-    matrix_vec_mul_transpose_gfvector, vector_dot_product
+use crate::hash::{shake256_digest, shake256_derive_salt, shake256_derive_target_t, shake256_derive_vinegar};
+use crate::codec::{decode_gf_elements, decode_o_matrix, encode_s_vector};
+use crate::matrix::{matrix_sub_vectors_gfvector, matrix_transpose, matrix_vec_mul};
+use crate::packed::{
+    m_vec_add, m_vec_limbs, m_vec_mul_z, matrix_packed_mul, packed_matrix_mul,
+    unpack_m_vector, whipped_pairs, PackedMatrix
 };
-use crate::solver::solve_linear_system;
-use getrandom::getrandom;
+use crate::gf::gf16_add;
+use crate::solver::sample_solution_ct;
+use crate::rng::{OsRandom, RandomSource};
+use crate::keygen::expand_sk;
+use std::fmt;
+use zeroize::Zeroizing;
 
 const MAX_SIGN_RETRIES: usize = 256;
 
-/// Placeholder for the core cryptographic math of MAYO signing.
-/// This function would compute the linearized system matrix A and target vector y'
-/// based on the vinegar variables and secret key components.
+/// Builds the whipped linear system A x = y of MAYO signing for one choice of
+/// the k vinegar vectors.
+///
+/// For every pair i <= j (with j running from k-1 down to i) the evaluation of the
+/// vinegar part, u = v_i^T P1 v_j + v_j^T P1 v_i (or v_i^T P1 v_i when i == j), is
+/// subtracted from y after multiplying by E^l, and E^l M_j (resp. E^l M_i) is added to
+/// the i-th (resp. j-th) column block of A, where the rows of M_i are v_i^T L_a.
+///
+/// All m equations are evaluated together on packed m-vectors: V L gives the columns
+/// of every M_i and V (P1 V^T) every v_i^T P1 v_j, and the powers of E are applied by
+/// Horner's rule, walking the pairs from the last to the first.
 ///
 /// # Arguments
-/// * `vinegar_vars` - The randomly sampled vinegar variables (n-o elements).
-/// * `o_matrix` - The secret O matrix.
-/// * `p1_matrices` - The set of m P1_i matrices.
-/// * `p2_matrices` - The set of m P2_i matrices.
-/// * `p3_matrices` - The set of m P3_i matrices.
-/// * `l_matrices` - The set of m L_i matrices (L_i = (P1_i + P1_i^T)O + P2_i).
+/// * `vinegar_vectors` - The k vinegar vectors v_i, each with n-o elements.
+/// * `t_vector` - The target vector t (m elements).
+/// * `p1` - The m upper triangular P1_a matrices, (n-o)x(n-o), as packed m-vectors.
+/// * `l` - The m L_a matrices, (n-o)xo, as packed m-vectors.
 /// * `params` - MAYO variant parameters.
 ///
 /// # Returns
-/// `Ok((GFMatrix /*A (m x o)*/, GFVector /*y_prime (m elements)*/))` or an error.
+/// `Ok((GFMatrix /*A (m x k*o)*/, GFVector /*y (m elements)*/))` or an error.
 fn compute_lin_system_components(
-    vinegar_vars: &GFVector,        // s_V, length n-o
-    p1_mats: &[GFMatrix],           // Source for P_i^1, m of them, each (n-o)x(n-o)
-    l_mats: &[GFMatrix],            // P_i^2, m of them, each (n-o)xo
+    vinegar_vectors: &[GFVector],
+    t_vector: &GFVector,
+    p1: &PackedMatrix,
+    l: &PackedMatrix,
     params: &MayoVariantParams
-) -> Result<(GFMatrix /*A*/, GFVector /*y_prime*/), &'static str> {
-    
-    let num_vinegar_vars = params.n - params.o;
-    let num_oil_vars = params.o;
-    let m = params.m;
-
-    if vinegar_vars.len() != num_vinegar_vars {
-        return Err("Vinegar variables vector has incorrect length");
+) -> Result<(GFMatrix /*A*/, GFVector /*y*/), IdentityError> {
+    let num_vinegar_vars = params.v();
+    let (m, o, k) = (params.m, params.o, params.k);
+
+    if vinegar_vectors.len() != k || vinegar_vectors.iter().any(|v_i| v_i.len() != num_vinegar_vars) {
+        return Err(IdentityError::DimensionMismatch("Vinegar variables vector has incorrect length"));
+    }
+    if t_vector.len() != m {
+        return Err(IdentityError::DimensionMismatch("Target vector t has incorrect length"));
     }
-    if p1_mats.len() != m {
-        return Err("Incorrect number of P1 matrices");
+    if p1.m() != m || p1.num_rows() != num_vinegar_vars || p1.num_cols() != num_vinegar_vars {
+        return Err(IdentityError::DimensionMismatch("P1 matrix has incorrect dimensions"));
     }
-    if l_mats.len() != m {
-        return Err("Incorrect number of L matrices");
+    if l.m() != m || l.num_rows() != num_vinegar_vars || l.num_cols() != o {
+        return Err(IdentityError::DimensionMismatch("L matrix has incorrect dimensions"));
     }
 
-    let mut y_prime_elements = Vec::with_capacity(m);
-    let mut a_matrix_rows_as_vectors: Vec<GFVector> = Vec::with_capacity(m);
+    // V is k x (n-o) with the vinegar vectors as rows.
+    let v_matrix = Zeroizing::new(GFMatrix::from_vectors(vinegar_vectors.to_vec()));
+    // (V L)(i, c) holds column c of M_i.
+    let v_l = Zeroizing::new(matrix_packed_mul(&v_matrix, l)?);
+    // (V P1 V^T)(i, j) holds v_i^T P1_a v_j for every a.
+    let p1_vt = Zeroizing::new(packed_matrix_mul(p1, &matrix_transpose(&v_matrix))?);
+    let v_p1_vt = Zeroizing::new(matrix_packed_mul(&v_matrix, &p1_vt)?);
+
+    let limbs = m_vec_limbs(m);
+    let mut y_acc = Zeroizing::new(vec![0u64; limbs]);
+    let mut a_columns = Zeroizing::new(vec![0u64; k * o * limbs]);
+    for &(i, j) in whipped_pairs(k).iter().rev() {
+        m_vec_mul_z(&mut y_acc, m, &params.f_tail);
+        for column in a_columns.chunks_exact_mut(limbs) {
+            m_vec_mul_z(column, m, &params.f_tail);
+        }
 
-    for i in 0..m {
-        let p1_i = &p1_mats[i];
-        if p1_i.num_rows() != num_vinegar_vars || p1_i.num_cols() != num_vinegar_vars {
-            return Err("P1 matrix has incorrect dimensions");
+        m_vec_add(&mut y_acc, v_p1_vt.entry(i, j));
+        for c in 0..o {
+            m_vec_add(&mut a_columns[(i * o + c) * limbs..(i * o + c + 1) * limbs], v_l.entry(j, c));
         }
-        
-        // y_prime_i = s_V^T * P_i^1_symmetric * s_V
-        // P_i^1_symmetric = P1_i + P1_i^T
-        let p1_i_symmetric = matrix_symmetrize(p1_i)?; // M + M^T
-        // temp_y_vec = s_V^T * P_i^1_symmetric
-        let temp_y_vec = matrix_vec_mul_transpose_gfvector(vinegar_vars, &p1_i_symmetric)?;
-        // y_prime_i = temp_y_vec * s_V
-        let y_prime_i = vector_dot_product(&temp_y_vec, vinegar_vars)?;
-        y_prime_elements.push(y_prime_i);
-
-        // A_row_i = s_V^T * P_i^2
-        // P_i^2 is l_mats[i]
-        let l_i = &l_mats[i]; // (n-o) x o
-        if l_i.num_rows() != num_vinegar_vars || l_i.num_cols() != num_oil_vars {
-            return Err("L matrix has incorrect dimensions");
+        if i != j {
+            m_vec_add(&mut y_acc, v_p1_vt.entry(j, i));
+            for c in 0..o {
+                m_vec_add(&mut a_columns[(j * o + c) * limbs..(j * o + c + 1) * limbs], v_l.entry(i, c));
+            }
         }
-        let a_row_i = matrix_vec_mul_transpose_gfvector(vinegar_vars, l_i)?; // (1 x (n-o)) * ((n-o) x o) = (1 x o)
-        a_matrix_rows_as_vectors.push(a_row_i);
     }
-    
-    // Construct A matrix from its rows
-    let a_matrix = GFMatrix::from_vectors(a_matrix_rows_as_vectors); // from_vectors checks for consistent row lengths
-    if a_matrix.num_rows() != m || a_matrix.num_cols() != num_oil_vars {
-        // This check should ideally be redundant if from_vectors is correct and inputs were okay
-        return Err("Constructed A matrix has incorrect dimensions");
+
+    let y_vector = matrix_sub_vectors_gfvector(t_vector, &Zeroizing::new(unpack_m_vector(&y_acc, m)))?;
+    let columns = Zeroizing::new(a_columns.chunks_exact(limbs).map(|column| unpack_m_vector(column, m)).collect::<Vec<_>>());
+    let mut a_matrix = GFMatrix::zero(m, k * o);
+    for (col, column) in columns.iter().enumerate() {
+        for (row, value) in column.iter().enumerate() {
+            a_matrix.set_val(row, col, *value);
+        }
     }
 
-    Ok((a_matrix, y_prime_elements))
+    Ok((a_matrix, y_vector))
 }
 
 
 /// Implements MAYO.Sign (Algorithm 8 from the MAYO specification).
-/// Generates a signature for a given message using an expanded secret key.
-pub fn sign_message(esk: &ExpandedSecretKey, message: &Message, params_enum: &MayoParams) -> Result<Signature, &'static str> {
-    let params = params_enum.variant();
-
-    // 1. Parse esk and re-derive necessary components
-    //    esk = seedsk || O_bytes || P1_all_bytes || L_all_bytes
-    
-    let seedsk_bytes_len = params.sk_seed_bytes;
-    let o_bytes_len = params.o_bytes;
-    let p1_all_bytes_len = params.p1_bytes;
-    // L_all_bytes length is the rest, or can be calculated:
-    let num_l_elements = params.m * (params.n - params.o) * (params.n - params.o);
-    let l_all_bytes_len_expected = MayoParams::bytes_for_gf16_elements(num_l_elements);
-
-    if esk.0.len() != seedsk_bytes_len + o_bytes_len + p1_all_bytes_len + l_all_bytes_len_expected {
-        return Err("Expanded secret key has incorrect total length based on components");
+/// Generates a signature for a given message using an expanded secret key; the signature
+/// belongs to the variant of `esk`.
+pub fn sign_message(esk: &ExpandedSecretKey, message: &Message) -> Result<Signature, IdentityError> {
+    sign_message_with_rng(esk, message, &mut OsRandom)
+}
+
+/// MAYO.Sign drawing the salt randomizer R from `rng` instead of the operating system.
+/// With a `CtrDrbg` seeded from a KAT entry this reproduces the reference signature.
+pub fn sign_message_with_rng<R: RandomSource>(esk: &ExpandedSecretKey, message: &Message, rng: &mut R) -> Result<Signature, IdentityError> {
+    sign_digest_with_rng(esk, &shake256_digest(&message.0, esk.params()), rng)
+}
+
+/// MAYO.Sign from step 3 on, for a message already hashed to `m_digest` with
+/// `shake256_digest` or a `MessageHasher`. The signature verifies against the message
+/// itself, exactly as if it had been signed with `sign_message`.
+pub fn sign_digest(esk: &ExpandedSecretKey, m_digest: &MessageDigest) -> Result<Signature, IdentityError> {
+    sign_digest_with_rng(esk, m_digest, &mut OsRandom)
+}
+
+/// `sign_digest` drawing the salt randomizer R from `rng`.
+pub fn sign_digest_with_rng<R: RandomSource>(esk: &ExpandedSecretKey, m_digest: &MessageDigest, rng: &mut R) -> Result<Signature, IdentityError> {
+    SigningKey::from_expanded(esk)?.sign_digest_with_rng(m_digest, rng)
+}
+
+/// An expanded secret key decoded once into the matrices MAYO.Sign works on, so that
+/// signing many messages with one key skips `expand_sk` and the decoding of O, P1 and L.
+/// Secret parts are wiped on drop.
+pub struct SigningKey {
+    params: MayoParams,
+    seedsk: Zeroizing<Vec<u8>>,
+    o_matrix: Zeroizing<GFMatrix>,
+    p1: PackedMatrix,
+    l: Zeroizing<PackedMatrix>,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SigningKey({}, [REDACTED])", self.params.name())
+    }
+}
+
+impl SigningKey {
+    /// Expands and decodes a compact secret key.
+    pub fn new(csk: &CompactSecretKey) -> Result<SigningKey, IdentityError> {
+        Self::from_expanded(&expand_sk(csk)?)
+    }
+
+    /// Decodes an expanded secret key: esk = seedsk || O_bytes || P1_bytes || L_bytes
+    /// (length checked by the type).
+    pub fn from_expanded(esk: &ExpandedSecretKey) -> Result<Self, IdentityError> {
+        let params_enum = *esk.params();
+        let params = params_enum.variant();
+        let seedsk_end = params.sk_seed_bytes;
+        let o_end = seedsk_end + params.o_bytes;
+        let p1_end = o_end + params.p1_bytes;
+
+        Ok(SigningKey {
+            params: params_enum,
+            seedsk: Zeroizing::new(esk.as_bytes()[..seedsk_end].to_vec()),
+            o_matrix: Zeroizing::new(decode_o_matrix(&esk.as_bytes()[seedsk_end..o_end], params)?),
+            p1: PackedMatrix::decode_upper_triangular(&esk.as_bytes()[o_end..p1_end], params.v(), params.m)?,
+            l: Zeroizing::new(PackedMatrix::decode(&esk.as_bytes()[p1_end..], params.v(), params.o, params.m)?),
+        })
+    }
+
+    pub fn params(&self) -> &MayoParams {
+        &self.params
     }
 
-    let seedsk_bytes_slice = &esk.0[0..seedsk_bytes_len];
-    let seedsk = SeedSK(seedsk_bytes_slice.to_vec());
+    /// Signs `message` with fresh randomness; equivalent to `sign_message`.
+    pub fn sign(&self, message: &Message) -> Result<Signature, IdentityError> {
+        self.sign_with_rng(message, &mut OsRandom)
+    }
 
-    let o_bytes_slice = &esk.0[seedsk_bytes_len .. seedsk_bytes_len + o_bytes_len];
-    // let p1_all_bytes_slice = &esk.0[seedsk_bytes_len + o_bytes_len .. seedsk_bytes_len + o_bytes_len + p1_all_bytes_len];
-    let l_all_bytes_slice = &esk.0[seedsk_bytes_len + o_bytes_len + p1_all_bytes_len ..];
-    
-    if l_all_bytes_slice.len() != l_all_bytes_len_expected {
-        return Err("L_all_bytes component of ESK has unexpected length");
+    /// `sign` drawing the salt randomizer R from `rng`.
+    pub fn sign_with_rng<R: RandomSource>(&self, message: &Message, rng: &mut R) -> Result<Signature, IdentityError> {
+        self.sign_digest_with_rng(&shake256_digest(&message.0, &self.params), rng)
     }
 
-    // Re-derive seedpk to get P2_bytes and P3_bytes (P1_bytes also re-derived for consistency, though available in esk)
-    let (seedpk, derived_o_bytes) = shake256_xof_derive_pk_seed_and_o(&seedsk, params_enum);
-    if derived_o_bytes.as_slice() != o_bytes_slice { // Compare Vec<u8> with &[u8]
-        return Err("O_bytes in ESK does not match derivation from seedsk in ESK");
+    /// Signs a message already hashed to `m_digest`; see `sign_digest`.
+    pub fn sign_digest(&self, m_digest: &MessageDigest) -> Result<Signature, IdentityError> {
+        self.sign_digest_with_rng(m_digest, &mut OsRandom)
     }
-    
-    // P1 matrices can be decoded from esk's p1_all_bytes, or re-derived from seedpk.
-    // Let's use re-derived ones as per typical flow where esk might only store minimal seeds.
-    // However, Algorithm 6 stores O_bytes, P1_all_bytes, L_all_bytes in esk.
-    // So, we should use P1_all_bytes from esk.
-    let p1_all_bytes_from_esk_slice = &esk.0[seedsk_bytes_len + o_bytes_len .. seedsk_bytes_len + o_bytes_len + p1_all_bytes_len];
-
-    let p1_matrices = decode_p1_matrices(p1_all_bytes_from_esk_slice, params)?;
-    
-    // P2 and P3 are not in esk, they are derived from seedpk.
-    let p2_all_bytes_from_seedpk = derive_p2_bytes(&seedpk, params);
-    let p3_all_bytes_from_seedpk = shake256_xof_derive_p3(&seedpk, params_enum);
-
-    let _p2_matrices = decode_p2_matrices(&p2_all_bytes_from_seedpk, params)?; // Prefixed
-    let _p3_matrices = decode_p3_matrices(&p3_all_bytes_from_seedpk, params)?; // Prefixed
-    
-    // O and L matrices are from esk.
-    let _o_matrix = decode_o_matrix(o_bytes_slice, params)?; // Prefixed
-    let l_matrices = decode_l_matrices(l_all_bytes_slice, params)?;
-
-
-    // 2. Hash message M to M_digest
-    let m_digest = shake256_digest(&message.0, params_enum);
-
-    for _retry_count in 0..MAX_SIGN_RETRIES {
-        // 3. Sample salt
-        let mut salt_bytes_vec = vec![0u8; params.salt_bytes];
-        getrandom(&mut salt_bytes_vec).map_err(|_| "Failed to generate random salt")?;
-        let salt = Salt(salt_bytes_vec);
+
+    /// MAYO.Sign from step 3 on, drawing the salt randomizer R from `rng`.
+    pub fn sign_digest_with_rng<R: RandomSource>(&self, m_digest: &MessageDigest, rng: &mut R) -> Result<Signature, IdentityError> {
+        let params_enum = &self.params;
+        let params = params_enum.variant();
+        if m_digest.0.len() != params.digest_bytes {
+            return Err(IdentityError::InvalidLength("Message digest has incorrect length"));
+        }
+        let (n, o, k) = (params.n, params.o, params.k);
+
+        // 1. esk was decoded by `from_expanded`. Secret intermediates are held in
+        //    `Zeroizing` so they are wiped on every exit path.
+        let (seedsk, o_matrix, p1, l) = (&self.seedsk[..], &*self.o_matrix, &self.p1, &*self.l);
+
+        // 2. M_digest = SHAKE256(M) is computed by the caller.
+
+        // 3. Derive the salt from M_digest, fresh randomness R and seedsk
+        let mut randomizer = Zeroizing::new(vec![0u8; params.salt_bytes]);
+        rng.fill_bytes(&mut randomizer)?;
+        let salt = shake256_derive_salt(m_digest, &randomizer, seedsk, params_enum);
 
         // 4. Derive target vector t
-        let t_bytes = shake256_derive_target_t(&m_digest, &salt, params_enum);
+        let t_bytes = shake256_derive_target_t(m_digest, &salt, params_enum);
         let t_vector = decode_gf_elements(&t_bytes, params.m)?;
 
-        // 5. Sample random vinegar variables (n-o variables)
-        let num_vinegar_vars = params.n - params.o;
-        let mut vinegar_vars_vec = Vec::with_capacity(num_vinegar_vars);
-        for _ in 0..num_vinegar_vars {
-            let mut v_byte = [0u8;1];
-            getrandom(&mut v_byte).map_err(|_| "Failed to generate random vinegar variable")?;
-            vinegar_vars_vec.push(GFElement(v_byte[0] & 0x0F)); // Ensure it's a nibble
-        }
-        let vinegar_vars = vinegar_vars_vec;
-
-        // 6. Compute matrix A (m x o) and vector y_prime (m elements)
-        // Note: P2 and P3 matrices are not directly used by compute_lin_system_components
-        // under the current interpretation. o_matrix is also not used.
-        let (a_matrix, y_prime_vector) = match compute_lin_system_components(
-            &vinegar_vars, &p1_matrices, &l_matrices, params
-        ) {
-            Ok(res) => res,
-            // If compute_lin_system_components is the one returning "Not yet implemented", update this.
-            // However, we are now implementing it.
-            // Err(e) if e == "compute_Y_A_yprime_and_s_components: Not yet implemented" => {
-            //     return Err("MAYO.Sign math core (compute_Y_A_yprime_and_s_components) not implemented");
-            // }
-            Err(e) => return Err(e), 
-        };
-
-        // 7. Solve Ax = t - y_prime for x (o elements - oil variables)
-        let target_for_solver = matrix_sub_vectors_gfvector(&t_vector, &y_prime_vector)?;
-        
-        match solve_linear_system(&a_matrix, &target_for_solver) {
-            Ok(Some(x_solution_oils)) => { // x_solution_oils has 'o' elements
-                if x_solution_oils.len() != params.o {
-                    // Should be guaranteed by solver if A is m x o.
-                    return Err("Solver returned oil solution of incorrect length");
-                }
-                // 8. Construct signature vector s (n elements = n-o vinegar + o oil)
-                let mut s_elements: GFVector = Vec::with_capacity(params.n);
-                s_elements.extend_from_slice(&vinegar_vars);
-                s_elements.extend_from_slice(&x_solution_oils);
-                
-                // 9. Encode s and concatenate with salt
-                let s_bytes = encode_s_vector(&s_elements, params);
-                
-                let mut sig_bytes = Vec::with_capacity(s_bytes.len() + params.salt_bytes);
-                sig_bytes.extend_from_slice(&s_bytes);
-                sig_bytes.extend_from_slice(&salt.0);
-                
-                return Ok(Signature(sig_bytes));
-            }
-            Ok(None) => continue, // No solution, try next salt
-            Err(e) => {
-                // Log solver error if possible, then continue or return based on policy
-                // For now, let's assume solver errors are fatal for this attempt.
-                // Depending on the error, it might be retryable.
-                eprintln!("Solver error: {}", e); // Temporary, not suitable for wasm/lib
-                continue; // Or return Err(e) if solver errors are not to be retried.
+        let v_bytes = params.v_bytes();
+        for ctr in 0..MAX_SIGN_RETRIES {
+            // 5. Derive the k vinegar vectors and the randomizer r for this attempt
+            let vinegar_bytes = shake256_derive_vinegar(m_digest, &salt, seedsk, ctr as u8, params_enum);
+            let vinegar_vectors = Zeroizing::new(vinegar_bytes[..k * v_bytes]
+                .chunks_exact(v_bytes)
+                .map(|chunk| decode_gf_elements(chunk, params.v()))
+                .collect::<Result<Vec<_>, _>>()?);
+            let r_vector = Zeroizing::new(decode_gf_elements(&vinegar_bytes[k * v_bytes..], k * o)?);
+
+            // 6. Build the whipped system A x = y (A is m x k*o)
+            let (a_matrix, y_vector) = compute_lin_system_components(
+                &vinegar_vectors, &t_vector, p1, l, params
+            )?;
+            let (a_matrix, y_vector) = (Zeroizing::new(a_matrix), Zeroizing::new(y_vector));
+
+            // 7. Sample a solution in constant time; retry with new vinegar vectors if A is not full rank
+            let x_solution = match sample_solution_ct(&a_matrix, &y_vector, &r_vector)? {
+                Some(x) => Zeroizing::new(x),
+                None => continue,
+            };
+
+            // 8. s_i = (v_i + O x_i) || x_i for each of the k vectors
+            let mut s_elements: GFVector = Vec::with_capacity(n * k);
+            for (v_i, x_i) in vinegar_vectors.iter().zip(x_solution.chunks_exact(o)) {
+                let x_i = Zeroizing::new(x_i.to_vec());
+                let o_x_i = Zeroizing::new(matrix_vec_mul(o_matrix, &x_i)?);
+                s_elements.extend(v_i.iter().zip(o_x_i.iter()).map(|(a, b)| gf16_add(*a, *b)));
+                s_elements.extend_from_slice(&x_i);
             }
+
+            // 9. Encode s and concatenate with salt
+            let s_bytes = encode_s_vector(&s_elements, params);
+            let mut sig_bytes = Vec::with_capacity(params.sig_bytes());
+            sig_bytes.extend_from_slice(&s_bytes);
+            sig_bytes.extend_from_slice(&salt.0);
+
+            return Signature::from_bytes(*params_enum, sig_bytes);
         }
+        Err(IdentityError::SigningFailed)
     }
-    Err("MAYO.Sign failed after maximum retries")
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{CompactSecretKey, ExpandedSecretKey as EskTypeForTest}; // Renamed to avoid conflict
     use crate::params::MayoParams;
-    use crate::keygen::{compact_key_gen, expand_sk}; // For generating esk
+    use crate::keygen::{compact_key_gen, expand_sk};
+    use crate::types::GFElement;
+    use crate::rng::CtrDrbg;
 
-    // Helper to create a dummy ESK for testing the flow
-    // This is complex because ESK structure is seedsk | O_bytes | P1_bytes | L_bytes
-    fn create_dummy_esk(params_enum: &MayoParams) -> EskTypeForTest {
-        let params = params_enum.variant();
+    fn create_esk(params_enum: &MayoParams) -> ExpandedSecretKey {
         let (csk, _cpk) = compact_key_gen(params_enum).unwrap();
-        expand_sk(&csk, params_enum).unwrap() // Use the actual expand_sk
+        expand_sk(&csk).unwrap()
+    }
+
+    fn test_sign_message_for_variant(params_enum: &MayoParams) {
+        let params = params_enum.variant();
+        let esk = create_esk(params_enum);
+        let message = Message(b"test message".to_vec());
+
+        let sig = sign_message(&esk, &message).expect("sign_message failed");
+        assert_eq!(sig.as_bytes().len(), params.sig_bytes(), "Signature length is incorrect");
+
+        // The salt is bound to the message: signing again gives a fresh salt.
+        let sig2 = sign_message(&esk, &message).expect("sign_message failed");
+        assert_ne!(sig.as_bytes()[params.s_bytes()..], sig2.as_bytes()[params.s_bytes()..]);
     }
 
     #[test]
-    fn test_sign_message_flow_mayo1() {
+    fn test_sign_message_mayo1() {
+        test_sign_message_for_variant(&MayoParams::mayo1());
+    }
+
+    #[test]
+    fn test_sign_message_mayo2() {
+        test_sign_message_for_variant(&MayoParams::mayo2());
+    }
+
+    #[test]
+    fn test_sign_message_rejects_bad_esk_length() {
+        // A truncated esk can no longer be built, so it never reaches sign_message.
         let params_enum = MayoParams::mayo1();
-        let params_variant = params_enum.variant();
-        let esk = create_dummy_esk(&params_enum);
-        let message = Message(b"test message".to_vec());
+        let esk = create_esk(&params_enum);
+        let mut esk_bytes = esk.into_bytes();
+        esk_bytes.pop();
+        assert_eq!(
+            ExpandedSecretKey::from_bytes(params_enum, esk_bytes),
+            Err(IdentityError::InvalidLength("Expanded secret key has incorrect length"))
+        );
+    }
 
-        let sign_result = sign_message(&esk, &message, &params_enum);
-        
-        // With compute_lin_system_components implemented, we expect either Ok (if solvable by chance)
-        // or Err from solver or "MAYO.Sign failed after maximum retries".
-        // For this test, we are checking that it doesn't panic and proceeds past component computation.
-        // If it returns Ok, A and y_prime were computed with correct dimensions.
-        // A more specific error than the placeholder is expected now.
-        match sign_result {
-            Ok(sig) => {
-                let expected_sig_len = MayoParams::bytes_for_gf16_elements(params_variant.n) + params_variant.salt_bytes;
-                assert_eq!(sig.0.len(), expected_sig_len, "Signature length is incorrect");
-            },
-            Err(e) => {
-                assert!(e == "MAYO.Sign failed after maximum retries" || e.starts_with("Solver error"), 
-                        "Expected sign failure or solver error, got: {}", e);
-            }
+    #[test]
+    fn test_lin_system_solution_satisfies_secret_map() {
+        // For s_i = (v_i + O x_i) || x_i, the whipped system is exactly
+        // sum_l E^l (v_i^T P1 v_j + ...) + A x = t, so A x must equal y.
+        let params_enum = MayoParams::mayo2();
+        let params = params_enum.variant();
+        let esk = create_esk(&params_enum);
+        let o_end = params.sk_seed_bytes + params.o_bytes;
+        let p1_end = o_end + params.p1_bytes;
+        let p1 = PackedMatrix::decode_upper_triangular(&esk.as_bytes()[o_end..p1_end], params.v(), params.m).unwrap();
+        let l = PackedMatrix::decode(&esk.as_bytes()[p1_end..], params.v(), params.o, params.m).unwrap();
+
+        let vinegar_vectors: Vec<GFVector> = (0..params.k)
+            .map(|i| (0..params.v()).map(|j| GFElement(((i * 5 + j * 3) % 16) as u8)).collect())
+            .collect();
+        let t_vector: GFVector = (0..params.m).map(|i| GFElement((i % 16) as u8)).collect();
+        let (a_matrix, y_vector) =
+            compute_lin_system_components(&vinegar_vectors, &t_vector, &p1, &l, params).unwrap();
+        assert_eq!(a_matrix.num_rows(), params.m);
+        assert_eq!(a_matrix.num_cols(), params.k * params.o);
+        assert_eq!(y_vector.len(), params.m);
+
+        let r_vector = vec![GFElement(0); params.k * params.o];
+        if let Some(x) = sample_solution_ct(&a_matrix, &y_vector, &r_vector).unwrap() {
+            assert_eq!(matrix_vec_mul(&a_matrix, &x).unwrap(), y_vector);
         }
+
+        assert!(compute_lin_system_components(&vinegar_vectors[1..], &t_vector, &p1, &l, params).is_err());
     }
 
     #[test]
-    fn test_sign_message_flow_mayo2() {
-        let params_enum = MayoParams::mayo2();
-        let params_variant = params_enum.variant();
-        let esk = create_dummy_esk(&params_enum);
-        let message = Message(b"another test message".to_vec());
-
-        let sign_result = sign_message(&esk, &message, &params_enum);
-        match sign_result {
-            Ok(sig) => {
-                let expected_sig_len = MayoParams::bytes_for_gf16_elements(params_variant.n) + params_variant.salt_bytes;
-                assert_eq!(sig.0.len(), expected_sig_len, "Signature length is incorrect");
-            },
-            Err(e) => {
-                assert!(e == "MAYO.Sign failed after maximum retries" || e.starts_with("Solver error"), 
-                        "Expected sign failure or solver error, got: {}", e);
-            }
+    fn test_signing_key_matches_one_shot() {
+        let params_enum = MayoParams::mayo1();
+        let (csk, cpk) = compact_key_gen(&params_enum).unwrap();
+        let esk = expand_sk(&csk).unwrap();
+        let signing_key = SigningKey::new(&csk).unwrap();
+        assert_eq!(signing_key.params(), &params_enum);
+                assert_eq!(format!("{:?}", signing_key), "SigningKey(mayo1, [REDACTED])");
+
+        // With the same randomness the context reproduces sign_message bit for bit.
+        for text in [&b"first"[..], b"second", b""] {
+            let message = Message(text.to_vec());
+            let from_key = signing_key.sign_with_rng(&message, &mut CtrDrbg::new(&[9; 48])).unwrap();
+            let one_shot = sign_message_with_rng(&esk, &message, &mut CtrDrbg::new(&[9; 48])).unwrap();
+            assert_eq!(from_key, one_shot);
+            assert!(crate::api::verify(&cpk, &message, &signing_key.sign(&message).unwrap()).unwrap());
         }
+
+        let short = MessageDigest(vec![0; params_enum.digest_bytes() - 1]);
+        assert_eq!(signing_key.sign_digest(&short), Err(IdentityError::InvalidLength("Message digest has incorrect length")));
     }
-    
-    // TODO: More detailed tests once compute_Y_A_yprime_and_s_components is implemented.
-    // These tests would involve:
-    // 1. Mocking or providing a test implementation for compute_Y_A_yprime_and_s_components.
-    // 2. Scenario 1: Test solver integration (inconsistent system):
-    //    - Craft dummy_A, dummy_y_prime, and t_vector such that Ax = t - y_prime is inconsistent.
-    //    - Assert that sign_message (possibly by controlling MAX_SIGN_RETRIES for the test)
-    //      returns Err("MAYO.Sign failed after maximum retries").
-    // 3. Scenario 2: Test solver integration (consistent system leading to signature):
-    //    - Craft dummy_A, dummy_y_prime, and t_vector such that Ax = t - y_prime is consistent
-    //      and solve_linear_system returns Ok(Some(dummy_x_solution)).
-    //    - Assert that sign_message returns Ok(Signature(...)).
-    //    - Check the structure of the returned Signature:
-    //        - Its length should be params.bytes_for_gf16_elements(params.n) + params.salt_bytes.
-    //        - The salt part of the signature should match the dummy salt used in the test setup.
-
-    // TODO: Implement Known Answer Tests (KATs) for sign_message 
-    // once compute_Y_A_yprime_and_s_components is fully implemented.
-    // These tests will use official MAYO test vectors.
 }
@@ -1,19 +1,31 @@
 //! Implements a linear system solver over GF(16) using Gaussian elimination.
+//!
+//! `solve_linear_system` and `sample_solution` branch on the matrix entries and are meant
+//! for public data; `sample_solution_ct` is the constant-time variant used when signing.
+//! `solution_space` describes every solution of a system by its rank, a particular
+//! solution and a kernel basis, and can sample uniformly among them.
 
+use zeroize::Zeroizing;
+use crate::error::IdentityError;
 use crate::types::{GFElement, GFMatrix, GFVector};
-use crate::gf::{gf16_mul, gf16_pow, gf16_sub}; // gf16_sub is same as gf16_add; removed gf16_add as unused
+use crate::gf::{ct_is_zero_mask, ct_mask_from_bit, gf16_add, gf16_inv_ct, gf16_mul, gf16_sub}; // gf16_sub is same as gf16_add
+use crate::matrix::{matrix_sub_vectors_gfvector, matrix_vec_mul};
+use crate::rng::{OsRandom, RandomSource};
 // Note: GFMatrix type is from crate::types, its methods are in crate::matrix
 // We'll use the struct directly and its public fields (data, rows, cols)
 // and helper methods like `get_unsafe`, `set_val` defined in `crate::matrix`.
 
 /// Computes the multiplicative inverse of an element in GF(16).
 /// GF(16)* is a cyclic group of order 15. The inverse of `a` is `a^(15-1) = a^14`.
-pub fn gf16_inv(element: GFElement) -> Result<GFElement, &'static str> {
+///
+/// The power is computed in constant time, but rejecting zero is a branch; use
+/// `gf16_inv_ct` on secret values that may be zero.
+pub fn gf16_inv(element: GFElement) -> Result<GFElement, IdentityError> {
     if element.0 == 0 {
-        Err("Cannot invert zero element")
+        Err(IdentityError::NotInvertible)
     } else {
         // For GF(q), inverse of a is a^(q-2). Here q=16, so a^14.
-        Ok(gf16_pow(element, 14))
+        Ok(gf16_inv_ct(element))
     }
 }
 
@@ -28,22 +40,287 @@ pub fn gf16_inv(element: GFElement) -> Result<GFElement, &'static str> {
 ///   exist (due to free variables), one particular solution is returned (typically by
 ///   setting free variables to 0).
 /// * `Ok(None)` - If the system is inconsistent (no solution).
-/// * `Err(&'static str)` - For dimension mismatches or other errors during solving.
-pub fn solve_linear_system(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<Option<GFVector>, &'static str> {
+/// * `Err(IdentityError)` - For dimension mismatches or other errors during solving.
+pub fn solve_linear_system(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<Option<GFVector>, IdentityError> {
+    let num_equations = a_matrix.num_rows();
+    let num_variables = a_matrix.num_cols();
+
+    let (aug, rank) = row_reduce_augmented(a_matrix, y_vector)?;
+
+    // 3. Check for No Solution (inconsistency)
+    // If any row [0 0 ... 0 | c] has c != 0, then system is inconsistent.
+    for r_idx in rank..num_equations {
+        if aug.get_unsafe(r_idx, num_variables).0 != 0 {
+            return Ok(None); // Inconsistent system
+        }
+    }
+
+    Ok(Some(back_substitute(&aug, rank)))
+}
+
+/// The complete solution set of a consistent system A x = y: every solution is
+/// `particular` plus a GF(16)-linear combination of the `kernel` basis vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolutionSpace {
+    rank: usize,
+    particular: GFVector,
+    kernel: Vec<GFVector>,
+}
+
+impl SolutionSpace {
+    /// The rank of A.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// The solution with every free variable set to zero, as `solve_linear_system`.
+    pub fn particular(&self) -> &GFVector {
+        &self.particular
+    }
+
+    /// A basis of the kernel of A, one vector per free variable.
+    pub fn kernel(&self) -> &[GFVector] {
+        &self.kernel
+    }
+
+    /// The dimension of the solution space; there are 16^dimension solutions.
+    pub fn dimension(&self) -> usize {
+        self.kernel.len()
+    }
+
+    /// Draws a uniformly random solution from the operating system RNG.
+    pub fn sample(&self) -> Result<GFVector, IdentityError> {
+        self.sample_with_rng(&mut OsRandom)
+    }
+
+    /// Draws a uniformly random solution: the particular solution plus a combination
+    /// of the kernel basis with uniform coefficients taken from `rng`.
+    pub fn sample_with_rng<R: RandomSource>(&self, rng: &mut R) -> Result<GFVector, IdentityError> {
+        let mut coefficients = Zeroizing::new(vec![0u8; self.kernel.len()]);
+        rng.fill_bytes(&mut coefficients)?;
+        let mut solution = self.particular.clone();
+        for (basis_vector, coefficient) in self.kernel.iter().zip(coefficients.iter()) {
+            let coefficient = GFElement(coefficient & 0x0F);
+            for (x, b) in solution.iter_mut().zip(basis_vector) {
+                *x = gf16_add(*x, gf16_mul(coefficient, *b));
+            }
+        }
+        Ok(solution)
+    }
+}
+
+/// Solves A x = y completely, returning its rank, a particular solution and a kernel
+/// basis, or `None` if the system is inconsistent.
+///
+/// In the reduced row echelon form of [A|y], the kernel vector of a free column f has
+/// a 1 at f and, for every pivot row, the row's entry in column f at the row's pivot
+/// column (-a = a in characteristic 2).
+pub fn solution_space(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<Option<SolutionSpace>, IdentityError> {
+    let num_equations = a_matrix.num_rows();
+    let num_variables = a_matrix.num_cols();
+
+    let (aug, rank) = row_reduce_augmented(a_matrix, y_vector)?;
+    if (rank..num_equations).any(|r| aug.get_unsafe(r, num_variables).0 != 0) {
+        return Ok(None);
+    }
+
+    // Pivot columns, one per pivot row: the first non-zero entry of the row.
+    let pivot_columns: Vec<usize> = (0..rank)
+        .map(|r| (0..num_variables).find(|&c| aug.get_unsafe(r, c).0 != 0).expect("pivot rows are non-zero"))
+        .collect();
+    let kernel = (0..num_variables)
+        .filter(|c| !pivot_columns.contains(c))
+        .map(|free| {
+            let mut basis_vector = vec![GFElement(0); num_variables];
+            basis_vector[free] = GFElement(1);
+            for (r, &pivot) in pivot_columns.iter().enumerate() {
+                basis_vector[pivot] = aug.get_unsafe(r, free);
+            }
+            basis_vector
+        })
+        .collect();
+
+    Ok(Some(SolutionSpace { rank, particular: back_substitute(&aug, rank), kernel }))
+}
+
+/// Returns a basis of the kernel {x : A x = 0}.
+pub fn kernel_basis(a_matrix: &GFMatrix) -> Result<Vec<GFVector>, IdentityError> {
+    let zero = vec![GFElement(0); a_matrix.num_rows()];
+    let space = solution_space(a_matrix, &zero)?.expect("homogeneous systems are consistent");
+    Ok(space.kernel)
+}
+
+/// Returns the rank of A.
+pub fn matrix_rank(a_matrix: &GFMatrix) -> usize {
+    let zero = vec![GFElement(0); a_matrix.num_rows()];
+    row_reduce_augmented(a_matrix, &zero).expect("zero vector has matching length").1
+}
+
+/// Implements SampleSolution from the MAYO specification.
+/// Returns the solution x of Ax = y that agrees with `r_vector` on every free
+/// (non-pivot) variable, or `None` if A does not have full row rank.
+///
+/// Because the free variables are fixed to `r_vector` and the pivot variables are then
+/// uniquely determined, the result does not depend on how the elimination is carried out.
+pub fn sample_solution(a_matrix: &GFMatrix, y_vector: &GFVector, r_vector: &GFVector) -> Result<Option<GFVector>, IdentityError> {
+    if r_vector.len() != a_matrix.num_cols() {
+        return Err(IdentityError::DimensionMismatch("Randomizer vector length must match matrix A columns"));
+    }
+
+    // Solve A * delta = y - A * r with free variables at zero, then x = r + delta.
+    let a_r = matrix_vec_mul(a_matrix, r_vector)?;
+    let shifted_y = matrix_sub_vectors_gfvector(y_vector, &a_r)?;
+    let (aug, rank) = row_reduce_augmented(a_matrix, &shifted_y)?;
+    if rank < a_matrix.num_rows() {
+        return Ok(None);
+    }
+
+    let delta = back_substitute(&aug, rank);
+    Ok(Some(r_vector.iter().zip(&delta).map(|(r_i, d_i)| gf16_add(*r_i, *d_i)).collect()))
+}
+
+/// Constant-time variant of `sample_solution`, used by `sign_message`.
+///
+/// Pivot search, row swaps, normalization and elimination are replaced by masked row
+/// operations that run over every entry of [A|y] whatever its value, and pivots are
+/// inverted with `gf16_inv_ct`. The only thing the running time reveals is whether A has
+/// full row rank, which decides whether the signer retries anyway. Since the solution
+/// is unique once the free variables are fixed to `r_vector`, the result is the same as
+/// `sample_solution` on every input.
+pub fn sample_solution_ct(a_matrix: &GFMatrix, y_vector: &GFVector, r_vector: &GFVector) -> Result<Option<GFVector>, IdentityError> {
+    let num_equations = a_matrix.num_rows();
+    let num_variables = a_matrix.num_cols();
+    if r_vector.len() != num_variables {
+        return Err(IdentityError::DimensionMismatch("Randomizer vector length must match matrix A columns"));
+    }
+    if y_vector.len() != num_equations {
+        return Err(IdentityError::DimensionMismatch("Matrix A rows must match y_vector length"));
+    }
+
+    // Solve A * delta = y - A * r with free variables at zero, then x = r + delta.
+    let a_r = Zeroizing::new(matrix_vec_mul(a_matrix, r_vector)?);
+    let shifted_y = Zeroizing::new(matrix_sub_vectors_gfvector(y_vector, &a_r)?);
+
+    let width = num_variables + 1;
+    let mut aug: Zeroizing<Vec<GFElement>> = Zeroizing::new(Vec::with_capacity(num_equations * width));
+    for (r, y_r) in shifted_y.iter().enumerate() {
+        aug.extend_from_slice(&a_matrix.data[r * num_variables..(r + 1) * num_variables]);
+        aug.push(*y_r);
+    }
+
+    let rank = echelon_form_ct(&mut aug, num_equations, width);
+    if rank < num_equations {
+        return Ok(None);
+    }
+
+    let mut x = r_vector.clone();
+    back_substitute_ct(&mut aug, num_equations, width, &mut x);
+    Ok(Some(x))
+}
+
+/// Returns 0xFF if `a == b` and 0x00 otherwise, without branching.
+fn ct_usize_eq_mask(a: usize, b: usize) -> u8 {
+    let diff = a ^ b;
+    // The top bit of (diff | -diff) is set exactly when diff != 0.
+    let nonzero = ((diff | diff.wrapping_neg()) >> (usize::BITS - 1)) as u8;
+    ct_mask_from_bit(nonzero ^ 1)
+}
+
+/// Returns 0xFF if `a > b` and 0x00 otherwise, without branching (row indices are far
+/// below `usize::MAX / 2`, so the subtraction borrows exactly when `a > b`).
+fn ct_usize_gt_mask(a: usize, b: usize) -> u8 {
+    ct_mask_from_bit((b.wrapping_sub(a) >> (usize::BITS - 1)) as u8)
+}
+
+/// Brings the row-major `rows x width` augmented matrix to row echelon form with unit
+/// pivots, in constant time, and returns the rank of its first `width - 1` columns.
+///
+/// The current pivot row is tracked as a counter that only enters masks. For every
+/// column, the pivot row accumulates the rows below it while its entry is still zero,
+/// is normalized and written back if a pivot was found, and is then eliminated from
+/// the rows below.
+fn echelon_form_ct(aug: &mut [GFElement], rows: usize, width: usize) -> usize {
+    let mut pivot_row = 0usize;
+    let mut pivot_row_buf: Zeroizing<Vec<GFElement>> = Zeroizing::new(vec![GFElement(0); width]);
+
+    for pivot_col in 0..width - 1 {
+        pivot_row_buf.iter_mut().for_each(|b| *b = GFElement(0));
+        let mut pivot = 0u8;
+        for row in 0..rows {
+            let take = ct_usize_eq_mask(row, pivot_row)
+                | (ct_usize_gt_mask(row, pivot_row) & ct_is_zero_mask(pivot));
+            for (b, a) in pivot_row_buf.iter_mut().zip(&aug[row * width..(row + 1) * width]) {
+                b.0 ^= a.0 & take;
+            }
+            pivot = pivot_row_buf[pivot_col].0;
+        }
+        let pivot_found = !ct_is_zero_mask(pivot);
+
+        let inv_pivot = gf16_inv_ct(GFElement(pivot));
+        for b in pivot_row_buf.iter_mut() {
+            *b = gf16_mul(*b, inv_pivot);
+        }
+
+        for row in 0..rows {
+            let write = ct_usize_eq_mask(row, pivot_row) & pivot_found;
+            for (a, b) in aug[row * width..(row + 1) * width].iter_mut().zip(pivot_row_buf.iter()) {
+                a.0 = (a.0 & !write) | (b.0 & write);
+            }
+        }
+
+        for row in 0..rows {
+            let below = ct_usize_gt_mask(row, pivot_row) & pivot_found;
+            let factor = GFElement(aug[row * width + pivot_col].0 & below);
+            for (a, b) in aug[row * width..(row + 1) * width].iter_mut().zip(pivot_row_buf.iter()) {
+                *a = gf16_sub(*a, gf16_mul(factor, *b));
+            }
+        }
+
+        pivot_row += (pivot_found & 1) as usize;
+    }
+    pivot_row
+}
+
+/// Back-substitution on an echelon form of full row rank, in constant time, adding the
+/// pivot variables into `x` (free variables keep their value).
+///
+/// Working upwards, the first non-zero entry of each row is its unit pivot; every
+/// column is visited and only the pivot column, selected by a mask, receives the
+/// right-hand side, which is then eliminated from the rows above.
+fn back_substitute_ct(aug: &mut [GFElement], rows: usize, width: usize, x: &mut [GFElement]) {
+    let num_variables = width - 1;
+    for row in (0..rows).rev() {
+        let mut finished = 0u8;
+        for col in 0..num_variables {
+            let is_pivot = !ct_is_zero_mask(aug[row * width + col].0) & !finished;
+            let u = GFElement(aug[row * width + num_variables].0 & is_pivot);
+            x[col] = gf16_add(x[col], u);
+            for above in 0..row {
+                let term = gf16_mul(aug[above * width + col], u);
+                aug[above * width + num_variables] = gf16_sub(aug[above * width + num_variables], term);
+            }
+            finished |= is_pivot;
+        }
+    }
+}
+
+/// Builds the augmented matrix [A|y] and brings it to reduced row echelon form.
+/// Returns the reduced matrix together with the rank of A.
+fn row_reduce_augmented(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<(GFMatrix, usize), IdentityError> {
     let num_equations = a_matrix.num_rows();
     let num_variables = a_matrix.num_cols();
 
     if num_equations != y_vector.len() {
-        return Err("Matrix A rows must match y_vector length");
+        return Err(IdentityError::DimensionMismatch("Matrix A rows must match y_vector length"));
     }
 
     // 1. Construct augmented matrix [A|y]
     let mut aug_matrix_data = Vec::with_capacity(num_equations * (num_variables + 1));
-    for r in 0..num_equations {
+    for (r, y_r) in y_vector.iter().enumerate() {
         for c in 0..num_variables {
             aug_matrix_data.push(a_matrix.get_unsafe(r, c));
         }
-        aug_matrix_data.push(y_vector[r]);
+        aug_matrix_data.push(*y_r);
     }
     // Directly using GFMatrix::new_with_data which is in matrix.rs impl block
     let mut aug = GFMatrix::new_with_data(num_equations, num_variables + 1, aug_matrix_data);
@@ -96,21 +373,15 @@ pub fn solve_linear_system(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<O
         // If no non-zero pivot found in this column (below current pivot_row),
         // this column corresponds to a free variable. We move to the next column.
     }
-    let rank = pivot_row; // Number of non-zero rows after REF
-
-    // 3. Check for No Solution (inconsistency)
-    // If any row [0 0 ... 0 | c] has c != 0, then system is inconsistent.
-    for r_idx in rank..num_equations {
-        if aug.get_unsafe(r_idx, num_variables).0 != 0 {
-            return Ok(None); // Inconsistent system
-        }
-    }
+    Ok((aug, pivot_row)) // pivot_row is the number of non-zero rows after REF, i.e. the rank
+}
 
-    // 4. Back-Substitution (and handle free variables by setting them to 0)
+/// Back-substitution on a reduced augmented matrix, with free variables set to 0.
+fn back_substitute(aug: &GFMatrix, rank: usize) -> GFVector {
+    let num_variables = aug.num_cols() - 1;
     let mut solution = vec![GFElement(0); num_variables];
-    
+
     // Iterate from the last pivot row upwards
-    // pivot_row here is `rank`
     for r_idx_piv in (0..rank).rev() {
         // Find the pivot column for this row. It's the first '1' from left.
         let mut p_col = 0;
@@ -121,23 +392,24 @@ pub fn solve_linear_system(a_matrix: &GFMatrix, y_vector: &GFVector) -> Result<O
         // This implies aug.get_unsafe(r_idx_piv, p_col) is 1 (due to normalization).
 
         let mut val = aug.get_unsafe(r_idx_piv, num_variables); // y_i'
-        for c_idx in (p_col + 1)..num_variables {
-            let term = gf16_mul(aug.get_unsafe(r_idx_piv, c_idx), solution[c_idx]);
+        for (c_idx, solution_c) in solution.iter().enumerate().skip(p_col + 1) {
+            let term = gf16_mul(aug.get_unsafe(r_idx_piv, c_idx), *solution_c);
             val = gf16_sub(val, term);
         }
         solution[p_col] = val; // Since aug(r_idx_piv, p_col) is 1
     }
-    
+
     // Free variables (if rank < num_variables) are already effectively set to 0
     // because `solution` was initialized to zeros and corresponding x_j are not updated by back-substitution if they are free.
-    
-    Ok(Some(solution))
+    solution
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gf::gf16_pow;
+    use crate::rng::{CtrDrbg, RandomSource};
     // Helper to create GFElement for tests
     fn gf(val: u8) -> GFElement { GFElement(val) }
     // Helper to create GFMatrix from Vec<Vec<GFElement>> for tests
@@ -150,7 +422,7 @@ mod tests {
 
     #[test]
     fn test_gf16_inv() {
-        assert_eq!(gf16_inv(gf(0)).err(), Some("Cannot invert zero element"));
+        assert_eq!(gf16_inv(gf(0)).err(), Some(IdentityError::NotInvertible));
         assert_eq!(gf16_inv(gf(1)).unwrap().0, 1); // 1^-1 = 1
 
         // x^4 + x + 1 = 0 (0x13, or 0b10011)
@@ -191,7 +463,10 @@ mod tests {
         let a = mat(vec![vec![gf(2), gf(1)], vec![gf(1), gf(2)]]);
         let y = vec_gf(vec![gf(1), gf(1)]);
         let x = solve_linear_system(&a, &y).unwrap().unwrap();
-        assert_eq!(x, vec![gf(0), gf(1)]);
+        // 2*9 = x*(x^3+1) = 1 and 2+9 = B are right, but 1*9 = 9 so R2 = [0, B, 8], not [0, B, B].
+        // Subtracting the equations gives 3*x1 = 3*x2, so x1 = x2 = inv(3) = E.
+        assert_eq!(x, vec![gf(0xE), gf(0xE)]);
+        assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y);
 
         // Verify: A*x = y
         // [2,1] * [0] = (2*0)^(1*1) = 0^1 = 1
@@ -306,4 +581,184 @@ mod tests {
         let y = vec_gf(vec![gf(1), gf(2)]);
         assert!(solve_linear_system(&a, &y).is_err());
     }
+
+    #[test]
+    fn test_sample_solution_keeps_free_variables() {
+        // x0 + x2 = 1, x1 + x2 = 2: x2 is free.
+        let a = mat(vec![
+            vec![gf(1), gf(0), gf(1)],
+            vec![gf(0), gf(1), gf(1)],
+        ]);
+        let y = vec_gf(vec![gf(1), gf(2)]);
+        let r = vec_gf(vec![gf(0xA), gf(0xB), gf(5)]);
+        let x = sample_solution(&a, &y, &r).unwrap().unwrap();
+        assert_eq!(x[2], gf(5)); // Free variable taken from r
+        assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y);
+    }
+
+    #[test]
+    fn test_sample_solution_rank_deficient() {
+        // Consistent but rank 1 < 2 rows: SampleSolution rejects it.
+        let a = mat(vec![
+            vec![gf(1), gf(2)],
+            vec![gf(1), gf(2)],
+        ]);
+        let y = vec_gf(vec![gf(3), gf(3)]);
+        let r = vec_gf(vec![gf(0), gf(0)]);
+        assert_eq!(sample_solution(&a, &y, &r).unwrap(), None);
+        assert!(solve_linear_system(&a, &y).unwrap().is_some());
+
+        assert!(sample_solution(&a, &y, &vec_gf(vec![gf(0)])).is_err());
+    }
+
+    #[test]
+    fn test_sample_solution_ct_matches_sample_solution() {
+        // Random systems of various shapes, with some rows copied so that rank-deficient
+        // systems are exercised as well.
+        let mut rng = CtrDrbg::new(&[3u8; 48]);
+        let mut byte = || {
+            let mut b = [0u8; 1];
+            rng.fill_bytes(&mut b).unwrap();
+            b[0]
+        };
+        for &(rows, cols) in &[(1, 1), (2, 3), (4, 4), (5, 9), (8, 12), (20, 24), (3, 2)] {
+            for trial in 0..20 {
+                let mut data: Vec<Vec<GFElement>> = (0..rows)
+                    .map(|_| (0..cols).map(|_| gf(byte() & 0x0F)).collect())
+                    .collect();
+                if trial % 4 == 0 && rows > 1 {
+                    data[rows - 1] = data[0].clone();
+                }
+                let a = mat(data);
+                let y: GFVector = (0..rows).map(|_| gf(byte() & 0x0F)).collect();
+                let r: GFVector = (0..cols).map(|_| gf(byte() & 0x0F)).collect();
+
+                let expected = sample_solution(&a, &y, &r).unwrap();
+                assert_eq!(sample_solution_ct(&a, &y, &r).unwrap(), expected, "{}x{} trial {}", rows, cols, trial);
+                if let Some(x) = expected {
+                    assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_solution_ct_zero_leading_column() {
+        // The first pivot has to be found below the first row, and column 1 has none.
+        let a = mat(vec![
+            vec![gf(0), gf(0), gf(3), gf(1)],
+            vec![gf(5), gf(0), gf(1), gf(0)],
+            vec![gf(0), gf(0), gf(0), gf(7)],
+        ]);
+        let y = vec_gf(vec![gf(1), gf(2), gf(3)]);
+        let r = vec_gf(vec![gf(9), gf(4), gf(2), gf(8)]);
+        let x = sample_solution_ct(&a, &y, &r).unwrap().unwrap();
+        assert_eq!(x[1], gf(4));
+        assert_eq!(Some(x), sample_solution(&a, &y, &r).unwrap());
+
+        assert!(sample_solution_ct(&a, &y, &vec_gf(vec![gf(0)])).is_err());
+        assert!(sample_solution_ct(&a, &vec_gf(vec![gf(0)]), &r).is_err());
+    }
+
+    #[test]
+    fn test_solution_space() {
+        // x0 + x1 = 1 and x2 = 3 over four variables: rank 2, free variables x1 and x3.
+        let a = mat(vec![
+            vec![gf(1), gf(1), gf(0), gf(0)],
+            vec![gf(0), gf(0), gf(1), gf(0)],
+            vec![gf(1), gf(1), gf(1), gf(0)],
+        ]);
+        let y = vec_gf(vec![gf(1), gf(3), gf(2)]);
+        let space = solution_space(&a, &y).unwrap().unwrap();
+        assert_eq!(space.rank(), 2);
+        assert_eq!(space.dimension(), 2);
+        assert_eq!(space.particular(), &vec_gf(vec![gf(1), gf(0), gf(3), gf(0)]));
+        assert_eq!(space.kernel(), &[vec_gf(vec![gf(1), gf(1), gf(0), gf(0)]), vec_gf(vec![gf(0), gf(0), gf(0), gf(1)])]);
+        assert_eq!(kernel_basis(&a).unwrap(), space.kernel());
+        assert_eq!(matrix_rank(&a), 2);
+
+        let mut rng = CtrDrbg::new(&[9; 48]);
+        for _ in 0..8 {
+            let x = space.sample_with_rng(&mut rng).unwrap();
+            assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y);
+        }
+
+        // Inconsistent: the third equation contradicts the first two.
+        assert_eq!(solution_space(&a, &vec_gf(vec![gf(1), gf(3), gf(3)])).unwrap(), None);
+        // Full column rank leaves a single solution and an empty kernel.
+        let unique = solution_space(&GFMatrix::identity(3), &vec_gf(vec![gf(4), gf(5), gf(6)])).unwrap().unwrap();
+        assert_eq!(unique.dimension(), 0);
+        assert_eq!(unique.sample().unwrap(), vec_gf(vec![gf(4), gf(5), gf(6)]));
+    }
+
+    #[test]
+    fn test_sampling_covers_the_solution_space() {
+        // x0 + x1 = 0 has 16 solutions (t, t); uniform sampling should reach all of them.
+        let a = mat(vec![vec![gf(1), gf(1)]]);
+        let space = solution_space(&a, &vec_gf(vec![gf(0)])).unwrap().unwrap();
+        let mut rng = CtrDrbg::new(&[1; 48]);
+        let mut seen = [false; 16];
+        for _ in 0..400 {
+            let x = space.sample_with_rng(&mut rng).unwrap();
+            assert_eq!(x[0], x[1]);
+            seen[x[0].0 as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// A random matrix A (up to 12 x 12), a vector x and y = A x, so A x = y is solvable.
+        fn solvable_system() -> impl Strategy<Value = (GFMatrix, GFVector, GFVector)> {
+            (1usize..=12, 1usize..=12).prop_flat_map(|(rows, cols)| {
+                let element = || (0u8..16).prop_map(GFElement);
+                (
+                    proptest::collection::vec(element(), rows * cols),
+                    proptest::collection::vec(element(), cols),
+                    proptest::collection::vec(element(), cols),
+                )
+                    .prop_map(move |(data, x, r)| {
+                        let a = GFMatrix::new_with_data(rows, cols, data);
+                        let y = matrix_vec_mul(&a, &x).unwrap();
+                        (a, y, r)
+                    })
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn solvers_solve_consistent_systems((a, y, r) in solvable_system()) {
+                let x = solve_linear_system(&a, &y).unwrap().expect("system is consistent");
+                prop_assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y.clone());
+
+                // The samplers only answer when A has full row rank, both with the
+                // solution whose free variables are fixed to r.
+                let sampled = sample_solution(&a, &y, &r).unwrap();
+                prop_assert_eq!(sample_solution_ct(&a, &y, &r).unwrap(), sampled.clone());
+                if let Some(sampled) = sampled {
+                    prop_assert_eq!(matrix_vec_mul(&a, &sampled).unwrap(), y.clone());
+                }
+            }
+
+            #[test]
+            fn solution_space_is_complete((a, y, _r) in solvable_system()) {
+                let space = solution_space(&a, &y).unwrap().expect("system is consistent");
+                prop_assert_eq!(Some(space.particular().clone()), solve_linear_system(&a, &y).unwrap());
+                prop_assert_eq!(space.rank() + space.dimension(), a.num_cols());
+                prop_assert_eq!(space.rank(), matrix_rank(&a));
+                let zero = vec![GFElement(0); a.num_rows()];
+                for basis_vector in space.kernel() {
+                    prop_assert_eq!(matrix_vec_mul(&a, basis_vector).unwrap(), zero.clone());
+                }
+                if space.dimension() > 0 {
+                    // The basis vectors are linearly independent.
+                    prop_assert_eq!(matrix_rank(&mat(space.kernel().to_vec())), space.dimension());
+                }
+                let x = space.sample_with_rng(&mut CtrDrbg::new(&[5; 48])).unwrap();
+                prop_assert_eq!(matrix_vec_mul(&a, &x).unwrap(), y);
+            }
+        }
+    }
 }
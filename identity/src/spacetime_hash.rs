@@ -1,22 +1,77 @@
-//! Implements Blake2b-512 hashing for CompactSecretKey.
-use wasm_bindgen::prelude::*;
-
-use crate::types::CompactSecretKey;
+//! Blake2b-512 hashes binding identities to data and time ("spacetime" bindings).
+//!
+//! Besides `hash_compact_secret_key`, which hashes the raw secret seed, the module has a
+//! family of domain-separated commitments: to a public key, to a message under a
+//! caller-chosen domain tag, and to a public key at a timestamp. Each hash starts with
+//! its own tag, and every variable-length field is preceded by its length as an 8-byte
+//! big-endian integer, so no input of one function collides with an input of another
+//! or with a different split of the same fields.
+use crate::types::{CompactPublicKey, CompactSecretKey};
 use blake2::{Blake2b512, Digest};
 
+/// Length of every spacetime hash.
+pub const SPACETIME_HASH_BYTES: usize = 64;
+
+/// Tag of `hash_public_key`.
+pub const PUBLIC_KEY_TAG: &str = "cubix/spacetime/public-key";
+/// Tag of `hash_message`.
+pub const MESSAGE_TAG: &str = "cubix/spacetime/message";
+/// Tag of `hash_public_key_at`.
+pub const KEY_TIME_TAG: &str = "cubix/spacetime/key-time";
+
+fn update_length_prefixed(hasher: &mut Blake2b512, part: &[u8]) {
+    hasher.update((part.len() as u64).to_be_bytes());
+    hasher.update(part);
+}
+
+fn tagged_hasher(tag: &str) -> Blake2b512 {
+    let mut hasher = Blake2b512::new();
+    update_length_prefixed(&mut hasher, tag.as_bytes());
+    hasher
+}
+
+/// Commits to a compact public key, including its MAYO variant:
+/// `Blake2b-512(tag || variant_id || cpk)`.
+pub fn hash_public_key(cpk: &CompactPublicKey) -> Vec<u8> {
+    let mut hasher = tagged_hasher(PUBLIC_KEY_TAG);
+    hasher.update([cpk.params().id()]);
+    update_length_prefixed(&mut hasher, cpk.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Commits to `message` under the application's `domain` tag (e.g. "cubix/tx"), so that
+/// the same bytes hashed for two purposes give unrelated digests:
+/// `Blake2b-512(tag || domain || message)`.
+pub fn hash_message(domain: &str, message: &[u8]) -> Vec<u8> {
+    let mut hasher = tagged_hasher(MESSAGE_TAG);
+    update_length_prefixed(&mut hasher, domain.as_bytes());
+    update_length_prefixed(&mut hasher, message);
+    hasher.finalize().to_vec()
+}
+
+/// Commits to a compact public key at a point in time, such as the moment an identity
+/// is registered: `Blake2b-512(tag || variant_id || cpk || timestamp)`, with the
+/// timestamp as an 8-byte big-endian integer (Unix seconds by convention).
+pub fn hash_public_key_at(cpk: &CompactPublicKey, timestamp: u64) -> Vec<u8> {
+    let mut hasher = tagged_hasher(KEY_TIME_TAG);
+    hasher.update([cpk.params().id()]);
+    update_length_prefixed(&mut hasher, cpk.as_bytes());
+    hasher.update(timestamp.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
 /// Hashes a CompactSecretKey (which is a seedsk) using Blake2b-512.
 /// Returns a 64-byte hash.
-#[wasm_bindgen]
 pub fn hash_compact_secret_key(csk: &CompactSecretKey) -> Vec<u8> {
     let mut hasher = Blake2b512::new();
-    hasher.update(&csk.0); // csk.0 is Vec<u8> representing seedsk
+    hasher.update(csk.as_bytes()); // the csk bytes are the seedsk
     hasher.finalize().to_vec()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::params::MayoParams; // To use for keypair generation
+    use crate::params::MayoParams; // To use for keypair generation and fixed keys
     use crate::keygen::compact_key_gen; // To generate a csk
 
     #[test]
@@ -44,11 +99,11 @@ mod tests {
         
         // Ensure csk1 and csk2 are actually different before hashing
         // This is highly probable due to random seed generation.
-        if csk1.0 == csk2.0 {
+        if csk1.as_bytes() == csk2.as_bytes() {
             // In the extremely unlikely event of a seed collision, try one more time.
             // This is mostly to make the test robust against theoretical collisions.
             let (csk_temp, _) = compact_key_gen(&params_mayo1).unwrap();
-            if csk_temp.0 != csk1.0 {
+            if csk_temp.as_bytes() != csk1.as_bytes() {
                  // Use csk_temp if it's different
                  let hash2 = hash_compact_secret_key(&csk_temp);
                  assert_ne!(hash1, hash2, "Hashes of different CSKs should be different (after retry)");
@@ -58,7 +113,7 @@ mod tests {
                 // For this test, we'll assume getrandom provides different seeds.
                 // If this part of the test is flaky, it points to issues in keygen or randomness.
                 let hash2 = hash_compact_secret_key(&csk2); // Proceed with potentially same csk2
-                if csk1.0 != csk2.0 { // Only assert if they were truly different
+                if csk1.as_bytes() != csk2.as_bytes() { // Only assert if they were truly different
                     assert_ne!(hash1, hash2, "Hashes of different CSKs should be different");
                 } else {
                     // If they are identical after two attempts, we can't test the "different hash" property here.
@@ -75,9 +130,31 @@ mod tests {
         // Test with a fixed known CSK to ensure deterministic output if needed,
         // but this requires a known hash value. For now, properties are tested.
         let fixed_csk_data = vec![0u8; params_mayo1.sk_seed_bytes()]; // e.g., all zeros
-        let fixed_csk = CompactSecretKey(fixed_csk_data);
-        let _fixed_hash = hash_compact_secret_key(&fixed_csk);
-        // To make this a strong assertion, one would need to precompute the hash of `fixed_csk_data`.
-        // e.g. assert_eq!(fixed_hash, PRECOMPUTED_HASH_OF_ZEROS_SK_SEED_BYTES);
+        let fixed_csk = CompactSecretKey::from_bytes(params_mayo1, fixed_csk_data).unwrap();
+        let fixed_hash = hash_compact_secret_key(&fixed_csk);
+        assert_eq!(hex::encode(fixed_hash), "134e54b6daa72b1d976dcfa0351a34d82bc778178bf29a505e729e4c260a07b12404bfd53184ebf541c0a2fa93a4bed76bd0e3b868907c6a0fc3d732b9bb0773");
+    }
+
+    #[test]
+    fn test_domain_separated_vectors() {
+        // Computed independently with Python's hashlib.blake2b(digest_size=64).
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![7u8; MayoParams::mayo1().cpk_bytes()]).unwrap();
+        assert_eq!(hex::encode(hash_public_key(&cpk)),
+                   "b3cefc58077f57c2019c2c19373a931221854bd72069f28eea069a3a4eff2ec29d7b03bdd6fddb3942e120c1eae5e2addba53b00668cab4075a3c2201a608794");
+        assert_eq!(hex::encode(hash_message("cubix/tx", b"hello")),
+                   "74b32c5b001f84d83d3d374a66ee4168b2afb0eb43440d2e77c6345f59ce27e336a4e0bdc7838477e143350ac71d2d0f1842459caf439a15eaa187d8698b1670");
+        assert_eq!(hex::encode(hash_public_key_at(&cpk, 1_700_000_000)),
+                   "996d2c730f3e8decdfe6fb7f08cf65842ec622f57d5f6ae8bebc5433be9409a35aef13d53e98830de0273943d2b71973b2b56a73ae634e40dcd67c2593f5b286");
+    }
+
+    #[test]
+    fn test_domains_and_fields_are_separated() {
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![7u8; MayoParams::mayo1().cpk_bytes()]).unwrap();
+        assert_eq!(hash_public_key(&cpk).len(), SPACETIME_HASH_BYTES);
+        assert_ne!(hash_public_key_at(&cpk, 1), hash_public_key_at(&cpk, 2));
+        assert_ne!(hash_message("cubix/tx", b"hello"), hash_message("cubix/block", b"hello"));
+        // Moving bytes between the domain and the message changes the hash.
+        assert_ne!(hash_message("cubix/tx", b"hello"), hash_message("cubix/txh", b"ello"));
+        assert_ne!(hash_message(PUBLIC_KEY_TAG, cpk.as_bytes()), hash_public_key(&cpk));
     }
 }
@@ -0,0 +1,154 @@
+//! Incremental signing and verification of messages supplied in chunks.
+//!
+//! MAYO only reads the message through M_digest = SHAKE256(M), so a `StreamingSigner` or
+//! `StreamingVerifier` absorbs chunks into a `MessageHasher` and, when finalized, hands
+//! the digest to the `SigningKey` or `VerifyingKey` it borrows, which runs the rest of
+//! MAYO.Sign or MAYO.Verify. The signatures are ordinary MAYO signatures of the
+//! concatenated chunks, and one decoded key can stream any number of messages. Both types implement `std::io::Write`, so
+//! a file or socket can be signed with `std::io::copy` without buffering it.
+
+use std::io;
+
+use crate::error::IdentityError;
+use crate::hash::MessageHasher;
+use crate::rng::{OsRandom, RandomSource};
+use crate::sign::SigningKey;
+use crate::types::Signature;
+use crate::verify::VerifyingKey;
+
+/// Signs a message fed through `update`.
+pub struct StreamingSigner<'a> {
+    key: &'a SigningKey,
+    hasher: MessageHasher,
+}
+
+impl<'a> StreamingSigner<'a> {
+    pub fn new(key: &'a SigningKey) -> Self {
+        StreamingSigner { key, hasher: MessageHasher::new(key.params()) }
+    }
+
+    /// Appends `chunk` to the message.
+    pub fn update(&mut self, chunk: &[u8]) -> &mut Self {
+        self.hasher.update(chunk);
+        self
+    }
+
+    /// Signs everything passed to `update`.
+    pub fn finalize(self) -> Result<Signature, IdentityError> {
+        self.finalize_with_rng(&mut OsRandom)
+    }
+
+    /// `finalize` drawing the salt randomizer from `rng`.
+    pub fn finalize_with_rng<R: RandomSource>(self, rng: &mut R) -> Result<Signature, IdentityError> {
+        self.key.sign_digest_with_rng(&self.hasher.finalize(), rng)
+    }
+}
+
+impl io::Write for StreamingSigner<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Verifies a signature on a message fed through `update`.
+pub struct StreamingVerifier<'a> {
+    key: &'a VerifyingKey,
+    hasher: MessageHasher,
+}
+
+impl<'a> StreamingVerifier<'a> {
+    pub fn new(key: &'a VerifyingKey) -> Self {
+        StreamingVerifier { key, hasher: MessageHasher::new(key.params()) }
+    }
+
+    /// Appends `chunk` to the message.
+    pub fn update(&mut self, chunk: &[u8]) -> &mut Self {
+        self.hasher.update(chunk);
+        self
+    }
+
+    /// Checks `signature` against everything passed to `update`.
+    pub fn finalize(self, signature: &Signature) -> Result<bool, IdentityError> {
+        self.key.verify_digest(&self.hasher.finalize(), signature)
+    }
+}
+
+impl io::Write for StreamingVerifier<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::shake256_digest;
+    use crate::keygen::{compact_key_gen, expand_pk, expand_sk};
+    use crate::params::MayoParams;
+    use crate::rng::CtrDrbg;
+    use crate::sign::{sign_digest, sign_message_with_rng};
+    use crate::types::{ExpandedPublicKey, ExpandedSecretKey, Message, MessageDigest};
+    use crate::verify::{verify_digest, verify_signature};
+
+    fn keys(params: &MayoParams) -> (ExpandedSecretKey, ExpandedPublicKey) {
+        let (csk, cpk) = compact_key_gen(params).unwrap();
+        (expand_sk(&csk).unwrap(), expand_pk(&cpk).unwrap())
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot() {
+        let (esk, epk) = keys(&MayoParams::mayo1());
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * 31 % 251) as u8).collect();
+
+        // Chunked signing with a fixed rng gives the one-shot signature bit for bit.
+        let signing_key = SigningKey::from_expanded(&esk).unwrap();
+        let mut signer = StreamingSigner::new(&signing_key);
+        for chunk in data.chunks(777) {
+            signer.update(chunk);
+        }
+        let streamed = signer.finalize_with_rng(&mut CtrDrbg::new(&[3; 48])).unwrap();
+        let one_shot = sign_message_with_rng(&esk, &Message(data.clone()), &mut CtrDrbg::new(&[3; 48])).unwrap();
+        assert_eq!(streamed, one_shot);
+        assert!(verify_signature(&epk, &Message(data.clone()), &streamed).unwrap());
+
+        let verifying_key = VerifyingKey::from_expanded(&epk).unwrap();
+        let mut verifier = StreamingVerifier::new(&verifying_key);
+        io::copy(&mut &data[..], &mut verifier).unwrap();
+        assert!(verifier.finalize(&streamed).unwrap());
+
+        // The same decoded keys stream further messages.
+        let mut verifier = StreamingVerifier::new(&verifying_key);
+        verifier.update(&data[..9_999]);
+        assert!(!verifier.finalize(&streamed).unwrap());
+        let mut signer = StreamingSigner::new(&signing_key);
+        signer.update(b"second message");
+        let second = signer.finalize().unwrap();
+        assert!(verifying_key.verify(&Message(b"second message".to_vec()), &second).unwrap());
+    }
+
+    #[test]
+    fn test_prehashed_signing() {
+        let params = MayoParams::mayo2();
+        let (esk, epk) = keys(&params);
+        let message = Message(b"cube face hash".to_vec());
+        let digest = shake256_digest(&message.0, &params);
+
+        let signature = sign_digest(&esk, &digest).unwrap();
+        assert!(verify_signature(&epk, &message, &signature).unwrap());
+        assert!(verify_digest(&epk, &digest, &signature).unwrap());
+
+        let short = MessageDigest(digest.0[1..].to_vec());
+        assert_eq!(sign_digest(&esk, &short), Err(IdentityError::InvalidLength("Message digest has incorrect length")));
+        assert_eq!(verify_digest(&epk, &short, &signature), Err(IdentityError::InvalidLength("Message digest has incorrect length")));
+    }
+}
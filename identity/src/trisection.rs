@@ -0,0 +1,524 @@
+//! The geometric identification protocol of the graypaper (section 6, Identity), over
+//! a discrete group.
+//!
+//! In the graypaper Alice's identity is a secret angle X_A and the public angle is its
+//! triple Y_A = 3·X_A: tripling an angle is a ruler-and-compass construction, while
+//! trisecting an arbitrary one is impossible. Here angles are scalars mod ℓ, and
+//! "tripling" is the map X ↦ X·B onto ristretto255 (RFC 9496), the prime-order group of
+//! order ℓ ≈ 2^252 built on Curve25519, with base point B. Like tripling it turns the
+//! addition of angles into the group operation, and inverting it is the discrete
+//! logarithm problem. The group arithmetic is that of `curve25519-dalek`, whose scalar
+//! multiplications run in constant time. One round runs as follows:
+//!
+//! 1. Alice picks a random angle K and sends the commitment R = K·B.
+//! 2. Bob flips a coin.
+//! 3. On heads Alice reveals K and Bob checks K·B = R. On tails Alice reveals
+//!    L = K + X_A and Bob checks L·B = R + Y_A.
+//!
+//! Bob accepts after t successful rounds. A prover who does not know X_A can prepare for
+//! only one of the two coins, so passes with probability 2^-t; the revealed angles
+//! are uniformly random, so the transcript tells Bob nothing about X_A.
+//!
+//! `Prover` and `Verifier` run the interactive protocol one message at a time. `prove`
+//! and `verify_proof` make it non-interactive with the Fiat–Shamir transform: the coins
+//! are derived from the public angle, a caller-chosen context and all commitments (see
+//! `hash::shake256_trisection_coins`). Since a forger can retry offline, a
+//! non-interactive proof needs t = 128 rounds (`DEFAULT_ROUNDS`) for 128-bit security.
+//!
+//! Group elements travel as 32-byte Ristretto encodings. Decoding accepts only the
+//! canonical encoding of a group element, and the group has prime order, so a decoded
+//! public angle or commitment can never lie in a small subgroup.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::error::IdentityError;
+use crate::hash::shake256_trisection_coins;
+use crate::rng::{OsRandom, RandomSource};
+
+/// Number of rounds t for a soundness error of 2^-128.
+pub const DEFAULT_ROUNDS: usize = 128;
+
+/// Length of an encoded angle or group element.
+pub const ELEMENT_BYTES: usize = 32;
+
+/// Length of one round of an encoded `Proof`: a commitment followed by a response.
+pub const ROUND_BYTES: usize = 2 * ELEMENT_BYTES;
+
+/// Draws a uniformly random angle from `rng`, reducing 64 bytes mod ℓ.
+fn random_scalar<R: RandomSource>(rng: &mut R) -> Result<Scalar, IdentityError> {
+    let mut wide = Zeroizing::new([0u8; 2 * ELEMENT_BYTES]);
+    rng.fill_bytes(&mut *wide)?;
+    Ok(Scalar::from_bytes_mod_order_wide(&wide))
+}
+
+/// Decodes a 32-byte little-endian angle, which must be below ℓ.
+fn decode_scalar(bytes: &[u8]) -> Result<Scalar, IdentityError> {
+    let bytes: [u8; ELEMENT_BYTES] = bytes.try_into().map_err(|_| IdentityError::InvalidLength("Angle must be 32 bytes long"))?;
+    Option::from(Scalar::from_canonical_bytes(bytes)).ok_or(IdentityError::InvalidEncoding("Angle is not reduced modulo the group order"))
+}
+
+/// Decodes a Ristretto group element, rejecting every non-canonical encoding.
+fn decode_point(bytes: &[u8]) -> Result<RistrettoPoint, IdentityError> {
+    CompressedRistretto::from_slice(bytes)
+        .map_err(|_| IdentityError::InvalidLength("Group element must be 32 bytes long"))?
+        .decompress()
+        .ok_or(IdentityError::InvalidEncoding("Group element is not a canonical Ristretto encoding"))
+}
+
+/// The secret angle X_A; wiped on drop and redacted in `Debug` output.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretAngle(Scalar);
+
+impl fmt::Debug for SecretAngle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretAngle([REDACTED])")
+    }
+}
+
+impl SecretAngle {
+    /// Returns the public angle Y_A = X_A·B.
+    pub fn public_angle(&self) -> PublicAngle {
+        PublicAngle(RistrettoPoint::mul_base(&self.0))
+    }
+
+    pub fn to_bytes(&self) -> [u8; ELEMENT_BYTES] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        decode_scalar(bytes).map(SecretAngle)
+    }
+}
+
+/// The public angle Y_A, the "triple" of the secret angle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicAngle(RistrettoPoint);
+
+impl PublicAngle {
+    pub fn to_bytes(&self) -> [u8; ELEMENT_BYTES] {
+        self.0.compress().to_bytes()
+    }
+
+    /// Decodes a public angle from its canonical Ristretto encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        decode_point(bytes).map(PublicAngle)
+    }
+}
+
+impl Hash for PublicAngle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+/// Generates a secret angle and its public angle from the operating system RNG.
+pub fn generate_identity() -> Result<(SecretAngle, PublicAngle), IdentityError> {
+    generate_identity_with_rng(&mut OsRandom)
+}
+
+/// Generates a secret angle and its public angle, drawing the secret from `rng`.
+pub fn generate_identity_with_rng<R: RandomSource>(rng: &mut R) -> Result<(SecretAngle, PublicAngle), IdentityError> {
+    let secret = SecretAngle(random_scalar(rng)?);
+    let public = secret.public_angle();
+    Ok((secret, public))
+}
+
+/// The verifier's coin flip of one round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Challenge {
+    /// Reveal K.
+    Heads,
+    /// Reveal K + X_A.
+    Tails,
+}
+
+impl Challenge {
+    fn from_bit(bit: bool) -> Self {
+        if bit { Challenge::Tails } else { Challenge::Heads }
+    }
+}
+
+/// The prover's first message of a round, R = K·B.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment(RistrettoPoint);
+
+impl Commitment {
+    pub fn to_bytes(&self) -> [u8; ELEMENT_BYTES] {
+        self.0.compress().to_bytes()
+    }
+
+    /// Decodes a commitment from its canonical Ristretto encoding, which is always an
+    /// element of the prime-order group.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        decode_point(bytes).map(Commitment)
+    }
+}
+
+impl Hash for Commitment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+/// The prover's answer to a challenge: K on heads, K + X_A on tails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response(Scalar);
+
+impl Response {
+    pub fn to_bytes(&self) -> [u8; ELEMENT_BYTES] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        decode_scalar(bytes).map(Response)
+    }
+}
+
+/// Returns true if `response` answers `challenge` for `commitment` under `public`.
+fn check_round(public: &PublicAngle, commitment: &Commitment, challenge: Challenge, response: &Response) -> bool {
+    let lhs = RistrettoPoint::mul_base(&response.0);
+    match challenge {
+        Challenge::Heads => lhs == commitment.0,
+        Challenge::Tails => lhs == commitment.0 + public.0,
+    }
+}
+
+fn check_round_count(rounds: usize) -> Result<(), IdentityError> {
+    if rounds == 0 {
+        return Err(IdentityError::InvalidInput("The protocol needs at least one round"));
+    }
+    Ok(())
+}
+
+/// Alice's side of the interactive protocol: `commit` and `respond` alternate for each
+/// of the t rounds.
+#[derive(Debug)]
+pub struct Prover<'a> {
+    secret: &'a SecretAngle,
+    rounds: usize,
+    completed: usize,
+    nonce: Option<Zeroizing<Scalar>>,
+}
+
+impl<'a> Prover<'a> {
+    pub fn new(secret: &'a SecretAngle, rounds: usize) -> Result<Self, IdentityError> {
+        check_round_count(rounds)?;
+        Ok(Prover { secret, rounds, completed: 0, nonce: None })
+    }
+
+    /// Starts the next round: picks a fresh random angle K and returns R = K·B.
+    pub fn commit<R: RandomSource>(&mut self, rng: &mut R) -> Result<Commitment, IdentityError> {
+        if self.nonce.is_some() || self.is_finished() {
+            return Err(IdentityError::InvalidInput("Prover cannot commit in the current protocol state"));
+        }
+        let nonce = Zeroizing::new(random_scalar(rng)?);
+        let commitment = Commitment(RistrettoPoint::mul_base(&nonce));
+        self.nonce = Some(nonce);
+        Ok(commitment)
+    }
+
+    /// Answers the challenge of the current round. K is used for this round only.
+    pub fn respond(&mut self, challenge: Challenge) -> Result<Response, IdentityError> {
+        let nonce = self.nonce.take().ok_or(IdentityError::InvalidInput("Prover must commit before responding"))?;
+        self.completed += 1;
+        Ok(Response(match challenge {
+            Challenge::Heads => *nonce,
+            Challenge::Tails => *nonce + self.secret.0,
+        }))
+    }
+
+    /// Returns true once all t rounds have been answered.
+    pub fn is_finished(&self) -> bool {
+        self.completed == self.rounds
+    }
+}
+
+/// Progress of a `Verifier`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifierStatus {
+    /// Rounds remain to be played.
+    InProgress,
+    /// All t rounds passed: the prover knows the secret angle.
+    Accepted,
+    /// A round failed; the session is over.
+    Rejected,
+}
+
+/// Bob's side of the interactive protocol: `challenge` answers each commitment with a
+/// coin flip, and `check` verifies the response.
+#[derive(Debug)]
+pub struct Verifier {
+    public: PublicAngle,
+    rounds: usize,
+    passed: usize,
+    pending: Option<(Commitment, Challenge)>,
+    status: VerifierStatus,
+}
+
+impl Verifier {
+    pub fn new(public: PublicAngle, rounds: usize) -> Result<Self, IdentityError> {
+        check_round_count(rounds)?;
+        Ok(Verifier { public, rounds, passed: 0, pending: None, status: VerifierStatus::InProgress })
+    }
+
+    /// Records the commitment of the next round and flips a coin with `rng`.
+    pub fn challenge<R: RandomSource>(&mut self, commitment: Commitment, rng: &mut R) -> Result<Challenge, IdentityError> {
+        if self.pending.is_some() || self.status != VerifierStatus::InProgress {
+            return Err(IdentityError::InvalidInput("Verifier cannot accept a commitment in the current protocol state"));
+        }
+        let mut coin = [0u8; 1];
+        rng.fill_bytes(&mut coin)?;
+        let challenge = Challenge::from_bit(coin[0] & 1 == 1);
+        self.pending = Some((commitment, challenge));
+        Ok(challenge)
+    }
+
+    /// Checks the response to the current round and returns the new status.
+    pub fn check(&mut self, response: &Response) -> Result<VerifierStatus, IdentityError> {
+        let (commitment, challenge) =
+            self.pending.take().ok_or(IdentityError::InvalidInput("Verifier has no challenge awaiting a response"))?;
+        if !check_round(&self.public, &commitment, challenge, response) {
+            self.status = VerifierStatus::Rejected;
+        } else {
+            self.passed += 1;
+            if self.passed == self.rounds {
+                self.status = VerifierStatus::Accepted;
+            }
+        }
+        Ok(self.status)
+    }
+
+    pub fn status(&self) -> VerifierStatus {
+        self.status
+    }
+}
+
+/// A non-interactive proof of knowledge of a secret angle: the commitment and response
+/// of every round, with the coins derived by Fiat–Shamir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    commitments: Vec<Commitment>,
+    responses: Vec<Response>,
+}
+
+impl Proof {
+    /// Returns the number of rounds t of the proof.
+    pub fn rounds(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// Encodes the proof as `commitment || response` for each round, `ROUND_BYTES` per round.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.rounds() * ROUND_BYTES);
+        for (commitment, response) in self.commitments.iter().zip(&self.responses) {
+            bytes.extend_from_slice(&commitment.to_bytes());
+            bytes.extend_from_slice(&response.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IdentityError> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(ROUND_BYTES) {
+            return Err(IdentityError::InvalidLength("Proof length is not a positive multiple of the round length"));
+        }
+        let mut proof = Proof { commitments: Vec::new(), responses: Vec::new() };
+        for round in bytes.chunks_exact(ROUND_BYTES) {
+            let (commitment, response) = round.split_at(ELEMENT_BYTES);
+            proof.commitments.push(Commitment::from_bytes(commitment)?);
+            proof.responses.push(Response::from_bytes(response)?);
+        }
+        Ok(proof)
+    }
+}
+
+/// The Fiat–Shamir coins of a proof: `public || len(context) || context || commitments`.
+fn fiat_shamir_challenges(public: &PublicAngle, context: &[u8], commitments: &[Commitment]) -> Vec<Challenge> {
+    let mut transcript = Vec::with_capacity(ELEMENT_BYTES * (1 + commitments.len()) + 4 + context.len());
+    transcript.extend_from_slice(&public.to_bytes());
+    transcript.extend_from_slice(&(context.len() as u32).to_be_bytes());
+    transcript.extend_from_slice(context);
+    for commitment in commitments {
+        transcript.extend_from_slice(&commitment.to_bytes());
+    }
+    shake256_trisection_coins(&transcript, commitments.len()).into_iter().map(Challenge::from_bit).collect()
+}
+
+/// Proves knowledge of `secret` in `rounds` rounds, bound to `context` (e.g. a session
+/// id or the message being authenticated), with commitments drawn from `rng`.
+pub fn prove<R: RandomSource>(secret: &SecretAngle, context: &[u8], rounds: usize, rng: &mut R) -> Result<Proof, IdentityError> {
+    check_round_count(rounds)?;
+    let public = secret.public_angle();
+    let nonces = Zeroizing::new((0..rounds).map(|_| random_scalar(rng)).collect::<Result<Vec<Scalar>, _>>()?);
+    let commitments: Vec<Commitment> = nonces.iter().map(|nonce| Commitment(RistrettoPoint::mul_base(nonce))).collect();
+    let responses = fiat_shamir_challenges(&public, context, &commitments)
+        .into_iter()
+        .zip(nonces.iter())
+        .map(|(challenge, nonce)| {
+            Response(match challenge {
+                Challenge::Heads => *nonce,
+                Challenge::Tails => nonce + secret.0,
+            })
+        })
+        .collect();
+    Ok(Proof { commitments, responses })
+}
+
+/// Verifies a non-interactive proof for `public` and `context`. Proofs with a round
+/// count other than `rounds` are rejected, so that a prover cannot pick a weaker t.
+pub fn verify_proof(public: &PublicAngle, context: &[u8], proof: &Proof, rounds: usize) -> bool {
+    if rounds == 0 || proof.rounds() != rounds || proof.responses.len() != rounds {
+        return false;
+    }
+    fiat_shamir_challenges(public, context, &proof.commitments)
+        .into_iter()
+        .zip(proof.commitments.iter().zip(&proof.responses))
+        .all(|(challenge, (commitment, response))| check_round(public, commitment, challenge, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::CtrDrbg;
+
+    /// Coin source that replays fixed bytes, to script the verifier's flips.
+    struct ScriptedCoins(std::vec::IntoIter<u8>);
+
+    impl RandomSource for ScriptedCoins {
+        fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), IdentityError> {
+            for byte in dest {
+                *byte = self.0.next().ok_or(IdentityError::RngFailure)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn identity(seed: u8) -> (SecretAngle, PublicAngle, CtrDrbg) {
+        let mut rng = CtrDrbg::new(&[seed; 48]);
+        let (secret, public) = generate_identity_with_rng(&mut rng).unwrap();
+        (secret, public, rng)
+    }
+
+    #[test]
+    fn test_interactive_protocol_accepts_honest_prover() {
+        let (secret, public, mut rng) = identity(1);
+        let mut prover = Prover::new(&secret, 4).unwrap();
+        let mut verifier = Verifier::new(public, 4).unwrap();
+        // Both coins occur among the four rounds.
+        let mut coins = ScriptedCoins(vec![0, 1, 1, 0].into_iter());
+        let mut status = VerifierStatus::InProgress;
+        while !prover.is_finished() {
+            let commitment = prover.commit(&mut rng).unwrap();
+            let challenge = verifier.challenge(commitment, &mut coins).unwrap();
+            status = verifier.check(&prover.respond(challenge).unwrap()).unwrap();
+        }
+        assert_eq!(status, VerifierStatus::Accepted);
+        assert_eq!(verifier.status(), VerifierStatus::Accepted);
+
+        // Messages out of order are refused.
+        assert!(prover.commit(&mut rng).is_err());
+        assert!(prover.respond(Challenge::Heads).is_err());
+        assert!(verifier.check(&Response(random_scalar(&mut rng).unwrap())).is_err());
+        assert!(Prover::new(&secret, 0).is_err());
+    }
+
+    #[test]
+    fn test_soundness_error_is_two_to_the_minus_t() {
+        // A prover without X_A prepares each round for a guessed coin: for heads it
+        // commits R = K·B and reveals K, for tails R = L·B - Y_A and reveals L. A round
+        // passes exactly when the coin matches the guess, so of the 2^t possible coin
+        // sequences exactly one gets the cheater through all t rounds.
+        const ROUNDS: usize = 3;
+        let (_secret, public, mut rng) = identity(2);
+        let public_inverse = -public.0;
+        let guesses = [Challenge::Tails, Challenge::Heads, Challenge::Tails];
+
+        let mut accepted = 0;
+        for coins in 0..1u8 << ROUNDS {
+            let mut verifier = Verifier::new(public, ROUNDS).unwrap();
+            let mut flips = ScriptedCoins((0..ROUNDS).map(|i| (coins >> i) & 1).collect::<Vec<u8>>().into_iter());
+            for guess in guesses {
+                let answer = random_scalar(&mut rng).unwrap();
+                let commitment = match guess {
+                    Challenge::Heads => Commitment(RistrettoPoint::mul_base(&answer)),
+                    Challenge::Tails => Commitment(RistrettoPoint::mul_base(&answer) + public_inverse),
+                };
+                verifier.challenge(commitment, &mut flips).unwrap();
+                if verifier.check(&Response(answer)).unwrap() != VerifierStatus::InProgress {
+                    break;
+                }
+            }
+            if verifier.status() == VerifierStatus::Accepted {
+                accepted += 1;
+                assert_eq!(coins, 0b101, "only the coins matching the guesses may pass");
+            }
+        }
+        assert_eq!(accepted, 1);
+    }
+
+    #[test]
+    fn test_non_interactive_proof() {
+        let (secret, public, mut rng) = identity(3);
+        let proof = prove(&secret, b"session 42", 6, &mut rng).unwrap();
+        assert_eq!(proof.rounds(), 6);
+        assert!(verify_proof(&public, b"session 42", &proof, 6));
+
+        let decoded = Proof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify_proof(&public, b"session 42", &decoded, 6));
+
+        // Wrong context, key or round count, and altered rounds, are rejected.
+        assert!(!verify_proof(&public, b"session 43", &proof, 6));
+        assert!(!verify_proof(&identity(4).1, b"session 42", &proof, 6));
+        assert!(!verify_proof(&public, b"session 42", &proof, 5));
+        let mut tampered = proof.clone();
+        tampered.responses.swap(0, 1);
+        assert!(!verify_proof(&public, b"session 42", &tampered, 6));
+        let mut truncated = proof.clone();
+        truncated.commitments.pop();
+        truncated.responses.pop();
+        assert!(!verify_proof(&public, b"session 42", &truncated, 5));
+
+        assert!(Proof::from_bytes(&[]).is_err());
+        assert!(Proof::from_bytes(&proof.to_bytes()[1..]).is_err());
+    }
+
+    #[test]
+    fn test_angle_encoding() {
+        let (secret, public, _) = identity(5);
+        assert_eq!(PublicAngle::from_bytes(&public.to_bytes()).unwrap(), public);
+        assert_eq!(SecretAngle::from_bytes(&secret.to_bytes()).unwrap(), secret);
+        assert!(!format!("{:?}", secret).contains(&hex::encode(&secret.to_bytes()[..8])));
+
+        // B and 2·B encode as in the test vectors of RFC 9496, section A.1.
+        let base = SecretAngle(Scalar::ONE).public_angle();
+        assert_eq!(hex::encode(base.to_bytes()), "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76");
+        let double = SecretAngle(Scalar::from(2u8)).public_angle();
+        assert_eq!(hex::encode(double.to_bytes()), "6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919");
+
+        // Non-canonical encodings, including those of small-order curve points, are
+        // rejected, so no decoded element lies outside the prime-order group.
+        let bad_encodings = [
+            // A field element that is not reduced mod 2^255 - 19.
+            "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            // A negative field element.
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            // Not a square root of any valid Ristretto input.
+            "26948d35ca62e643e26a83177332e6b6afeb9d08e4268b650f1f5bbd8d81d371",
+        ];
+        for bad in bad_encodings {
+            let bytes = hex::decode(bad).unwrap();
+            let error = Some(IdentityError::InvalidEncoding("Group element is not a canonical Ristretto encoding"));
+            assert_eq!(PublicAngle::from_bytes(&bytes).err(), error);
+            assert_eq!(Commitment::from_bytes(&bytes).err(), error);
+        }
+        assert_eq!(Commitment::from_bytes(&[0u8; 31]), Err(IdentityError::InvalidLength("Group element must be 32 bytes long")));
+        // Angles must be reduced mod ℓ.
+        assert_eq!(Response::from_bytes(&[0xff; ELEMENT_BYTES]),
+                   Err(IdentityError::InvalidEncoding("Angle is not reduced modulo the group order")));
+    }
+}
@@ -1,16 +1,18 @@
-use wasm_bindgen::prelude::*;
-// use crate::params::MayoParams; // Removed as per compiler warning
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::error::IdentityError;
+use crate::params::MayoParams;
 
 // Field element for GF(16), represented as a nibble in a u8.
 // The actual value should be in the lower 4 bits.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Zeroize)]
 pub struct GFElement(pub u8);
 
 // Vector of field elements.
 pub type GFVector = Vec<GFElement>;
 
 // Matrix of field elements (row-major storage).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Zeroize)]
 pub struct GFMatrix {
     pub data: Vec<GFElement>,
     pub rows: usize,
@@ -43,100 +45,199 @@ impl GFMatrix {
     }
 }
 
+// Seeds are internal to key generation and always sized by the caller from MayoParams.
 
-// Cryptographic types - currently Vec<u8> wrappers.
-// TODO: Once MayoParams are finalized, these could become fixed-size arrays [u8; N]
-// or structs that enforce byte length constraints based on MayoParams.
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The secret key seed; wiped on drop and redacted in `Debug` output.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
 pub struct SeedSK(pub Vec<u8>);
 
+impl fmt::Debug for SeedSK {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeedSK([REDACTED; {}])", self.0.len())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SeedPK(pub Vec<u8>);
 
-/// CompactSecretKey is typically the same as SeedSK.
-#[wasm_bindgen(getter_with_clone)]
-#[derive(Debug, Clone, PartialEq, Eq)] // Removed Copy
-pub struct CompactSecretKey(pub Vec<u8>); // Represents SeedSK
+// Key and signature types carry the MAYO variant they belong to and can only be built
+// with the byte length that variant prescribes, so a MAYO1 key cannot reach a MAYO2
+// computation. Secret key types wipe their bytes on drop and redact them in `Debug`.
+
+/// Shared constructors and conversions for the variant-tagged byte types.
+macro_rules! impl_variant_bytes {
+    ($ty:ident, $len:ident, $len_err:literal) => {
+        impl $ty {
+            /// Wraps `bytes` for the given variant, checking the length it prescribes.
+            pub fn from_bytes(params: MayoParams, mut bytes: Vec<u8>) -> Result<Self, IdentityError> {
+                if bytes.len() != params.$len() {
+                    // The rejected bytes may still be key material.
+                    bytes.zeroize();
+                    return Err(IdentityError::InvalidLength($len_err));
+                }
+                Ok(Self { params, bytes })
+            }
+
+            /// The MAYO variant this value belongs to.
+            pub fn params(&self) -> &MayoParams {
+                &self.params
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.bytes
+            }
+
+            /// Moves the bytes out; for secret types the caller takes over wiping them.
+            pub fn into_bytes(mut self) -> Vec<u8> {
+                std::mem::take(&mut self.bytes)
+            }
+        }
 
-#[wasm_bindgen]
-impl CompactSecretKey {
-    #[wasm_bindgen(constructor)]
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self(bytes)
-    }
+        impl AsRef<[u8]> for $ty {
+            fn as_ref(&self) -> &[u8] {
+                &self.bytes
+            }
+        }
 
-    pub fn get_bytes(&self) -> Vec<u8> {
-        self.0.clone()
-    }
+        /// Infers the variant from the length; fails if no variant, or more than one,
+        /// uses that length (e.g. MAYO1 and MAYO2 compact secret keys are both 24 bytes).
+        impl TryFrom<&[u8]> for $ty {
+            type Error = IdentityError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                let mut candidates = MayoParams::all().into_iter().filter(|p| p.$len() == bytes.len());
+                match (candidates.next(), candidates.next()) {
+                    (Some(params), None) => Self::from_bytes(params, bytes.to_vec()),
+                    (Some(_), Some(_)) => Err(IdentityError::AmbiguousVariant),
+                    (None, _) => Err(IdentityError::InvalidLength($len_err)),
+                }
+            }
+        }
+    };
 }
 
-/// CompactPublicKey typically contains SeedPK and a representation of P3 (or its hash).
-#[wasm_bindgen(getter_with_clone)]
-#[derive(Debug, Clone, PartialEq, Eq)] // Removed Copy
-pub struct CompactPublicKey(pub Vec<u8>); // Represents SeedPK || P3_bytes or similar
-
-#[wasm_bindgen]
-impl CompactPublicKey {
-    #[wasm_bindgen(constructor)]
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self(bytes)
-    }
+/// Debug output for secret key types that names the variant but not the bytes.
+macro_rules! impl_redacted_debug {
+    ($ty:ident) => {
+        impl fmt::Debug for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($ty))
+                    .field("variant", &self.params.name())
+                    .field("bytes", &format_args!("[REDACTED; {}]", self.bytes.len()))
+                    .finish()
+            }
+        }
+    };
+}
 
-    pub fn get_bytes(&self) -> Vec<u8> {
-        self.0.clone()
-    }
+/// CompactSecretKey is the secret key seed (seedsk).
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct CompactSecretKey {
+    #[zeroize(skip)]
+    params: MayoParams,
+    bytes: Vec<u8>,
 }
+impl_redacted_debug!(CompactSecretKey);
+impl_variant_bytes!(CompactSecretKey, csk_bytes, "Compact secret key has incorrect length");
 
-/// ExpandedSecretKey contains the full secret key components derived from SeedSK.
-/// This would include S, P1, P2, P3 (or their components).
-#[derive(Debug, Clone, PartialEq, Eq)] // Ensure no Copy
-pub struct ExpandedSecretKey(pub Vec<u8>);
+/// CompactPublicKey is SeedPK || P3_bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactPublicKey {
+    params: MayoParams,
+    bytes: Vec<u8>,
+}
+impl_variant_bytes!(CompactPublicKey, cpk_bytes, "Compact public key has incorrect length");
+
+/// ExpandedSecretKey is seedsk || O_bytes || P1_bytes || L_bytes.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct ExpandedSecretKey {
+    #[zeroize(skip)]
+    params: MayoParams,
+    bytes: Vec<u8>,
+}
+impl_redacted_debug!(ExpandedSecretKey);
+impl_variant_bytes!(ExpandedSecretKey, esk_bytes, "Expanded secret key has incorrect length");
 
-/// ExpandedPublicKey contains the full public key components derived from SeedPK.
-/// This would include P1, P2, P3 (or parts of them, or their public representation).
-#[derive(Debug, Clone, PartialEq, Eq)] // Ensure no Copy
-pub struct ExpandedPublicKey(pub Vec<u8>);
+/// ExpandedPublicKey is P1_bytes || P2_bytes || P3_bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedPublicKey {
+    params: MayoParams,
+    bytes: Vec<u8>,
+}
+impl_variant_bytes!(ExpandedPublicKey, epk_bytes, "Expanded public key has incorrect length");
 
 /// Signature containing the solution `s` and the salt.
-#[wasm_bindgen(getter_with_clone)]
-#[derive(Debug, Clone, PartialEq, Eq)] // Removed Copy
-pub struct Signature(pub Vec<u8>); // Represents s_bytes || salt
-
-#[wasm_bindgen]
-impl Signature {
-    #[wasm_bindgen(constructor)]
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self(bytes)
-    }
-
-    pub fn get_bytes(&self) -> Vec<u8> {
-        self.0.clone()
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    params: MayoParams,
+    bytes: Vec<u8>,
 }
+impl_variant_bytes!(Signature, sig_bytes, "Signature has incorrect length");
 
-#[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone, PartialEq, Eq)] // Removed Copy
 pub struct Message(pub Vec<u8>);
 
-#[wasm_bindgen]
-impl Message {
-    #[wasm_bindgen(constructor)]
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self(bytes)
-    }
-
-    pub fn get_bytes(&self) -> Vec<u8> {
-        self.0.clone()
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MessageDigest(pub Vec<u8>);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Salt(pub Vec<u8>);
 
-// Implementations for converting to/from bytes for these types might be useful later.
-// e.g., impl From<Vec<u8>> for SeedSK ...
-// impl AsRef<[u8]> for SeedSK ...
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes_checks_variant_length() {
+        let mayo1 = MayoParams::mayo1();
+        let cpk = CompactPublicKey::from_bytes(mayo1, vec![0u8; mayo1.cpk_bytes()]).unwrap();
+        assert_eq!(cpk.params(), &mayo1);
+        assert_eq!(cpk.as_ref().len(), mayo1.cpk_bytes());
+
+        let mayo2 = MayoParams::mayo2();
+        assert_eq!(CompactPublicKey::from_bytes(mayo2, vec![0u8; mayo1.cpk_bytes()]),
+                   Err(IdentityError::InvalidLength("Compact public key has incorrect length")));
+        assert_eq!(Signature::from_bytes(mayo1, vec![0u8; mayo1.sig_bytes() + 1]),
+                   Err(IdentityError::InvalidLength("Signature has incorrect length")));
+    }
+
+    #[test]
+    fn test_secret_debug_is_redacted() {
+        let csk = CompactSecretKey::from_bytes(MayoParams::mayo1(), vec![0xAB; 24]).unwrap();
+        let debug = format!("{:?}", csk);
+        assert_eq!(debug, "CompactSecretKey { variant: \"mayo1\", bytes: [REDACTED; 24] }");
+        assert!(!format!("{:?}", SeedSK(vec![0xAB; 24])).contains("171"));
+
+        let cpk = CompactPublicKey::from_bytes(MayoParams::mayo1(), vec![0xAB; 1420]).unwrap();
+        assert!(format!("{:?}", cpk).contains("171"));
+    }
+
+    #[test]
+    fn test_secret_zeroize_and_into_bytes() {
+        let params = MayoParams::mayo2();
+        let mut esk = ExpandedSecretKey::from_bytes(params, vec![7u8; params.esk_bytes()]).unwrap();
+        esk.zeroize();
+        assert!(esk.as_bytes().iter().all(|&b| b == 0));
+
+        let csk = CompactSecretKey::from_bytes(MayoParams::mayo1(), vec![7u8; 24]).unwrap();
+        assert_eq!(csk.into_bytes(), vec![7u8; 24]);
+
+        let mut matrix = GFMatrix { data: vec![GFElement(9); 4], rows: 2, cols: 2 };
+        matrix.zeroize();
+        assert!(matrix.data.iter().all(|e| e.0 == 0));
+    }
+
+    #[test]
+    fn test_try_from_infers_variant() {
+        for params in MayoParams::all() {
+            let bytes = vec![0u8; params.cpk_bytes()];
+            assert_eq!(CompactPublicKey::try_from(bytes.as_slice()).unwrap().params(), &params);
+            let bytes = vec![0u8; params.sig_bytes()];
+            assert_eq!(Signature::try_from(bytes.as_slice()).unwrap().params(), &params);
+        }
+        // MAYO3 and MAYO5 seeds are unambiguous, MAYO1 and MAYO2 share 24-byte seeds.
+        assert_eq!(CompactSecretKey::try_from(&[0u8; 32][..]).unwrap().params(), &MayoParams::mayo3());
+        assert_eq!(CompactSecretKey::try_from(&[0u8; 24][..]), Err(IdentityError::AmbiguousVariant));
+        assert_eq!(CompactSecretKey::try_from(&[0u8; 7][..]), Err(IdentityError::InvalidLength("Compact secret key has incorrect length")));
+    }
+}
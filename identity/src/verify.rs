@@ -1,140 +1,172 @@
 //! Implements MAYO.Verify (Algorithm 9).
 
-use crate::types::{ExpandedPublicKey, Message, Signature, GFVector, Salt, GFMatrix}; // Removed MessageDigest, GFElement
+use crate::error::IdentityError;
+use crate::types::{CompactPublicKey, ExpandedPublicKey, Message, MessageDigest, Signature, GFVector, Salt, GFMatrix};
 use crate::params::{MayoParams, MayoVariantParams};
+use crate::keygen::expand_pk;
 use crate::hash::{shake256_digest, shake256_derive_target_t};
-use crate::codec::{decode_p1_matrices, decode_p2_matrices, decode_p3_matrices, decode_s_vector, decode_gf_elements};
-use crate::matrix::{matrix_symmetrize, matrix_vec_mul_transpose_gfvector, vector_dot_product};
-use crate::gf::gf16_add;
+use crate::codec::{decode_s_vector, decode_gf_elements};
+use crate::matrix::matrix_transpose;
+use crate::packed::{m_vec_add, m_vec_limbs, m_vec_mul_z, matrix_packed_mul, packed_matrix_mul, unpack_m_vector, whipped_pairs, PackedMatrix};
 
 /// Computes the public map P*(s) for MAYO verification.
 ///
+/// For every pair i <= j of whipped vectors (with j running from k-1 down to i),
+/// u = s_i^T P s_j + s_j^T P s_i (or s_i^T P s_i when i == j) is evaluated for all m
+/// public matrices and accumulated as E^l u, mirroring the system built by MAYO.Sign.
+///
+/// With S the k x n matrix whose rows are the s_i, split into S_V and S_O, all the
+/// s_i^T P_a s_j are the entries of the packed k x k product
+/// S P S^T = S_V (P1 S_V^T + P2 S_O^T) + S_O (P3 S_O^T), and the powers of E are
+/// applied by Horner's rule, walking the pairs from the last to the first.
+///
 /// # Arguments
-/// * `s_vector` - The solution vector from the signature (n elements).
-/// * `p1_matrices` - The set of m P1_i matrices from epk, each (n-o)x(n-o) upper triangular.
-/// * `p2_matrices` - The set of m P2_i matrices from epk, each (n-o)xo.
-/// * `p3_matrices` - The set of m P3_i matrices from epk, each oxo upper triangular.
+/// * `s_vectors` - The k solution vectors from the signature (n elements each).
+/// * `p1` - The m P1_i matrices from epk, (n-o)x(n-o) upper triangular, packed.
+/// * `p2` - The m P2_i matrices from epk, (n-o)xo, packed.
+/// * `p3` - The m P3_i matrices from epk, oxo upper triangular, packed.
 /// * `params` - MAYO variant parameters.
 ///
 /// # Returns
-/// `Ok(GFVector /* y_vector, m elements */)` or an error string.
+/// `Ok(GFVector /* y_vector, m elements */)` or an `IdentityError`.
 fn compute_p_star_s(
-    s_vector: &GFVector,
-    p1_matrices: &[GFMatrix],
-    p2_matrices: &[GFMatrix],
-    p3_matrices: &[GFMatrix],
+    s_vectors: &[GFVector],
+    p1: &PackedMatrix,
+    p2: &PackedMatrix,
+    p3: &PackedMatrix,
     params: &MayoVariantParams
-) -> Result<GFVector /* y_vector */, &'static str> {
-    if s_vector.len() != params.n {
-        return Err("Signature vector s has incorrect length");
-    }
-    if p1_matrices.len() != params.m || p2_matrices.len() != params.m || p3_matrices.len() != params.m {
-        return Err("Incorrect number of P matrices");
-    }
-
-    let num_vinegar_vars = params.n - params.o;
+) -> Result<GFVector /* y_vector */, IdentityError> {
+    let num_vinegar_vars = params.v();
     let num_oil_vars = params.o;
 
-    // Check consistency of s_vector length with n-o and o
-    if num_vinegar_vars + num_oil_vars != params.n {
-        return Err("Internal error: n-o + o != n");
+    if s_vectors.len() != params.k || s_vectors.iter().any(|s_i| s_i.len() != params.n) {
+        return Err(IdentityError::InvalidLength("Signature vector s has incorrect length"));
+    }
+    if p1.m() != params.m || p2.m() != params.m || p3.m() != params.m {
+        return Err(IdentityError::DimensionMismatch("Incorrect number of P matrices"));
+    }
+    if p1.num_rows() != num_vinegar_vars || p1.num_cols() != num_vinegar_vars {
+        return Err(IdentityError::DimensionMismatch("P1 matrix dimension mismatch"));
+    }
+    if p2.num_rows() != num_vinegar_vars || p2.num_cols() != num_oil_vars {
+        return Err(IdentityError::DimensionMismatch("P2 matrix dimension mismatch"));
+    }
+    if p3.num_rows() != num_oil_vars || p3.num_cols() != num_oil_vars {
+        return Err(IdentityError::DimensionMismatch("P3 matrix dimension mismatch"));
     }
 
-    let s_v = &s_vector[0..num_vinegar_vars];
-    let s_o = &s_vector[num_vinegar_vars..params.n];
-    
-    let s_v_gfvec = s_v.to_vec(); 
-    let s_o_gfvec = s_o.to_vec();
-
-    let mut y_elements: GFVector = Vec::with_capacity(params.m);
-
-    for i in 0..params.m {
-        let p1_i = &p1_matrices[i];
-        let p2_i = &p2_matrices[i];
-        let p3_i = &p3_matrices[i];
-
-        // Dimension checks for each matrix P_i^k
-        if p1_i.num_rows() != num_vinegar_vars || p1_i.num_cols() != num_vinegar_vars {
-            return Err("P1 matrix dimension mismatch");
-        }
-        if p2_i.num_rows() != num_vinegar_vars || p2_i.num_cols() != num_oil_vars {
-            return Err("P2 matrix dimension mismatch");
+    let s_v = GFMatrix::from_vectors(s_vectors.iter().map(|s_i| s_i[..num_vinegar_vars].to_vec()).collect());
+    let s_o = GFMatrix::from_vectors(s_vectors.iter().map(|s_i| s_i[num_vinegar_vars..].to_vec()).collect());
+    let (s_v_t, s_o_t) = (matrix_transpose(&s_v), matrix_transpose(&s_o));
+
+    let mut p_s_top = packed_matrix_mul(p1, &s_v_t)?;
+    p_s_top.add_assign(&packed_matrix_mul(p2, &s_o_t)?)?;
+    let p_s_bottom = packed_matrix_mul(p3, &s_o_t)?;
+    // s_p_s(i, j) holds s_i^T P_a s_j for every a.
+    let mut s_p_s = matrix_packed_mul(&s_v, &p_s_top)?;
+    s_p_s.add_assign(&matrix_packed_mul(&s_o, &p_s_bottom)?)?;
+
+    let mut y_acc = vec![0u64; m_vec_limbs(params.m)];
+    for &(i, j) in whipped_pairs(params.k).iter().rev() {
+        m_vec_mul_z(&mut y_acc, params.m, &params.f_tail);
+        m_vec_add(&mut y_acc, s_p_s.entry(i, j));
+        if i != j {
+            m_vec_add(&mut y_acc, s_p_s.entry(j, i));
         }
-        if p3_i.num_rows() != num_oil_vars || p3_i.num_cols() != num_oil_vars {
-            return Err("P3 matrix dimension mismatch");
-        }
-
-        // Symmetrize P1_i and P3_i (M + M^T, diagonal becomes 0)
-        let p1_i_sym = matrix_symmetrize(p1_i)?;
-        let p3_i_sym = matrix_symmetrize(p3_i)?;
-
-        // Term 1: s_V^T * P1_i_sym * s_V
-        let sv_p1_intermediate = matrix_vec_mul_transpose_gfvector(&s_v_gfvec, &p1_i_sym)?;
-        let term1 = vector_dot_product(&sv_p1_intermediate, &s_v_gfvec)?;
-
-        // Term 2: s_V^T * P2_i * s_O
-        let sv_p2_intermediate = matrix_vec_mul_transpose_gfvector(&s_v_gfvec, p2_i)?;
-        let term2 = vector_dot_product(&sv_p2_intermediate, &s_o_gfvec)?;
-
-        // Term 3: s_O^T * P3_i_sym * s_O
-        let so_p3_intermediate = matrix_vec_mul_transpose_gfvector(&s_o_gfvec, &p3_i_sym)?;
-        let term3 = vector_dot_product(&so_p3_intermediate, &s_o_gfvec)?;
-        
-        let y_i = gf16_add(gf16_add(term1, term2), term3);
-        y_elements.push(y_i);
     }
-    Ok(y_elements)
+    Ok(unpack_m_vector(&y_acc, params.m))
 }
 
 /// Implements MAYO.Verify (Algorithm 9 from the MAYO specification).
 /// Verifies a signature against a message and an expanded public key.
-pub fn verify_signature(epk: &ExpandedPublicKey, message: &Message, signature: &Signature, params_enum: &MayoParams) -> Result<bool, &'static str> {
-    let params = params_enum.variant();
+/// Fails if the signature belongs to a different MAYO variant than `epk`.
+pub fn verify_signature(epk: &ExpandedPublicKey, message: &Message, signature: &Signature) -> Result<bool, IdentityError> {
+    verify_digest(epk, &shake256_digest(&message.0, epk.params()), signature)
+}
 
-    // 1. Decode epk into P1, P2, P3 matrices
-    let p1_bytes_end = params.p1_bytes;
-    let p2_bytes_end = params.p1_bytes + params.p2_bytes;
+/// MAYO.Verify for a message already hashed to `m_digest` with `shake256_digest` or a
+/// `MessageHasher`.
+pub fn verify_digest(epk: &ExpandedPublicKey, m_digest: &MessageDigest, signature: &Signature) -> Result<bool, IdentityError> {
+    if signature.params() != epk.params() {
+        return Err(IdentityError::VariantMismatch);
+    }
+    VerifyingKey::from_expanded(epk)?.verify_digest(m_digest, signature)
+}
+
+/// An expanded public key decoded once into the packed P1, P2 and P3 matrices, so that
+/// checking many signatures under one key skips `expand_pk` and the decoding.
+#[derive(Debug, Clone)]
+pub struct VerifyingKey {
+    params: MayoParams,
+    p1: PackedMatrix,
+    p2: PackedMatrix,
+    p3: PackedMatrix,
+}
 
-    if epk.0.len() != params.p1_bytes + params.p2_bytes + params.p3_bytes {
-        return Err("Expanded public key has incorrect length");
+impl VerifyingKey {
+    /// Expands and decodes a compact public key.
+    pub fn new(cpk: &CompactPublicKey) -> Result<VerifyingKey, IdentityError> {
+        Self::from_expanded(&expand_pk(cpk)?)
     }
 
-    let p1_all_bytes = &epk.0[0..p1_bytes_end];
-    let p2_all_bytes = &epk.0[p1_bytes_end..p2_bytes_end];
-    let p3_all_bytes = &epk.0[p2_bytes_end..];
+    /// Decodes an expanded public key: epk = P1_bytes || P2_bytes || P3_bytes (lengths
+    /// are checked by the type).
+    pub fn from_expanded(epk: &ExpandedPublicKey) -> Result<Self, IdentityError> {
+        let params_enum = *epk.params();
+        let params = params_enum.variant();
+        let p1_bytes_end = params.p1_bytes;
+        let p2_bytes_end = params.p1_bytes + params.p2_bytes;
+
+        Ok(VerifyingKey {
+            params: params_enum,
+            p1: PackedMatrix::decode_upper_triangular(&epk.as_bytes()[..p1_bytes_end], params.v(), params.m)?,
+            p2: PackedMatrix::decode(&epk.as_bytes()[p1_bytes_end..p2_bytes_end], params.v(), params.o, params.m)?,
+            p3: PackedMatrix::decode_upper_triangular(&epk.as_bytes()[p2_bytes_end..], params.o, params.m)?,
+        })
+    }
 
-    let p1_matrices = decode_p1_matrices(p1_all_bytes, params)?;
-    let p2_matrices = decode_p2_matrices(p2_all_bytes, params)?;
-    let p3_matrices = decode_p3_matrices(p3_all_bytes, params)?;
+    pub fn params(&self) -> &MayoParams {
+        &self.params
+    }
 
-    // 2. Decode signature into salt and s_vector
-    let s_bytes_len = MayoParams::bytes_for_gf16_elements(params.n);
-    if signature.0.len() != s_bytes_len + params.salt_bytes {
-        return Err("Signature has incorrect length");
+    /// Verifies a detached signature on `message`; equivalent to `api::verify`.
+    pub fn verify(&self, message: &Message, signature: &Signature) -> Result<bool, IdentityError> {
+        self.verify_digest(&shake256_digest(&message.0, &self.params), signature)
     }
-    let s_bytes = &signature.0[0..s_bytes_len];
-    let salt_bytes_slice = &signature.0[s_bytes_len..];
-    
-    let s_vector = decode_s_vector(s_bytes, params)?;
-    let salt = Salt(salt_bytes_slice.to_vec());
 
-    // 3. Hash message M to M_digest
-    let m_digest = shake256_digest(&message.0, params_enum);
+    /// MAYO.Verify for a message already hashed to `m_digest`; see `verify_digest`.
+    pub fn verify_digest(&self, m_digest: &MessageDigest, signature: &Signature) -> Result<bool, IdentityError> {
+        let params_enum = &self.params;
+        let params = params_enum.variant();
+        if signature.params() != params_enum {
+            return Err(IdentityError::VariantMismatch);
+        }
+        if m_digest.0.len() != params.digest_bytes {
+            return Err(IdentityError::InvalidLength("Message digest has incorrect length"));
+        }
 
-    // 4. Derive target vector t
-    let t_bytes = shake256_derive_target_t(&m_digest, &salt, params_enum);
-    let t_vector = decode_gf_elements(&t_bytes, params.m)?;
+        // 1. epk was decoded into packed P1, P2, P3 matrices by `from_expanded`.
 
-    // 5. Compute y = P*(s)
-    let y_computed_vector = compute_p_star_s(&s_vector, &p1_matrices, &p2_matrices, &p3_matrices, params)?;
-    
-    if y_computed_vector.len() != params.m {
-        // This check should be redundant if compute_p_star_s is correct
-        return Err("Computed y vector has incorrect length");
-    }
+        // 2. Decode signature into salt and the k solution vectors s_i
+        let s_bytes = &signature.as_bytes()[0..params.s_bytes()];
+        let salt_bytes_slice = &signature.as_bytes()[params.s_bytes()..];
+
+        let s_vector = decode_s_vector(s_bytes, params)?;
+        let s_vectors: Vec<GFVector> = s_vector.chunks_exact(params.n).map(|s_i| s_i.to_vec()).collect();
+        let salt = Salt(salt_bytes_slice.to_vec());
+
+        // 3. M_digest = SHAKE256(M) is computed by the caller.
+
+        // 4. Derive target vector t
+        let t_bytes = shake256_derive_target_t(m_digest, &salt, params_enum);
+        let t_vector = decode_gf_elements(&t_bytes, params.m)?;
 
-    // 6. Compare computed y with target t
-    Ok(y_computed_vector == t_vector)
+        // 5. Compute y = P*(s)
+        let y_computed_vector = compute_p_star_s(&s_vectors, &self.p1, &self.p2, &self.p3, params)?;
+
+        // 6. Compare computed y with target t
+        Ok(y_computed_vector == t_vector)
+    }
 }
 
 
@@ -142,101 +174,159 @@ pub fn verify_signature(epk: &ExpandedPublicKey, message: &Message, signature: &
 mod tests {
     use super::*;
     use crate::params::MayoParams;
-    // GFElement removed from here as it's only used in create_dummy_signature for GFElement(0) which can be Self(0) or just 0 if type inference works.
-    // However, GFVector is Vec<GFElement>, so GFElement itself might still be needed if GFVector is constructed with GFElement explicitly.
-    // Let's check if the compiler complains after removing GFElement from the main import. It's used in dummy_s_vector.
-    use crate::types::{ExpandedPublicKey as EpkTypeForTest, Signature as SigTypeForTest, Message as MsgTypeForTest, GFElement}; // Re-added GFElement for test
-    use crate::keygen::{compact_key_gen, expand_pk}; 
-    use crate::codec::encode_s_vector; 
-
-    fn create_dummy_epk(params_enum: &MayoParams) -> EpkTypeForTest {
+    use crate::types::{CompactPublicKey, CompactSecretKey};
+    use crate::keygen::{compact_key_gen, expand_pk, expand_sk};
+    use crate::sign::sign_message;
+    use crate::codec::encode_s_vector;
+    use crate::types::GFElement;
+
+    fn create_epk(params_enum: &MayoParams) -> ExpandedPublicKey {
         let (_csk, cpk) = compact_key_gen(params_enum).unwrap();
-        expand_pk(&cpk, params_enum).unwrap()
+        expand_pk(&cpk).unwrap()
     }
 
-    fn create_dummy_signature(params_enum: &MayoParams) -> SigTypeForTest {
+    fn create_dummy_signature(params_enum: &MayoParams) -> Signature {
         let params = params_enum.variant();
-        let s_len = params.n;
-        let s_bytes_len = MayoParams::bytes_for_gf16_elements(s_len);
-        let salt_len = params.salt_bytes;
+        let dummy_s_vector: GFVector = vec![GFElement(0); params.n * params.k];
+        let mut sig_bytes = encode_s_vector(&dummy_s_vector, params);
+        sig_bytes.extend_from_slice(&vec![0u8; params.salt_bytes]);
+        Signature::from_bytes(*params_enum, sig_bytes).unwrap()
+    }
 
-        let dummy_s_vector: GFVector = vec![GFElement(0); s_len];
-        let s_bytes = encode_s_vector(&dummy_s_vector, params);
-        
-        let dummy_salt_bytes = vec![0u8; salt_len];
-        
-        let mut sig_bytes = Vec::with_capacity(s_bytes_len + salt_len);
-        sig_bytes.extend_from_slice(&s_bytes);
-        sig_bytes.extend_from_slice(&dummy_salt_bytes);
-        
-        SigTypeForTest(sig_bytes)
+    fn sign_with_fresh_key(params_enum: &MayoParams, message: &Message) -> (CompactSecretKey, CompactPublicKey, Signature) {
+        let (csk, cpk) = compact_key_gen(params_enum).unwrap();
+        let esk = expand_sk(&csk).unwrap();
+        let signature = sign_message(&esk, message).unwrap();
+        (csk, cpk, signature)
+    }
+
+    fn test_sign_verify_roundtrip_for_variant(params_enum: &MayoParams) {
+        let message = Message(b"cubix transaction".to_vec());
+        let (_csk, cpk, signature) = sign_with_fresh_key(params_enum, &message);
+        let epk = expand_pk(&cpk).unwrap();
+
+        assert_eq!(verify_signature(&epk, &message, &signature), Ok(true));
+
+        // A different message must not verify.
+        let other_message = Message(b"cubix transaction!".to_vec());
+        assert_eq!(verify_signature(&epk, &other_message, &signature), Ok(false));
+
+        // Flipping a nibble of s or a bit of the salt must not verify.
+        let mut tampered_s = signature.as_bytes().to_vec();
+        tampered_s[0] ^= 0x01;
+        let tampered_s = Signature::from_bytes(*params_enum, tampered_s).unwrap();
+        assert_eq!(verify_signature(&epk, &message, &tampered_s), Ok(false));
+        let mut tampered_salt = signature.as_bytes().to_vec();
+        *tampered_salt.last_mut().unwrap() ^= 0x80;
+        let tampered_salt = Signature::from_bytes(*params_enum, tampered_salt).unwrap();
+        assert_eq!(verify_signature(&epk, &message, &tampered_salt), Ok(false));
+
+        // Another key must not verify.
+        let other_epk = create_epk(params_enum);
+        assert_eq!(verify_signature(&other_epk, &message, &signature), Ok(false));
     }
 
     #[test]
-    fn test_verify_signature_flow_mayo1() {
+    fn test_sign_verify_roundtrip_mayo1() {
+        test_sign_verify_roundtrip_for_variant(&MayoParams::mayo1());
+    }
+
+    #[test]
+    fn test_sign_verify_roundtrip_mayo2() {
+        test_sign_verify_roundtrip_for_variant(&MayoParams::mayo2());
+    }
+
+    #[test]
+    fn test_sign_verify_roundtrip_mayo3() {
+        test_sign_verify_roundtrip_for_variant(&MayoParams::mayo3());
+    }
+
+    #[test]
+    fn test_sign_verify_roundtrip_mayo5() {
+        test_sign_verify_roundtrip_for_variant(&MayoParams::mayo5());
+    }
+
+    #[test]
+    fn test_verify_signature_dummy_mayo1() {
         let params_enum = MayoParams::mayo1();
-        let epk = create_dummy_epk(&params_enum);
-        let message = MsgTypeForTest(b"test message for verify".to_vec());
+        let epk = create_epk(&params_enum);
+        let message = Message(b"test message for verify".to_vec());
         let signature = create_dummy_signature(&params_enum);
 
-        let verify_result = verify_signature(&epk, &message, &signature, &params_enum);
-        
-        // With compute_p_star_s implemented, we expect Ok(false) for a dummy signature
-        // as it's highly unlikely to match the recomputed t_vector.
-        match verify_result {
-            Ok(false) => { /* Expected for a dummy signature not matching a real message hash */ }
-            Ok(true) => panic!("Verification unexpectedly succeeded with a dummy signature"),
-            Err(e) => panic!("Verification failed with an unexpected error: {}", e),
-        }
+        // An all-zero s evaluates to zero, which does not match the derived target.
+        assert_eq!(verify_signature(&epk, &message, &signature), Ok(false));
     }
 
     #[test]
-    fn test_verify_signature_flow_mayo2() {
+    fn test_verify_signature_dummy_mayo2() {
         let params_enum = MayoParams::mayo2();
-        let epk = create_dummy_epk(&params_enum);
-        let message = MsgTypeForTest(b"another test message for verify".to_vec());
+        let epk = create_epk(&params_enum);
+        let message = Message(b"another test message for verify".to_vec());
         let signature = create_dummy_signature(&params_enum);
 
-        let verify_result = verify_signature(&epk, &message, &signature, &params_enum);
-        match verify_result {
-            Ok(false) => { /* Expected for a dummy signature */ }
-            Ok(true) => panic!("Verification unexpectedly succeeded with a dummy signature"),
-            Err(e) => panic!("Verification failed with an unexpected error: {}", e),
-        }
+        assert_eq!(verify_signature(&epk, &message, &signature), Ok(false));
     }
 
     #[test]
     fn test_verify_signature_length_checks() {
         let params_enum = MayoParams::mayo1();
-        let epk = create_dummy_epk(&params_enum);
-        let message = MsgTypeForTest(b"test".to_vec());
+        let epk = create_epk(&params_enum);
+        let message = Message(b"test".to_vec());
         let valid_signature = create_dummy_signature(&params_enum);
 
-        let mut wrong_epk_bytes = epk.0.clone();
+        // Wrong lengths are rejected when the keys and signatures are built.
+        let mut wrong_epk_bytes = epk.as_bytes().to_vec();
         wrong_epk_bytes.pop();
-        let wrong_epk = EpkTypeForTest(wrong_epk_bytes);
-        assert_eq!(verify_signature(&wrong_epk, &message, &valid_signature, &params_enum), 
-                   Err("Expanded public key has incorrect length"));
+        assert_eq!(ExpandedPublicKey::from_bytes(params_enum, wrong_epk_bytes),
+                   Err(IdentityError::InvalidLength("Expanded public key has incorrect length")));
 
-        let mut wrong_sig_bytes = valid_signature.0.clone();
+        let mut wrong_sig_bytes = valid_signature.as_bytes().to_vec();
         wrong_sig_bytes.pop();
-        let wrong_sig = SigTypeForTest(wrong_sig_bytes);
-        assert_eq!(verify_signature(&epk, &message, &wrong_sig, &params_enum),
-                   Err("Signature has incorrect length"));
-    }
-    
-    // TODO: More detailed structural tests once compute_p_star_s is implemented.
-    // These tests would involve:
-    // 1. Mocking or providing a test implementation for compute_p_star_s.
-    // 2. Scenario 1: Test verification success:
-    //    - Have compute_p_star_s return a y_computed_vector that matches the t_vector derived in the test.
-    //    - Assert that verify_signature returns Ok(true).
-    // 3. Scenario 2: Test verification failure (y_computed mismatch):
-    //    - Have compute_p_star_s return a y_computed_vector that *does not* match the t_vector.
-    //    - Assert that verify_signature returns Ok(false).
-    // These tests verify the comparison logic in verify_signature.
-
-    // TODO: Implement Known Answer Tests (KATs) for verify_signature 
-    // once compute_p_star_s is fully implemented.
-    // These tests will use official MAYO test vectors.
+        assert_eq!(Signature::from_bytes(params_enum, wrong_sig_bytes),
+                   Err(IdentityError::InvalidLength("Signature has incorrect length")));
+
+        assert_eq!(verify_signature(&epk, &message, &valid_signature), Ok(false));
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_other_variant() {
+        let epk = create_epk(&MayoParams::mayo2());
+        let message = Message(b"test".to_vec());
+        let mayo1_signature = create_dummy_signature(&MayoParams::mayo1());
+        assert_eq!(verify_signature(&epk, &message, &mayo1_signature),
+                   Err(IdentityError::VariantMismatch));
+    }
+
+    #[test]
+    fn test_verifying_key_reuses_decoded_matrices() {
+        let params_enum = MayoParams::mayo2();
+        let (csk, cpk) = compact_key_gen(&params_enum).unwrap();
+        let esk = expand_sk(&csk).unwrap();
+        let verifying_key = VerifyingKey::new(&cpk).unwrap();
+        assert_eq!(verifying_key.params(), &params_enum);
+        
+        for text in [&b"first"[..], b"second"] {
+            let message = Message(text.to_vec());
+            let signature = sign_message(&esk, &message).unwrap();
+            assert!(verifying_key.verify(&message, &signature).unwrap());
+            assert!(!verifying_key.verify(&Message(b"other".to_vec()), &signature).unwrap());
+        }
+
+        let mayo1_signature = create_dummy_signature(&MayoParams::mayo1());
+        assert_eq!(verifying_key.verify(&Message(b"test".to_vec()), &mayo1_signature),
+                   Err(IdentityError::VariantMismatch));
+    }
+
+    #[test]
+    fn test_compute_p_star_s_rejects_wrong_vector_count() {
+        let params_enum = MayoParams::mayo2();
+        let params = params_enum.variant();
+        let epk = create_epk(&params_enum);
+        let p1 = PackedMatrix::decode_upper_triangular(&epk.as_bytes()[..params.p1_bytes], params.v(), params.m).unwrap();
+        let p2 = PackedMatrix::decode(&epk.as_bytes()[params.p1_bytes..params.p1_bytes + params.p2_bytes], params.v(), params.o, params.m).unwrap();
+        let p3 = PackedMatrix::decode_upper_triangular(&epk.as_bytes()[params.p1_bytes + params.p2_bytes..], params.o, params.m).unwrap();
+        let s_vectors = vec![vec![GFElement(1); params.n]; params.k - 1];
+        assert_eq!(compute_p_star_s(&s_vectors, &p1, &p2, &p3, params),
+                   Err(IdentityError::InvalidLength("Signature vector s has incorrect length")));
+    }
 }
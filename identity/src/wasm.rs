@@ -0,0 +1,396 @@
+//! JS bindings, compiled with the `wasm` feature.
+//!
+//! The rest of the crate is plain Rust. This module wraps each type that crosses the JS
+//! boundary in a thin `#[wasm_bindgen]` class of the same name, and exports the functions
+//! of `api`, `address`, `spacetime_hash` and `keystore` over those classes. JS names are
+//! camelCase, variant names are typed as the `Variant` union, and wasm-bindgen generates
+//! the TypeScript declarations shipped by the npm package in `identity/npm`. Errors are
+//! thrown as the JS `Error` described in `error`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::address;
+use crate::api;
+use crate::derive::MasterSeed;
+use crate::encoding::Envelope;
+use crate::error::IdentityError;
+use crate::keystore;
+use crate::params::MayoParams;
+use crate::spacetime_hash;
+use crate::types;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_VARIANT: &str = r#"
+/** A MAYO parameter set. */
+export type Variant = "mayo1" | "mayo2" | "mayo3" | "mayo5";
+"#;
+
+/// A wasm class around a key or signature type, with the byte, variant and
+/// serialization accessors JS needs.
+macro_rules! wasm_bytes_type {
+    ($ty:ident) => {
+        #[wasm_bindgen]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $ty(types::$ty);
+
+        impl From<types::$ty> for $ty {
+            fn from(inner: types::$ty) -> Self {
+                $ty(inner)
+            }
+        }
+
+        impl From<$ty> for types::$ty {
+            fn from(wrapper: $ty) -> Self {
+                wrapper.0
+            }
+        }
+
+        impl AsRef<types::$ty> for $ty {
+            fn as_ref(&self) -> &types::$ty {
+                &self.0
+            }
+        }
+
+        #[wasm_bindgen]
+        impl $ty {
+            /// Builds a value of the given variant from its raw bytes.
+            #[wasm_bindgen(constructor)]
+            pub fn new(#[wasm_bindgen(unchecked_param_type = "Variant")] variant: &str, bytes: Vec<u8>) -> Result<$ty, IdentityError> {
+                types::$ty::from_bytes(MayoParams::get_params_by_name(variant)?, bytes).map($ty)
+            }
+
+            /// The raw bytes, without the envelope header.
+            #[wasm_bindgen(js_name = toBytes)]
+            pub fn to_bytes(&self) -> Vec<u8> {
+                self.0.as_bytes().to_vec()
+            }
+
+            #[wasm_bindgen(getter, unchecked_return_type = "Variant")]
+            pub fn variant(&self) -> String {
+                self.0.params().name().to_string()
+            }
+
+            #[wasm_bindgen(js_name = toEnvelope)]
+            pub fn to_envelope(&self) -> Vec<u8> {
+                <[u8]>::to_vec(self.0.to_envelope().as_ref())
+            }
+
+            #[wasm_bindgen(js_name = fromEnvelope)]
+            pub fn from_envelope(bytes: &[u8]) -> Result<$ty, IdentityError> {
+                types::$ty::from_envelope(bytes).map($ty)
+            }
+
+            #[wasm_bindgen(js_name = toHex)]
+            pub fn to_hex(&self) -> String {
+                str::to_owned(self.0.to_hex().as_ref())
+            }
+
+            #[wasm_bindgen(js_name = fromHex)]
+            pub fn from_hex(s: &str) -> Result<$ty, IdentityError> {
+                types::$ty::from_hex(s).map($ty)
+            }
+
+            #[wasm_bindgen(js_name = toBase64)]
+            pub fn to_base64(&self) -> String {
+                str::to_owned(self.0.to_base64().as_ref())
+            }
+
+            #[wasm_bindgen(js_name = fromBase64)]
+            pub fn from_base64(s: &str) -> Result<$ty, IdentityError> {
+                types::$ty::from_base64(s).map($ty)
+            }
+
+            #[wasm_bindgen(js_name = toPem)]
+            pub fn to_pem(&self) -> String {
+                str::to_owned(self.0.to_pem().as_ref())
+            }
+
+            #[wasm_bindgen(js_name = fromPem)]
+            pub fn from_pem(pem: &str) -> Result<$ty, IdentityError> {
+                types::$ty::from_pem(pem).map($ty)
+            }
+        }
+    };
+}
+
+wasm_bytes_type!(CompactSecretKey);
+wasm_bytes_type!(CompactPublicKey);
+wasm_bytes_type!(Signature);
+
+#[wasm_bindgen]
+impl CompactPublicKey {
+    /// The Cubix address of the key, as a string.
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> String {
+        self.0.address()
+    }
+
+    /// The fingerprint of the key, in hex.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.0.fingerprint()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message(types::Message);
+
+impl From<types::Message> for Message {
+    fn from(inner: types::Message) -> Self {
+        Message(inner)
+    }
+}
+
+#[wasm_bindgen]
+impl Message {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Message(types::Message(bytes))
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.0.clone()
+    }
+}
+
+/// A compact key pair. Each getter returns a fresh copy of the key.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct KeyPair {
+    secret_key: CompactSecretKey,
+    public_key: CompactPublicKey,
+}
+
+impl From<(types::CompactSecretKey, types::CompactPublicKey)> for KeyPair {
+    fn from((secret_key, public_key): (types::CompactSecretKey, types::CompactPublicKey)) -> Self {
+        KeyPair { secret_key: secret_key.into(), public_key: public_key.into() }
+    }
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    #[wasm_bindgen(getter, js_name = secretKey)]
+    pub fn secret_key(&self) -> CompactSecretKey {
+        self.secret_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> CompactPublicKey {
+        self.public_key.clone()
+    }
+
+    #[wasm_bindgen(getter, unchecked_return_type = "Variant")]
+    pub fn variant(&self) -> String {
+        self.public_key.variant()
+    }
+
+    /// The Cubix address of the public key.
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> String {
+        self.public_key.address()
+    }
+}
+
+/// See `api::BatchVerification`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchVerification(api::BatchVerification);
+
+#[wasm_bindgen]
+impl BatchVerification {
+    #[wasm_bindgen(getter)]
+    pub fn valid(&self) -> bool {
+        self.0.valid()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn failed(&self) -> Vec<u32> {
+        self.0.failed().iter().map(|&index| index as u32).collect()
+    }
+}
+
+/// See `sign::SigningKey`.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct SigningKey(crate::sign::SigningKey);
+
+#[wasm_bindgen]
+impl SigningKey {
+    #[wasm_bindgen(constructor)]
+    pub fn new(secret_key: &CompactSecretKey) -> Result<SigningKey, IdentityError> {
+        crate::sign::SigningKey::new(&secret_key.0).map(SigningKey)
+    }
+
+    pub fn sign(&self, message: &Message) -> Result<Signature, IdentityError> {
+        self.0.sign(&message.0).map(Signature)
+    }
+
+    #[wasm_bindgen(getter, unchecked_return_type = "Variant")]
+    pub fn variant(&self) -> String {
+        self.0.params().name().to_string()
+    }
+}
+
+/// See `verify::VerifyingKey`.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct VerifyingKey(crate::verify::VerifyingKey);
+
+#[wasm_bindgen]
+impl VerifyingKey {
+    #[wasm_bindgen(constructor)]
+    pub fn new(public_key: &CompactPublicKey) -> Result<VerifyingKey, IdentityError> {
+        crate::verify::VerifyingKey::new(&public_key.0).map(VerifyingKey)
+    }
+
+    pub fn verify(&self, message: &Message, signature: &Signature) -> Result<bool, IdentityError> {
+        self.0.verify(&message.0, &signature.0)
+    }
+
+    #[wasm_bindgen(getter, unchecked_return_type = "Variant")]
+    pub fn variant(&self) -> String {
+        self.0.params().name().to_string()
+    }
+}
+
+/// See `api::keypair`.
+#[wasm_bindgen]
+pub fn keypair(#[wasm_bindgen(unchecked_param_type = "Variant")] variant: String) -> Result<KeyPair, IdentityError> {
+    api::keypair(&MayoParams::get_params_by_name(&variant)?).map(KeyPair::from)
+}
+
+/// See `MasterSeed::generate_mnemonic`.
+#[wasm_bindgen(js_name = generateMnemonic)]
+pub fn generate_mnemonic(word_count: usize) -> Result<String, IdentityError> {
+    MasterSeed::generate_mnemonic(word_count).map(|phrase| phrase.as_str().to_owned())
+}
+
+/// See `api::keypair_from_mnemonic`.
+#[wasm_bindgen(js_name = keypairFromMnemonic)]
+pub fn keypair_from_mnemonic(
+    #[wasm_bindgen(unchecked_param_type = "Variant")] variant: String,
+    mnemonic: String,
+    passphrase: String,
+    path: String,
+) -> Result<KeyPair, IdentityError> {
+    api::keypair_from_mnemonic(&MayoParams::get_params_by_name(&variant)?, &mnemonic, &passphrase, &path).map(KeyPair::from)
+}
+
+/// See `api::keypair_from_seed`.
+#[wasm_bindgen(js_name = keypairFromSeed)]
+pub fn keypair_from_seed(#[wasm_bindgen(unchecked_param_type = "Variant")] variant: String, seed: &[u8], path: String) -> Result<KeyPair, IdentityError> {
+    api::keypair_from_seed(&MayoParams::get_params_by_name(&variant)?, seed, &path).map(KeyPair::from)
+}
+
+/// See `api::sign_detached`.
+#[wasm_bindgen(js_name = signDetached)]
+pub fn sign_detached(secret_key: &CompactSecretKey, message: &Message) -> Result<Signature, IdentityError> {
+    api::sign_detached(&secret_key.0, &message.0).map(Signature)
+}
+
+/// See `api::sign`.
+#[wasm_bindgen]
+pub fn sign(secret_key: &CompactSecretKey, message: &Message) -> Result<Vec<u8>, IdentityError> {
+    api::sign(&secret_key.0, &message.0)
+}
+
+/// See `api::verify`.
+#[wasm_bindgen]
+pub fn verify(public_key: &CompactPublicKey, message: &Message, signature: &Signature) -> Result<bool, IdentityError> {
+    api::verify(&public_key.0, &message.0, &signature.0)
+}
+
+/// See `api::open`.
+#[wasm_bindgen]
+pub fn open(public_key: &CompactPublicKey, signed_message: &[u8]) -> Result<Option<Message>, IdentityError> {
+    Ok(api::open(&public_key.0, signed_message)?.map(Message))
+}
+
+/// See `api::verify_batch`.
+#[wasm_bindgen(js_name = verifyBatch)]
+pub fn verify_batch(public_keys: Vec<CompactPublicKey>, messages: Vec<Message>, signatures: Vec<Signature>) -> Result<BatchVerification, IdentityError> {
+    let public_keys: Vec<types::CompactPublicKey> = public_keys.into_iter().map(|key| key.0).collect();
+    let messages: Vec<types::Message> = messages.into_iter().map(|message| message.0).collect();
+    let signatures: Vec<types::Signature> = signatures.into_iter().map(|signature| signature.0).collect();
+    api::verify_batch(&public_keys, &messages, &signatures).map(BatchVerification)
+}
+
+/// See `address::is_valid_address`.
+#[wasm_bindgen(js_name = isValidAddress)]
+pub fn is_valid_address(s: &str) -> bool {
+    address::is_valid_address(s)
+}
+
+/// See `spacetime_hash::hash_public_key`.
+#[wasm_bindgen(js_name = hashPublicKey)]
+pub fn hash_public_key(public_key: &CompactPublicKey) -> Vec<u8> {
+    spacetime_hash::hash_public_key(&public_key.0)
+}
+
+/// See `spacetime_hash::hash_message`.
+#[wasm_bindgen(js_name = hashMessage)]
+pub fn hash_message(domain: &str, message: &[u8]) -> Vec<u8> {
+    spacetime_hash::hash_message(domain, message)
+}
+
+/// See `spacetime_hash::hash_public_key_at`.
+#[wasm_bindgen(js_name = hashPublicKeyAt)]
+pub fn hash_public_key_at(public_key: &CompactPublicKey, timestamp: u64) -> Vec<u8> {
+    spacetime_hash::hash_public_key_at(&public_key.0, timestamp)
+}
+
+/// See `spacetime_hash::hash_compact_secret_key`.
+#[wasm_bindgen(js_name = hashCompactSecretKey)]
+pub fn hash_compact_secret_key(secret_key: &CompactSecretKey) -> Vec<u8> {
+    spacetime_hash::hash_compact_secret_key(&secret_key.0)
+}
+
+/// See `keystore::create_keystore`.
+#[wasm_bindgen(js_name = createKeystore)]
+pub fn create_keystore(secret_key: &CompactSecretKey, password: &str) -> Result<String, IdentityError> {
+    keystore::create_keystore(&secret_key.0, password)
+}
+
+/// See `keystore::unlock_keystore`.
+#[wasm_bindgen(js_name = unlockKeystore)]
+pub fn unlock_keystore(json: &str, password: &str) -> Result<CompactSecretKey, IdentityError> {
+    keystore::unlock_keystore(json, password).map(CompactSecretKey)
+}
+
+/// See `keystore::change_keystore_password`.
+#[wasm_bindgen(js_name = changeKeystorePassword)]
+pub fn change_keystore_password(json: &str, old_password: &str, new_password: &str) -> Result<String, IdentityError> {
+    keystore::change_keystore_password(json, old_password, new_password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrappers_round_trip_through_the_core_api() {
+        let pair = keypair("mayo1".to_string()).unwrap();
+        let (sk, pk) = (pair.secret_key(), pair.public_key());
+        assert_eq!(pair.variant(), "mayo1");
+        assert_eq!(pair.address(), pk.address());
+        assert!(is_valid_address(&pk.address()));
+
+        let message = Message::new(b"wasm face".to_vec());
+        let signature = SigningKey::new(&sk).unwrap().sign(&message).unwrap();
+        assert!(verify(&pk, &message, &signature).unwrap());
+        assert!(VerifyingKey::new(&pk).unwrap().verify(&message, &signature).unwrap());
+        assert_eq!(open(&pk, &sign(&sk, &message).unwrap()).unwrap(), Some(message.clone()));
+
+        assert_eq!(Signature::from_pem(&signature.to_pem()).unwrap(), signature);
+        assert_eq!(CompactSecretKey::from_hex(&sk.to_hex()).unwrap(), sk);
+        assert_eq!(CompactPublicKey::from_base64(&pk.to_base64()).unwrap(), pk);
+        assert_eq!(CompactPublicKey::new("mayo1", pk.to_bytes()).unwrap(), pk);
+
+        let report = verify_batch(vec![pk.clone(), pk], vec![message.clone(), Message::new(vec![])], vec![signature.clone(), signature]).unwrap();
+        assert!(!report.valid());
+        assert_eq!(report.failed(), vec![1]);
+        assert_eq!(Signature::new("mayo9", vec![]), Err(IdentityError::UnknownVariant));
+    }
+}